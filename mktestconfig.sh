@@ -1,3 +1,3 @@
 echo "{\"owners\": [\"test\"],\"nickname\": \"test\",\"username\": \"test\",\"realname\": \"test\",\"password\": \"\",\"server\": \"irc.fyrechat.net\",\"port\": 6667,\"encoding\": \"UTF-8\",\"channels\": [\"#test\", \"#test2\"],\"options\": {}}" > config.json
 mkdir users
-echo "{\"username\":\"login\",\"password\":\"ee26b0dd4af7e749aa1a8ee3c10ae9923f618980772e473f8819a5d4940e0db27ac185f8a0e1d5f84f88bc887fd67b143732c304cc5fa9ad8e6f57f50028a8ff\",\"stats\":{\"health\":20,\"movement\":30,\"strength\":12,\"dexterity\":12,\"constitution\":12,\"wisdom\":12,\"intellect\":12,\"charisma\":12},\"feats\":[],\"temp_stats\":null,\"position\":{\"_field0\":0,\"_field1\":0}}" > users/login.json
+echo "{\"username\":\"login\",\"password\":\"ee26b0dd4af7e749aa1a8ee3c10ae9923f618980772e473f8819a5d4940e0db27ac185f8a0e1d5f84f88bc887fd67b143732c304cc5fa9ad8e6f57f50028a8ff\",\"stats\":{\"health\":20,\"movement\":30,\"strength\":12,\"dexterity\":12,\"constitution\":12,\"wisdom\":12,\"intellect\":12,\"charisma\":12},\"feats\":[],\"temp_stats\":null,\"position\":{\"_field0\":0,\"_field1\":0},\"defenses\":{\"resistances\":[],\"immunities\":[],\"vulnerabilities\":[]},\"saves\":[],\"level\":1,\"skills\":[],\"class\":null,\"xp\":0,\"max_health\":20,\"race\":null,\"inventory\":{\"items\":[]},\"coins\":{\"cp\":0,\"sp\":0,\"ep\":0,\"gp\":0,\"pp\":0},\"spells\":[],\"slots_used\":[],\"concentration\":null,\"hit_dice_used\":0,\"modifiers\":[],\"rules\":null,\"extra_stats\":[],\"macros\":[]}" > users/login.json
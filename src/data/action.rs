@@ -0,0 +1,186 @@
+use std::ascii::AsciiExt;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::BotResult;
+use data::BotError::InvalidInput;
+use data::damage::DamageType;
+use data::dice::{Dice, record, roll_die};
+
+#[derive(RustcDecodable, RustcEncodable, Debug, PartialEq, Clone)]
+pub enum ActionKind {
+    Attack { bonus: i32, reach: u8, damage: Dice, damage_type: DamageType },
+    Multiattack(Vec<String>),
+}
+
+#[derive(RustcDecodable, RustcEncodable, Debug, PartialEq, Clone)]
+pub struct Action {
+    pub name: String,
+    pub kind: ActionKind,
+    pub recharge: Option<u8>,
+    pub charged: bool,
+    pub uses: Option<u8>,
+    pub used: u8,
+}
+
+impl Action {
+    pub fn attack(name: &str, bonus: i32, reach: u8, damage: Dice, damage_type: DamageType) -> Action {
+        Action {
+            name: name.to_string(),
+            kind: ActionKind::Attack {
+                bonus: bonus, reach: reach, damage: damage, damage_type: damage_type
+            },
+            recharge: None,
+            charged: true,
+            uses: None,
+            used: 0,
+        }
+    }
+
+    pub fn multiattack(name: &str, actions: Vec<String>) -> Action {
+        Action {
+            name: name.to_string(),
+            kind: ActionKind::Multiattack(actions),
+            recharge: None,
+            charged: true,
+            uses: None,
+            used: 0,
+        }
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    pub fn is_multiattack(&self) -> bool {
+        match self.kind {
+            ActionKind::Multiattack(_) => true,
+            _ => false,
+        }
+    }
+
+    // Checks that the action is available and marks it as spent. Recharge actions that have been
+    // spent get a d6 roll to see if they're back, as if the monster rolled at the start of its turn.
+    pub fn spend(&mut self) -> BotResult<()> {
+        if let Some(uses) = self.uses {
+            if self.used >= uses {
+                return Err(InvalidInput(format!("{} has no uses left today.", self.name)));
+            }
+        }
+        if let Some(recharge) = self.recharge {
+            if !self.charged {
+                let roll = roll_die(6);
+                record(&format!("d6 (recharge {})", self.name), None, 0, roll);
+                if roll < recharge as i32 {
+                    return Err(InvalidInput(
+                        format!("{} has not recharged (rolled {}).", self.name, roll)
+                    ));
+                }
+            }
+            self.charged = false;
+        }
+        if self.uses.is_some() {
+            self.used += 1;
+        }
+        Ok(())
+    }
+
+    // Recharges the action after any rest, and gives back its daily uses after a long one. Returns
+    // whether there was anything to restore.
+    pub fn rest(&mut self, long: bool) -> bool {
+        let restored = !self.charged || (long && self.used > 0);
+        self.charged = true;
+        if long {
+            self.used = 0;
+        }
+        restored
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        try!(match self.kind {
+            ActionKind::Attack { bonus, reach, damage, damage_type } => {
+                let sign = if bonus < 0 { "" } else { "+" };
+                write!(fmt, "{} ({}{}, {} ft., {} {}", self.name, sign, bonus, reach, damage,
+                       damage_type)
+            },
+            ActionKind::Multiattack(ref actions) => {
+                write!(fmt, "{} ({}", self.name, actions.join(", "))
+            },
+        });
+        if let Some(recharge) = self.recharge {
+            try!(write!(fmt, ", recharge {}-6", recharge));
+        }
+        if let Some(uses) = self.uses {
+            try!(write!(fmt, ", {}/{} per day", uses - self.used, uses));
+        }
+        write!(fmt, ")")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Action;
+    use data::damage::DamageType::{Fire, Piercing};
+    use data::BotError::InvalidInput;
+    use data::dice::{Dice, script};
+
+    #[test]
+    fn is_named() {
+        let a = Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing);
+        assert!(a.is_named("bite"));
+        assert!(a.is_named("BITE"));
+        assert!(!a.is_named("claw"));
+    }
+
+    #[test]
+    fn spend_uses() {
+        let mut a = Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing);
+        a.uses = Some(2);
+        assert!(a.spend().is_ok());
+        assert!(a.spend().is_ok());
+        assert!(a.spend().is_err());
+        assert_eq!(a.used, 2);
+    }
+
+    #[test]
+    fn spend_recharge() {
+        let mut a = Action::attack("Breath", 4, 15, Dice::new(4, 6, 0), Fire);
+        a.recharge = Some(5);
+        script(vec![4, 5]);
+        assert!(a.spend().is_ok());
+        assert!(!a.charged);
+        assert_eq!(a.spend(), Err(InvalidInput(format!("Breath has not recharged (rolled 4)."))));
+        assert!(!a.charged);
+        assert!(a.spend().is_ok());
+        assert!(!a.charged);
+    }
+
+    #[test]
+    fn rest() {
+        let mut a = Action::attack("Breath", 4, 15, Dice::new(4, 6, 0), Fire);
+        a.recharge = Some(5);
+        a.uses = Some(1);
+        a.charged = false;
+        a.used = 1;
+        assert!(a.rest(false));
+        assert!(a.charged);
+        assert_eq!(a.used, 1);
+        assert!(a.rest(true));
+        assert_eq!(a.used, 0);
+        assert!(!a.rest(true));
+    }
+
+    #[test]
+    fn display() {
+        let mut a = Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing);
+        assert_eq!(format!("{}", a), format!("Bite (+4, 5 ft., 1d6+2 piercing)"));
+        a.recharge = Some(5);
+        a.uses = Some(3);
+        a.used = 1;
+        assert_eq!(format!("{}", a), format!("Bite (+4, 5 ft., 1d6+2 piercing, recharge 5-6, 2/3 per day)"));
+        let m = Action::multiattack("Multiattack", vec!["Bite".to_string(), "Claw".to_string()]);
+        assert_eq!(format!("{}", m), format!("Multiattack (Bite, Claw)"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::ascii::AsciiExt;
+use data::BotResult;
+use data::BotError::InvalidInput;
+use data::class::CharacterClass;
+use data::dice::{record, roll_die};
+use data::race::RaceInfo;
+use self::Method::{PointBuy, StandardArray, Rolled};
+use self::Step::{ChooseMethod, ChooseScores, ChooseRace, ChooseClass, ChooseName};
+
+pub static STANDARD_ARRAY: [u8; 6] = [15, 14, 13, 12, 10, 8];
+pub static POINT_BUY_BUDGET: u8 = 27;
+
+pub fn point_buy_cost(score: u8) -> Option<u8> {
+    match score {
+        8...13 => Some(score - 8),
+        14 => Some(7),
+        15 => Some(9),
+        _ => None,
+    }
+}
+
+// Rolls 4d6 and drops the lowest die, returning the total and the dice rolled.
+pub fn roll_4d6_drop_lowest() -> (u8, Vec<u8>) {
+    let dice: Vec<u8> = (0..4).map(|_| roll_die(6) as u8).collect();
+    let lowest = *dice.iter().min().unwrap();
+    let total = dice.iter().fold(0, |acc, d| acc + d) - lowest;
+    record("4d6 (drop lowest)", None, 0, total as i32);
+    (total, dice)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    PointBuy,
+    StandardArray,
+    Rolled,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    ChooseMethod,
+    ChooseScores,
+    ChooseRace,
+    ChooseClass,
+    ChooseName,
+}
+
+// A character part-way through creation. Each step is filled in by a separate message, and the
+// player is only created once every step is done.
+#[derive(Clone, Debug)]
+pub struct CharacterBuilder {
+    pub chan: String,
+    pub method: Option<Method>,
+    pub rolls: Vec<u8>,
+    pub scores: Option<Vec<u8>>,
+    pub race: Option<&'static RaceInfo>,
+    pub class: Option<CharacterClass>,
+}
+
+impl CharacterBuilder {
+    pub fn new(chan: &str) -> CharacterBuilder {
+        CharacterBuilder {
+            chan: chan.to_string(),
+            method: None,
+            rolls: Vec::new(),
+            scores: None,
+            race: None,
+            class: None,
+        }
+    }
+
+    pub fn step(&self) -> Step {
+        if self.method.is_none() {
+            ChooseMethod
+        } else if self.scores.is_none() {
+            ChooseScores
+        } else if self.race.is_none() {
+            ChooseRace
+        } else if self.class.is_none() {
+            ChooseClass
+        } else {
+            ChooseName
+        }
+    }
+
+    pub fn prompt(&self) -> String {
+        match self.step() {
+            ChooseMethod => format!("Choose how to generate ability scores: pointbuy, standard or roll."),
+            ChooseScores => match self.method.unwrap() {
+                PointBuy => format!("Give six scores from 8 to 15 (str dex con wis int cha) costing at most {} points.", POINT_BUY_BUDGET),
+                StandardArray => format!("Assign 15 14 13 12 10 8 to str dex con wis int cha, in that order."),
+                Rolled => {
+                    let rolls: Vec<_> = self.rolls.iter().map(|r| format!("{}", r)).collect();
+                    format!("Assign your rolls ({}) to str dex con wis int cha, in that order.", rolls.join(" "))
+                },
+            },
+            ChooseRace => format!("Choose a race and, if it has them, a subrace (e.g. elf wood)."),
+            ChooseClass => format!("Choose a class (e.g. fighter)."),
+            ChooseName => format!("Choose a character name and password."),
+        }
+    }
+
+    pub fn set_method(&mut self, method: &str) -> BotResult<()> {
+        self.method = Some(match &method.to_ascii_lowercase()[..] {
+            "pointbuy" | "point-buy" => PointBuy,
+            "standard" | "array" => StandardArray,
+            "roll" | "4d6" => Rolled,
+            _ => return Err(InvalidInput(format!("{} is not a valid method.", method))),
+        });
+        Ok(())
+    }
+
+    // Checks the scores against the chosen method. Standard and rolled scores must use each value
+    // exactly once, in whatever order the player likes.
+    pub fn set_scores(&mut self, scores: Vec<u8>) -> BotResult<()> {
+        if scores.len() != 6 {
+            return Err(InvalidInput(format!("Six scores are required (str dex con wis int cha).")));
+        }
+        match self.method {
+            Some(PointBuy) => {
+                let mut total = 0;
+                for score in scores.iter() {
+                    total += match point_buy_cost(*score) {
+                        Some(cost) => cost,
+                        None => return Err(InvalidInput(format!("Point-buy scores must be from 8 to 15."))),
+                    };
+                }
+                if total > POINT_BUY_BUDGET {
+                    return Err(InvalidInput(
+                        format!("Those scores cost {} points, but the budget is {}.", total, POINT_BUY_BUDGET)
+                    ));
+                }
+            },
+            Some(StandardArray) => if !is_permutation(&scores, &STANDARD_ARRAY) {
+                return Err(InvalidInput(format!("Scores must use 15 14 13 12 10 8 once each.")));
+            },
+            Some(Rolled) => if !is_permutation(&scores, &self.rolls) {
+                return Err(InvalidInput(format!("Scores must use each of your rolls once.")));
+            },
+            None => return Err(InvalidInput(format!("Choose a method first."))),
+        }
+        self.scores = Some(scores);
+        Ok(())
+    }
+
+    // Rolls the six scores for the rolled method, returning the dice so they can be logged.
+    pub fn roll_scores(&mut self) -> Vec<(u8, Vec<u8>)> {
+        let rolls: Vec<_> = (0..6).map(|_| roll_4d6_drop_lowest()).collect();
+        self.rolls = rolls.iter().map(|&(total, _)| total).collect();
+        rolls
+    }
+}
+
+fn is_permutation(scores: &[u8], values: &[u8]) -> bool {
+    let mut a = scores.to_vec();
+    let mut b = values.to_vec();
+    a.sort();
+    b.sort();
+    a == b
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CharacterBuilder, point_buy_cost, roll_4d6_drop_lowest};
+    use super::Step::{ChooseMethod, ChooseScores, ChooseRace};
+    use data::BotError::InvalidInput;
+
+    #[test]
+    fn point_buy_costs() {
+        assert_eq!(point_buy_cost(8), Some(0));
+        assert_eq!(point_buy_cost(13), Some(5));
+        assert_eq!(point_buy_cost(15), Some(9));
+        assert_eq!(point_buy_cost(16), None);
+    }
+
+    #[test]
+    fn roll_4d6() {
+        let (total, dice) = roll_4d6_drop_lowest();
+        assert_eq!(dice.len(), 4);
+        assert!(total >= 3 && total <= 18);
+    }
+
+    #[test]
+    fn point_buy_budget() {
+        let mut b = CharacterBuilder::new("#test");
+        assert_eq!(b.step(), ChooseMethod);
+        b.set_method("pointbuy").unwrap();
+        assert_eq!(b.step(), ChooseScores);
+        assert_eq!(b.set_scores(vec![15, 15, 15, 8, 8, 8]), Ok(()));
+        assert_eq!(b.step(), ChooseRace);
+        assert_eq!(b.set_scores(vec![15, 15, 15, 10, 8, 8]),
+                   Err(InvalidInput(format!("Those scores cost 29 points, but the budget is 27."))));
+        assert!(b.set_scores(vec![18, 8, 8, 8, 8, 8]).is_err());
+    }
+
+    #[test]
+    fn standard_array() {
+        let mut b = CharacterBuilder::new("#test");
+        b.set_method("standard").unwrap();
+        assert!(b.set_scores(vec![15, 15, 13, 12, 10, 8]).is_err());
+        assert_eq!(b.set_scores(vec![8, 10, 12, 13, 14, 15]), Ok(()));
+    }
+
+    #[test]
+    fn rolled_scores() {
+        let mut b = CharacterBuilder::new("#test");
+        b.set_method("roll").unwrap();
+        b.roll_scores();
+        let mut scores = b.rolls.clone();
+        scores.reverse();
+        assert_eq!(b.set_scores(scores), Ok(()));
+    }
+}
@@ -0,0 +1,172 @@
+use std::ascii::AsciiExt;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::RollType;
+use self::CharacterClass::*;
+
+// The total experience needed to reach each level, starting at level 1.
+pub static XP_THRESHOLDS: [u32; 20] = [
+    0, 300, 900, 2700, 6500, 14000, 23000, 34000, 48000, 64000,
+    85000, 100000, 120000, 140000, 165000, 195000, 225000, 265000, 305000, 355000,
+];
+
+pub fn level_for_xp(xp: u32) -> u8 {
+    XP_THRESHOLDS.iter().take_while(|&&t| t <= xp).count() as u8
+}
+
+// Spell slots of each level for a full caster, starting at caster level 1. Half casters use the
+// row for half their class level, rounded up.
+static FULL_CASTER_SLOTS: [[u8; 9]; 20] = [
+    [2, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 2, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 2, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 1, 0, 0, 0, 0, 0],
+    [4, 3, 3, 2, 0, 0, 0, 0, 0],
+    [4, 3, 3, 3, 1, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 2, 1, 1],
+];
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum CharacterClass {
+    Barbarian,
+    Bard,
+    Cleric,
+    Druid,
+    Fighter,
+    Monk,
+    Paladin,
+    Ranger,
+    Rogue,
+    Sorcerer,
+    Warlock,
+    Wizard,
+}
+
+impl CharacterClass {
+    pub fn to_class(class: &str) -> Option<CharacterClass> {
+        match &class.to_ascii_lowercase()[..] {
+            "barbarian" => Some(Barbarian),
+            "bard" => Some(Bard),
+            "cleric" => Some(Cleric),
+            "druid" => Some(Druid),
+            "fighter" => Some(Fighter),
+            "monk" => Some(Monk),
+            "paladin" => Some(Paladin),
+            "ranger" => Some(Ranger),
+            "rogue" => Some(Rogue),
+            "sorcerer" => Some(Sorcerer),
+            "warlock" => Some(Warlock),
+            "wizard" => Some(Wizard),
+            _ => None,
+        }
+    }
+
+    pub fn hit_die(&self) -> u8 {
+        match *self {
+            Barbarian => 12,
+            Fighter | Paladin | Ranger => 10,
+            Bard | Cleric | Druid | Monk | Rogue | Warlock => 8,
+            Sorcerer | Wizard => 6,
+        }
+    }
+
+    pub fn spellcasting_ability(&self) -> Option<RollType> {
+        match *self {
+            Bard | Paladin | Sorcerer | Warlock => Some(RollType::Charisma),
+            Cleric | Druid | Ranger => Some(RollType::Wisdom),
+            Wizard => Some(RollType::Intellect),
+            Barbarian | Fighter | Monk | Rogue => None,
+        }
+    }
+
+    // Prepared casters pick their castable spells each day from the ones they know, while the
+    // others can cast anything they know.
+    pub fn prepares_spells(&self) -> bool {
+        match *self {
+            Cleric | Druid | Paladin | Wizard => true,
+            _ => false,
+        }
+    }
+
+    // Warlocks get their pact slots back on a short rest, everyone else has to wait for a long one.
+    pub fn recovers_slots_on_short_rest(&self) -> bool {
+        *self == Warlock
+    }
+
+    // The number of slots of each spell level at the given class level. Warlocks get a few pact
+    // slots which are all of the same level.
+    pub fn spell_slots(&self, level: u8) -> [u8; 9] {
+        let level = if level < 1 { 1 } else if level > 20 { 20 } else { level };
+        match *self {
+            Bard | Cleric | Druid | Sorcerer | Wizard => FULL_CASTER_SLOTS[level as usize - 1],
+            Paladin | Ranger if level >= 2 => FULL_CASTER_SLOTS[(level as usize + 1) / 2 - 1],
+            Warlock => {
+                let mut slots = [0; 9];
+                let count = match level { 1 => 1, 2...10 => 2, 11...16 => 3, _ => 4 };
+                let slot_level = if level >= 9 { 5 } else { (level + 1) / 2 };
+                slots[slot_level as usize - 1] = count;
+                slots
+            },
+            _ => [0; 9],
+        }
+    }
+}
+
+impl Display for CharacterClass {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "{:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CharacterClass, level_for_xp};
+    use super::CharacterClass::{Barbarian, Fighter, Paladin, Warlock, Wizard};
+
+    #[test]
+    fn to_class() {
+        assert_eq!(CharacterClass::to_class("fighter"), Some(Fighter));
+        assert_eq!(CharacterClass::to_class("WIZARD"), Some(Wizard));
+        assert_eq!(CharacterClass::to_class("test"), None);
+    }
+
+    #[test]
+    fn hit_die() {
+        assert_eq!(Barbarian.hit_die(), 12);
+        assert_eq!(Fighter.hit_die(), 10);
+        assert_eq!(Wizard.hit_die(), 6);
+    }
+
+    #[test]
+    fn spell_slots() {
+        assert_eq!(Wizard.spell_slots(5), [4, 3, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Paladin.spell_slots(1), [0; 9]);
+        assert_eq!(Paladin.spell_slots(5), [4, 2, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Warlock.spell_slots(5), [0, 0, 2, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Warlock.spell_slots(20), [0, 0, 0, 0, 4, 0, 0, 0, 0]);
+        assert_eq!(Fighter.spell_slots(20), [0; 9]);
+    }
+
+    #[test]
+    fn level_for_xp_thresholds() {
+        assert_eq!(level_for_xp(0), 1);
+        assert_eq!(level_for_xp(299), 1);
+        assert_eq!(level_for_xp(300), 2);
+        assert_eq!(level_for_xp(6500), 5);
+        assert_eq!(level_for_xp(1000000), 20);
+    }
+}
@@ -0,0 +1,258 @@
+use std::ascii::AsciiExt;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::BotResult;
+use data::BotError::InvalidInput;
+use data::utils::{clock_time, date, now};
+use self::Denomination::{Copper, Silver, Electrum, Gold, Platinum};
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum Denomination {
+    Copper,
+    Silver,
+    Electrum,
+    Gold,
+    Platinum,
+}
+
+impl Denomination {
+    pub fn to_denomination(denom: &str) -> Option<Denomination> {
+        match &denom.to_ascii_lowercase()[..] {
+            "cp" | "copper" => Some(Copper),
+            "sp" | "silver" => Some(Silver),
+            "ep" | "electrum" => Some(Electrum),
+            "gp" | "gold" => Some(Gold),
+            "pp" | "platinum" => Some(Platinum),
+            _ => None,
+        }
+    }
+
+    pub fn in_copper(&self) -> u32 {
+        match *self {
+            Copper => 1,
+            Silver => 10,
+            Electrum => 50,
+            Gold => 100,
+            Platinum => 1000,
+        }
+    }
+}
+
+impl Display for Denomination {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "{}", match *self {
+            Copper => "cp",
+            Silver => "sp",
+            Electrum => "ep",
+            Gold => "gp",
+            Platinum => "pp",
+        })
+    }
+}
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Purse {
+    pub cp: u32,
+    pub sp: u32,
+    pub ep: u32,
+    pub gp: u32,
+    pub pp: u32,
+}
+
+impl Purse {
+    pub fn new() -> Purse {
+        Purse { cp: 0, sp: 0, ep: 0, gp: 0, pp: 0 }
+    }
+
+    // Parses pairs of amounts and denominations, e.g. "150 gp 30 sp".
+    pub fn parse(args: &[&str]) -> Option<Purse> {
+        if args.is_empty() || args.len() % 2 != 0 {
+            return None;
+        }
+        let mut purse = Purse::new();
+        for pair in args.chunks(2) {
+            match (pair[0].parse::<u32>(), Denomination::to_denomination(pair[1])) {
+                (Ok(n), Some(denom)) => purse.add(denom, n),
+                _ => return None,
+            }
+        }
+        Some(purse)
+    }
+
+    fn slot(&mut self, denom: Denomination) -> &mut u32 {
+        match denom {
+            Copper => &mut self.cp,
+            Silver => &mut self.sp,
+            Electrum => &mut self.ep,
+            Gold => &mut self.gp,
+            Platinum => &mut self.pp,
+        }
+    }
+
+    pub fn get(&self, denom: Denomination) -> u32 {
+        match denom {
+            Copper => self.cp,
+            Silver => self.sp,
+            Electrum => self.ep,
+            Gold => self.gp,
+            Platinum => self.pp,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Purse::new()
+    }
+
+    pub fn total_in_copper(&self) -> u64 {
+        [Copper, Silver, Electrum, Gold, Platinum].iter()
+            .fold(0, |acc, d| acc + self.get(*d) as u64 * d.in_copper() as u64)
+    }
+
+    pub fn add(&mut self, denom: Denomination, amount: u32) {
+        let slot = self.slot(denom);
+        *slot = slot.saturating_add(amount);
+    }
+
+    pub fn add_purse(&mut self, other: &Purse) {
+        for d in [Copper, Silver, Electrum, Gold, Platinum].iter() {
+            self.add(*d, other.get(*d));
+        }
+    }
+
+    // Takes out exactly the coins in the other purse, without making change. Nothing is taken
+    // unless every denomination can be covered.
+    pub fn remove_purse(&mut self, other: &Purse) -> BotResult<()> {
+        for d in [Copper, Silver, Electrum, Gold, Platinum].iter() {
+            if self.get(*d) < other.get(*d) {
+                return Err(InvalidInput(format!("Not enough {} (only {}).", d, self.get(*d))));
+            }
+        }
+        for d in [Copper, Silver, Electrum, Gold, Platinum].iter() {
+            *self.slot(*d) -= other.get(*d);
+        }
+        Ok(())
+    }
+
+    // Exchanges coins of one denomination for another. Going up in value only works with
+    // amounts that convert exactly, since there is no change to give back.
+    pub fn convert(&mut self, amount: u32, from: Denomination, to: Denomination) -> BotResult<u32> {
+        if self.get(from) < amount {
+            return Err(InvalidInput(format!("Not enough {} (only {}).", from, self.get(from))));
+        }
+        let copper = amount as u64 * from.in_copper() as u64;
+        if copper % to.in_copper() as u64 != 0 {
+            return Err(InvalidInput(format!("{} {} does not convert evenly into {}.", amount, from, to)));
+        }
+        let result = copper / to.in_copper() as u64;
+        if result > u32::max_value() as u64 {
+            return Err(InvalidInput(format!("That is too many coins to carry.")));
+        }
+        *self.slot(from) -= amount;
+        self.add(to, result as u32);
+        Ok(result as u32)
+    }
+
+    // Splits every denomination evenly between the given number of shares, leaving whatever
+    // doesn't divide in this purse.
+    pub fn split(&mut self, shares: u32) -> Purse {
+        let mut share = Purse::new();
+        if shares == 0 {
+            return share;
+        }
+        for d in [Copper, Silver, Electrum, Gold, Platinum].iter() {
+            let each = self.get(*d) / shares;
+            *self.slot(*d) -= each * shares;
+            share.add(*d, each);
+        }
+        share
+    }
+}
+
+impl Display for Purse {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        if self.is_empty() {
+            return write!(fmt, "no coins");
+        }
+        let coins: Vec<_> = [Platinum, Gold, Electrum, Silver, Copper].iter()
+            .filter(|d| self.get(**d) > 0)
+            .map(|d| format!("{} {}", self.get(*d), d)).collect();
+        write!(fmt, "{}", coins.join(", "))
+    }
+}
+
+// A record of coins changing hands, kept so that disputes can be settled later.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Transaction {
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub from: String,
+    pub to: String,
+    pub coins: Purse,
+    pub note: String,
+}
+
+impl Transaction {
+    pub fn new(from: &str, to: &str, coins: Purse, note: &str) -> Transaction {
+        Transaction { time: now(), from: from.to_string(), to: to.to_string(), coins: coins, note: note.to_string() }
+    }
+}
+
+impl Display for Transaction {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "[{} {}] {} -> {}: {} ({})", date(self.time), clock_time(self.time), self.from, self.to,
+               self.coins, self.note)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Denomination, Purse, Transaction};
+    use super::Denomination::{Copper, Silver, Gold, Platinum};
+
+    #[test]
+    fn parse() {
+        let p = Purse::parse(&["150", "gp", "30", "silver"]).unwrap();
+        assert_eq!(p.gp, 150);
+        assert_eq!(p.sp, 30);
+        assert_eq!(Purse::parse(&["150"]), None);
+        assert_eq!(Purse::parse(&["150", "yen"]), None);
+        assert_eq!(Denomination::to_denomination("PP"), Some(Platinum));
+    }
+
+    #[test]
+    fn remove_purse() {
+        let mut p = Purse::parse(&["10", "gp", "5", "sp"]).unwrap();
+        assert!(p.remove_purse(&Purse::parse(&["1", "gp", "6", "sp"]).unwrap()).is_err());
+        assert_eq!(p.gp, 10);
+        p.remove_purse(&Purse::parse(&["1", "gp", "5", "sp"]).unwrap()).unwrap();
+        assert_eq!(format!("{}", p), format!("9 gp"));
+    }
+
+    #[test]
+    fn convert() {
+        let mut p = Purse::parse(&["3", "gp", "25", "cp"]).unwrap();
+        assert_eq!(p.convert(2, Gold, Silver).unwrap(), 20);
+        assert_eq!(p.gp, 1);
+        assert_eq!(p.sp, 20);
+        assert!(p.convert(25, Copper, Silver).is_err());
+        assert_eq!(p.convert(20, Copper, Silver).unwrap(), 2);
+        assert_eq!(p.total_in_copper(), 325);
+    }
+
+    #[test]
+    fn split() {
+        let mut p = Purse::parse(&["100", "gp", "7", "sp"]).unwrap();
+        let share = p.split(3);
+        assert_eq!(format!("{}", share), format!("33 gp, 2 sp"));
+        assert_eq!(format!("{}", p), format!("1 gp, 1 sp"));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", Purse::new()), format!("no coins"));
+        let mut t = Transaction::new("test", "test2", Purse::parse(&["5", "pp"]).unwrap(), "pay");
+        t.time = 1700000000;
+        assert_eq!(format!("{}", t), format!("[2023-11-14 22:13:20] test -> test2: 5 pp (pay)"));
+    }
+}
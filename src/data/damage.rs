@@ -0,0 +1,295 @@
+use std::ascii::AsciiExt;
+use std::cmp::max;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::spell::Concentration;
+use self::Adjustment::{Cancelled, Immune, Normal, Resisted, Vulnerable};
+use self::DamageType::*;
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum DamageType {
+    Acid,
+    Bludgeoning,
+    Cold,
+    Fire,
+    Force,
+    Lightning,
+    Necrotic,
+    Piercing,
+    Poison,
+    Psychic,
+    Radiant,
+    Slashing,
+    Thunder,
+}
+
+impl DamageType {
+    pub fn to_damage_type(damage_type: &str) -> Option<DamageType> {
+        match &damage_type.to_ascii_lowercase()[..] {
+            "acid" => Some(Acid),
+            "bludgeoning" => Some(Bludgeoning),
+            "cold" => Some(Cold),
+            "fire" => Some(Fire),
+            "force" => Some(Force),
+            "lightning" => Some(Lightning),
+            "necrotic" => Some(Necrotic),
+            "piercing" => Some(Piercing),
+            "poison" => Some(Poison),
+            "psychic" => Some(Psychic),
+            "radiant" => Some(Radiant),
+            "slashing" => Some(Slashing),
+            "thunder" => Some(Thunder),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DamageType {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "{}", format!("{:?}", self).to_ascii_lowercase())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Adjustment {
+    Normal,
+    Resisted,
+    Vulnerable,
+    Cancelled,
+    Immune,
+}
+
+impl Display for Adjustment {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "{}", match *self {
+            Normal => "unadjusted",
+            Resisted => "halved by resistance",
+            Vulnerable => "doubled by vulnerability",
+            Cancelled => "resistance and vulnerability cancel out",
+            Immune => "negated by immunity",
+        })
+    }
+}
+
+// The constitution save that damage forces on an entity which is concentrating on a spell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConcentrationCheck {
+    pub effect: Concentration,
+    pub total: i32,
+    pub dc: i32,
+    pub kept: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DamageResult {
+    pub raw: i32,
+    pub dealt: i32,
+    pub adjustment: Adjustment,
+    pub conscious: bool,
+    pub concentration: Option<ConcentrationCheck>,
+}
+
+impl DamageResult {
+    // Describes how the raw damage was adjusted, e.g. " (halved by resistance from 10)".
+    pub fn describe_adjustment(&self) -> String {
+        match self.adjustment {
+            Normal => String::new(),
+            adj => format!(" ({} from {})", adj, self.raw),
+        }
+    }
+
+    // Describes the damage on its own, as it appears in a game's journal.
+    pub fn describe(&self, name: &str, damage_type: Option<DamageType>, health: i32) -> String {
+        let kind = damage_type.map_or(String::new(), |t| format!(" {}", t));
+        let state = if self.conscious {
+            format!("has {} health remaining", health)
+        } else {
+            format!("has fallen unconscious")
+        };
+        format!("{} took {}{} damage{} and {}.{}", name, self.dealt, kind, self.describe_adjustment(), state,
+                self.describe_concentration(name))
+    }
+
+    // The concentration the damage broke, if any, so that its effects can be lifted from its targets.
+    pub fn lost_concentration(&self) -> Option<Concentration> {
+        match self.concentration {
+            Some(ref check) if !check.kept => Some(check.effect.clone()),
+            _ => None,
+        }
+    }
+
+    // Describes the concentration save the damage forced, if any, as a sentence to follow the
+    // damage announcement.
+    pub fn describe_concentration(&self, name: &str) -> String {
+        match self.concentration {
+            None => String::new(),
+            Some(ref check) if check.kept => format!(" {} kept concentration on {} ({} vs DC {}).", name,
+                                                     check.effect.spell, check.total, check.dc),
+            Some(ref check) if !self.conscious => format!(" {} lost concentration on {}{}.", name,
+                                                          check.effect.spell, check.effect.ending()),
+            Some(ref check) => format!(" {} lost concentration on {} ({} vs DC {}){}.", name, check.effect.spell,
+                                       check.total, check.dc, check.effect.ending()),
+        }
+    }
+}
+
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Defenses {
+    pub resistances: Vec<DamageType>,
+    pub immunities: Vec<DamageType>,
+    pub vulnerabilities: Vec<DamageType>,
+}
+
+impl Defenses {
+    pub fn new() -> Defenses {
+        Defenses { resistances: Vec::new(), immunities: Vec::new(), vulnerabilities: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resistances.is_empty() && self.immunities.is_empty() && self.vulnerabilities.is_empty()
+    }
+
+    pub fn add_resistance(&mut self, damage_type: DamageType) {
+        self.clear(damage_type);
+        self.resistances.push(damage_type);
+    }
+
+    pub fn add_immunity(&mut self, damage_type: DamageType) {
+        self.clear(damage_type);
+        self.immunities.push(damage_type);
+    }
+
+    pub fn add_vulnerability(&mut self, damage_type: DamageType) {
+        self.clear(damage_type);
+        self.vulnerabilities.push(damage_type);
+    }
+
+    pub fn clear(&mut self, damage_type: DamageType) {
+        self.resistances.retain(|t| *t != damage_type);
+        self.immunities.retain(|t| *t != damage_type);
+        self.vulnerabilities.retain(|t| *t != damage_type);
+    }
+
+    pub fn adjust(&self, amount: i32, damage_type: Option<DamageType>) -> (i32, Adjustment) {
+        let amount = max(0, amount);
+        let damage_type = match damage_type {
+            Some(t) => t,
+            None => return (amount, Normal),
+        };
+        if self.immunities.contains(&damage_type) {
+            return (0, Immune);
+        }
+        match (self.resistances.contains(&damage_type), self.vulnerabilities.contains(&damage_type)) {
+            (true, true) => ((amount / 2).saturating_mul(2), Cancelled),
+            (true, false) => (amount / 2, Resisted),
+            (false, true) => (amount.saturating_mul(2), Vulnerable),
+            (false, false) => (amount, Normal),
+        }
+    }
+}
+
+impl Display for Defenses {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        fn list(types: &Vec<DamageType>) -> String {
+            let names: Vec<_> = types.iter().map(|t| format!("{}", t)).collect();
+            if names.is_empty() { "none".to_string() } else { names.join(", ") }
+        }
+        write!(fmt, "Resistances: {}; Immunities: {}; Vulnerabilities: {}", list(&self.resistances),
+               list(&self.immunities), list(&self.vulnerabilities))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConcentrationCheck, DamageResult, DamageType, Defenses};
+    use super::Adjustment::{Cancelled, Immune, Normal, Resisted, Vulnerable};
+    use super::DamageType::{Cold, Fire, Slashing};
+    use data::spell::Concentration;
+
+    #[test]
+    fn to_damage_type() {
+        assert_eq!(DamageType::to_damage_type("fire"), Some(Fire));
+        assert_eq!(DamageType::to_damage_type("SLASHING"), Some(Slashing));
+        assert_eq!(DamageType::to_damage_type("test"), None);
+    }
+
+    #[test]
+    fn display_damage_type() {
+        assert_eq!(format!("{}", Fire), format!("fire"));
+        assert_eq!(format!("{}", Slashing), format!("slashing"));
+    }
+
+    #[test]
+    fn adjust() {
+        let mut d = Defenses::new();
+        d.add_resistance(Fire);
+        d.add_immunity(Cold);
+        assert_eq!(d.adjust(11, Some(Fire)), (5, Resisted));
+        assert_eq!(d.adjust(11, Some(Cold)), (0, Immune));
+        assert_eq!(d.adjust(11, Some(Slashing)), (11, Normal));
+        assert_eq!(d.adjust(11, None), (11, Normal));
+        d.add_vulnerability(Slashing);
+        assert_eq!(d.adjust(11, Some(Slashing)), (22, Vulnerable));
+        assert_eq!(d.adjust(200, Some(Slashing)), (400, Vulnerable));
+        assert_eq!(d.adjust(-5, Some(Slashing)), (0, Vulnerable));
+    }
+
+    #[test]
+    fn adjust_cancelled() {
+        let mut d = Defenses::new();
+        d.resistances.push(Fire);
+        d.vulnerabilities.push(Fire);
+        assert_eq!(d.adjust(11, Some(Fire)), (10, Cancelled));
+    }
+
+    #[test]
+    fn add_replaces_existing() {
+        let mut d = Defenses::new();
+        d.add_resistance(Fire);
+        d.add_vulnerability(Fire);
+        assert!(d.resistances.is_empty());
+        assert_eq!(d.vulnerabilities, vec![Fire]);
+        d.clear(Fire);
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn describe_adjustment() {
+        let r = DamageResult { raw: 10, dealt: 5, adjustment: Resisted, conscious: true, concentration: None };
+        assert_eq!(r.describe_adjustment(), format!(" (halved by resistance from 10)"));
+        let n = DamageResult { raw: 10, dealt: 10, adjustment: Normal, conscious: true, concentration: None };
+        assert_eq!(n.describe_adjustment(), format!(""));
+    }
+
+    #[test]
+    fn describe() {
+        let r = DamageResult { raw: 10, dealt: 5, adjustment: Resisted, conscious: true, concentration: None };
+        assert_eq!(r.describe("test", Some(Fire), 15),
+                   format!("test took 5 fire damage (halved by resistance from 10) and has 15 health remaining."));
+        let n = DamageResult { raw: 10, dealt: 10, adjustment: Normal, conscious: false, concentration: None };
+        assert_eq!(n.describe("test", None, 0), format!("test took 10 damage and has fallen unconscious."));
+    }
+
+    #[test]
+    fn describe_concentration() {
+        let effect = Concentration { spell: "Bless".to_string(), targets: vec!["test2".to_string()] };
+        let mut r = DamageResult { raw: 10, dealt: 10, adjustment: Normal, conscious: true, concentration: None };
+        assert_eq!(r.describe_concentration("test"), format!(""));
+        r.concentration = Some(ConcentrationCheck { effect: effect.clone(), total: 12, dc: 10, kept: true });
+        assert_eq!(r.describe_concentration("test"), format!(" test kept concentration on Bless (12 vs DC 10)."));
+        assert_eq!(r.lost_concentration(), None);
+        r.concentration = Some(ConcentrationCheck { effect: effect.clone(), total: 7, dc: 10, kept: false });
+        assert_eq!(r.describe_concentration("test"),
+                   format!(" test lost concentration on Bless (7 vs DC 10), ending it for test2."));
+        assert_eq!(r.lost_concentration(), Some(effect));
+    }
+
+    #[test]
+    fn display_defenses() {
+        let mut d = Defenses::new();
+        d.add_resistance(Fire);
+        d.add_resistance(Cold);
+        assert_eq!(format!("{}", d), format!("Resistances: fire, cold; Immunities: none; Vulnerabilities: none"));
+    }
+}
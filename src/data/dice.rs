@@ -0,0 +1,490 @@
+use std::ascii::AsciiExt;
+use std::borrow::ToOwned;
+use std::cell::RefCell;
+use std::cmp::max;
+#[cfg(test)]
+use std::collections::VecDeque;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::fs::{OpenOptions, create_dir_all};
+use std::io::{Error, ErrorKind, Result};
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+use std::result::Result as StdResult;
+use data::journal::{EventKind, log_event};
+use data::utils::{clock_time, now};
+use rand::{Rng, SeedableRng, StdRng, thread_rng};
+use rand::distributions::{IndependentSample, Range};
+use rustc_serialize::json::encode;
+
+// How many rolls a tray keeps in memory. Rolls made during a session are also written to disk.
+pub const MAX_ROLLS: usize = 500;
+
+// Where die results come from. Every roll goes through roll_die(...), which draws from the source
+// set for the message being handled, so a campaign's seed decides its rolls and tests can script them.
+pub enum DiceSource {
+    Random,
+    // The seed and how many dice have been drawn from it. Each die is drawn from the seed and its
+    // place in the order, so a source can be picked back up from just those two numbers.
+    Seeded(usize, usize),
+    // Results are handed out in order. Only tests script their dice, and running out is a mistake
+    // in the script, so it panics.
+    #[cfg(test)]
+    Scripted(VecDeque<i32>),
+}
+
+impl DiceSource {
+    pub fn seeded(seed: usize) -> DiceSource {
+        DiceSource::Seeded(seed, 0)
+    }
+
+    #[cfg(test)]
+    pub fn scripted(rolls: Vec<i32>) -> DiceSource {
+        DiceSource::Scripted(rolls.into_iter().collect())
+    }
+
+    pub fn shared(self) -> SharedDice {
+        Rc::new(RefCell::new(DiceTray { source: self, log: Vec::new(), recorded: 0, file: None, pending: Vec::new() }))
+    }
+
+    pub fn seed(&self) -> Option<usize> {
+        match *self {
+            DiceSource::Seeded(seed, _) => Some(seed),
+            _ => None,
+        }
+    }
+
+    pub fn drawn(&self) -> usize {
+        match *self {
+            DiceSource::Seeded(_, drawn) => drawn,
+            _ => 0,
+        }
+    }
+
+    fn roll(&mut self, sides: i32) -> i32 {
+        let die = Range::new(1, max(1, sides) + 1);
+        match *self {
+            DiceSource::Random => die.ind_sample(&mut thread_rng()),
+            DiceSource::Seeded(seed, ref mut drawn) => {
+                let mut rng: StdRng = SeedableRng::from_seed(&[seed, *drawn][..]);
+                *drawn += 1;
+                die.ind_sample(&mut rng)
+            },
+            #[cfg(test)]
+            DiceSource::Scripted(ref mut rolls) => match rolls.pop_front() {
+                Some(roll) => roll,
+                None => panic!("Ran out of scripted rolls."),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum Visibility {
+    Public,
+    Private,
+    // Seen only by the DM.
+    Secret,
+    // Seen only by the roller and the DM.
+    Whispered,
+}
+
+// A roll as it appears in the audit trail.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct RollRecord {
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub roller: String,
+    pub character: Option<String>,
+    pub expression: String,
+    pub dice: Vec<i32>,
+    pub modifier: i32,
+    pub total: i32,
+    pub visibility: Visibility,
+}
+
+impl RollRecord {
+    pub fn involves(&self, name: &str) -> bool {
+        self.roller.eq_ignore_ascii_case(name) || self.character.as_ref().map_or(false, |c| c.eq_ignore_ascii_case(name))
+    }
+
+    // Describes the roll without its time. Anyone can see the log, so rolls that weren't made in the
+    // open only show that they were made.
+    pub fn summary(&self) -> String {
+        let mut s = format!("{} rolled {}", self.roller, self.expression);
+        if let Some(ref character) = self.character {
+            s.push_str(&format!(" for {}", character));
+        }
+        match self.visibility {
+            Visibility::Secret => return format!("{} (secret)", s),
+            Visibility::Whispered => return format!("{} (whispered)", s),
+            Visibility::Private => return format!("{} (private)", s),
+            Visibility::Public => (),
+        }
+        let dice: Vec<_> = self.dice.iter().map(|d| d.to_string()).collect();
+        s.push_str(&format!(": [{}]", dice.join(", ")));
+        if self.modifier != 0 {
+            s.push_str(&format!("{:+}", self.modifier));
+        }
+        s.push_str(&format!(" = {}", self.total));
+        s
+    }
+}
+
+impl Display for RollRecord {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "[{}] {}", clock_time(self.time), self.summary())
+    }
+}
+
+// A source of dice along with the record of everything rolled with it.
+pub struct DiceTray {
+    pub source: DiceSource,
+    // The most recent rolls, oldest first.
+    pub log: Vec<RollRecord>,
+    // How many rolls have been recorded, including those no longer in the log.
+    pub recorded: usize,
+    // Where every roll is appended in full, one JSON record per line, while a session is running.
+    pub file: Option<String>,
+    // Dice rolled but not yet recorded, as (sides, result) pairs.
+    pending: Vec<(i32, i32)>,
+}
+
+pub type SharedDice = Rc<RefCell<DiceTray>>;
+
+impl DiceTray {
+    // The last count rolls, oldest first, optionally only those made by or for the named character.
+    pub fn recent(&self, name: Option<&str>, count: usize) -> Vec<&RollRecord> {
+        let mut rolls: Vec<_> = self.log.iter().rev().filter(|r| name.map_or(true, |n| r.involves(n)))
+            .take(count).collect();
+        rolls.reverse();
+        rolls
+    }
+
+    fn add(&mut self, roll: RollRecord) {
+        if let Some(ref path) = self.file {
+            if let Err(e) = append(path, &roll) {
+                println!("Failed to save roll to {}: {}", path, e);
+            }
+        }
+        self.log.push(roll);
+        self.recorded += 1;
+        if self.log.len() > MAX_ROLLS {
+            self.log.remove(0);
+        }
+    }
+}
+
+fn append(path: &str, roll: &RollRecord) -> Result<()> {
+    if let Some(dir) = Path::new(path).parent() {
+        try!(create_dir_all(dir));
+    }
+    let mut f = try!(OpenOptions::new().create(true).append(true).open(&Path::new(path)));
+    let line = try!(encode(roll).map_err(|_| Error::new(ErrorKind::InvalidInput, "Failed to encode roll.")));
+    f.write_all(format!("{}\n", line).as_bytes())
+}
+
+// A seed for a new session, which has to be unpredictable unlike the rolls it produces.
+pub fn fresh_seed() -> usize {
+    thread_rng().gen()
+}
+
+thread_local!(static CURRENT: RefCell<Option<SharedDice>> = RefCell::new(None));
+thread_local!(static ROLLER: RefCell<(String, Visibility)> = RefCell::new((String::new(), Visibility::Public)));
+
+// Sets where rolls on this thread come from, with None meaning plain random rolls.
+pub fn use_source(source: Option<SharedDice>) {
+    CURRENT.with(|current| *current.borrow_mut() = source);
+}
+
+// Sets who the rolls on this thread are recorded as being made by.
+pub fn set_roller(roller: &str, visibility: Visibility) {
+    ROLLER.with(|current| *current.borrow_mut() = (roller.to_owned(), visibility));
+}
+
+// Whether rolls on this thread come from a source, rather than falling back to plain random rolls.
+pub fn has_source() -> bool {
+    CURRENT.with(|current| current.borrow().is_some())
+}
+
+// Puts rolls on this thread back to plain random rolls made by nobody once a message is handled, so
+// that neither its game's dice nor its roller carry over to the next one.
+pub fn reset() {
+    use_source(None);
+    set_roller("", Visibility::Public);
+}
+
+fn with_tray<F, R>(f: F) -> Option<R> where F: FnOnce(&mut DiceTray) -> R {
+    CURRENT.with(|current| {
+        let current = current.borrow();
+        current.as_ref().map(|tray| f(&mut tray.borrow_mut()))
+    })
+}
+
+// Rolls a single die with the given number of sides from the current source.
+pub fn roll_die(sides: i32) -> i32 {
+    with_tray(|tray| {
+        let roll = tray.source.roll(sides);
+        tray.pending.push((sides, roll));
+        roll
+    }).unwrap_or_else(|| DiceSource::Random.roll(sides))
+}
+
+// Rolls a d20 for a check, save or attack, recording it with the modifier that applies. Returns the
+// natural roll, since criticals depend on it.
+pub fn roll_d20(character: Option<&str>, purpose: &str, modifier: i32) -> i32 {
+    let natural = roll_die(20);
+    let expression = if purpose.is_empty() { format!("d20") } else { format!("d20 ({})", purpose) };
+    record(&expression, character, modifier, natural.saturating_add(modifier));
+    natural
+}
+
+// Adds the dice rolled since the last record to the audit trail as a single roll.
+pub fn record(expression: &str, character: Option<&str>, modifier: i32, total: i32) {
+    let (roller, visibility) = ROLLER.with(|current| current.borrow().clone());
+    let summary = with_tray(|tray| {
+        let dice = tray.pending.drain(..).map(|(_, roll)| roll).collect();
+        let roll = RollRecord {
+            time: now(), roller: roller, character: character.map(|c| c.to_owned()),
+            expression: expression.to_owned(), dice: dice, modifier: modifier, total: total,
+            visibility: visibility,
+        };
+        let summary = roll.summary();
+        tray.add(roll);
+        summary
+    });
+    if let Some(summary) = summary {
+        log_event(EventKind::Roll, &summary);
+    }
+}
+
+// Records any dice that were rolled without being recorded, grouped as one roll.
+pub fn record_remaining() {
+    let pending = with_tray(|tray| tray.pending.clone()).unwrap_or_else(Vec::new);
+    if pending.is_empty() {
+        return;
+    }
+    let sides = pending[0].0;
+    let expression = if pending.iter().all(|&(s, _)| s == sides) {
+        format!("{}d{}", pending.len(), sides)
+    } else {
+        let dice: Vec<_> = pending.iter().map(|&(s, _)| format!("d{}", s)).collect();
+        dice.join("+")
+    };
+    record(&expression, None, 0, pending.iter().fold(0, |acc, &(_, roll)| acc + roll));
+}
+
+// Makes the following rolls on this thread come out as given.
+#[cfg(test)]
+pub fn script(rolls: Vec<i32>) {
+    use_source(Some(DiceSource::scripted(rolls).shared()));
+}
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Dice {
+    pub count: u8,
+    pub sides: u8,
+    pub bonus: i32,
+}
+
+impl Dice {
+    pub fn new(count: u8, sides: u8, bonus: i32) -> Dice {
+        Dice { count: count, sides: sides, bonus: bonus }
+    }
+
+    pub fn parse(expr: &str) -> Option<Dice> {
+        let (dice, bonus) = if let Some(i) = expr.find(|c: char| c == '+' || c == '-') {
+            let bonus: i32 = match expr[i + 1..].parse() {
+                Ok(n) => n,
+                Err(_) => return None,
+            };
+            (&expr[..i], if expr[i..].starts_with("-") { -bonus } else { bonus })
+        } else {
+            (expr, 0)
+        };
+        if let Some(i) = dice.find('d') {
+            let count = if i == 0 { Ok(1) } else { dice[..i].parse() };
+            match (count, dice[i + 1..].parse()) {
+                (Ok(c), Ok(s)) if c > 0 && s > 0 => Some(Dice::new(c, s, bonus)),
+                _ => None,
+            }
+        } else {
+            dice.parse::<i32>().ok().and_then(|n| n.checked_add(bonus)).map(|n| Dice::new(0, 1, n))
+        }
+    }
+
+    pub fn roll(&self) -> i32 {
+        self.roll_with_crit(false)
+    }
+
+    // Totals never go below zero.
+    pub fn roll_with_crit(&self, crit: bool) -> i32 {
+        let count = if crit { self.count as u16 * 2 } else { self.count as u16 };
+        let total = (0..count).fold(0i32, |acc, _| acc + roll_die(self.sides as i32));
+        let total = max(0, total.saturating_add(self.bonus));
+        if self.count > 0 {
+            record(&format!("{}{}", self, if crit { " (critical)" } else { "" }), None, self.bonus, total);
+        }
+        total
+    }
+}
+
+impl Display for Dice {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        if self.count == 0 {
+            write!(fmt, "{}", self.bonus)
+        } else if self.bonus > 0 {
+            write!(fmt, "{}d{}+{}", self.count, self.sides, self.bonus)
+        } else if self.bonus < 0 {
+            write!(fmt, "{}d{}{}", self.count, self.sides, self.bonus)
+        } else {
+            write!(fmt, "{}d{}", self.count, self.sides)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Dice, DiceSource, MAX_ROLLS, RollRecord, Visibility, record_remaining, roll_d20, roll_die,
+                script, set_roller, use_source};
+    use data::journal::{Journal, use_journal};
+
+    #[test]
+    fn parse() {
+        assert_eq!(Dice::parse("2d6+3"), Some(Dice::new(2, 6, 3)));
+        assert_eq!(Dice::parse("1d8-1"), Some(Dice::new(1, 8, -1)));
+        assert_eq!(Dice::parse("d12"), Some(Dice::new(1, 12, 0)));
+        assert_eq!(Dice::parse("5"), Some(Dice::new(0, 1, 5)));
+        assert_eq!(Dice::parse("0d6"), None);
+        assert_eq!(Dice::parse("2d"), None);
+        assert_eq!(Dice::parse("test"), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", Dice::new(2, 6, 3)), format!("2d6+3"));
+        assert_eq!(format!("{}", Dice::new(1, 8, -1)), format!("1d8-1"));
+        assert_eq!(format!("{}", Dice::new(1, 12, 0)), format!("1d12"));
+        assert_eq!(format!("{}", Dice::new(0, 1, 5)), format!("5"));
+    }
+
+    #[test]
+    fn roll() {
+        let d = Dice::new(2, 6, 3);
+        for _ in 0..1000 {
+            let r = d.roll();
+            assert!(r >= 5 && r <= 15);
+        }
+    }
+
+    #[test]
+    fn roll_never_negative() {
+        let d = Dice::new(1, 4, -5);
+        for _ in 0..1000 {
+            assert_eq!(d.roll(), 0);
+        }
+    }
+
+    #[test]
+    fn roll_with_crit() {
+        let d = Dice::new(1, 6, 0);
+        for _ in 0..1000 {
+            let r = d.roll_with_crit(true);
+            assert!(r >= 2 && r <= 12);
+        }
+    }
+
+    #[test]
+    fn roll_scripted() {
+        script(vec![4, 6, 1]);
+        assert_eq!(Dice::new(2, 6, 3).roll(), 13);
+        assert_eq!(roll_die(20), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Ran out of scripted rolls.")]
+    fn roll_scripted_runs_out() {
+        script(vec![4]);
+        roll_die(20);
+        roll_die(20);
+    }
+
+    #[test]
+    fn roll_seeded() {
+        use_source(Some(DiceSource::seeded(42).shared()));
+        let first: Vec<i32> = (0..20).map(|_| roll_die(20)).collect();
+        use_source(Some(DiceSource::seeded(42).shared()));
+        let second: Vec<i32> = (0..20).map(|_| roll_die(20)).collect();
+        assert_eq!(first, second);
+        assert!(first.iter().all(|&r| r >= 1 && r <= 20));
+        use_source(Some(DiceSource::Seeded(42, 12).shared()));
+        let resumed: Vec<i32> = (0..8).map(|_| roll_die(20)).collect();
+        assert_eq!(&first[12..], &resumed[..]);
+    }
+
+    #[test]
+    fn rolls_are_recorded() {
+        let tray = DiceSource::scripted(vec![14, 3, 5, 2, 6]).shared();
+        use_source(Some(tray.clone()));
+        set_roller("test", Visibility::Private);
+        assert_eq!(roll_d20(Some("Test"), "Stealth check", 2), 14);
+        assert_eq!(Dice::new(2, 8, 1).roll_with_crit(false), 9);
+        roll_die(6);
+        roll_die(6);
+        record_remaining();
+        record_remaining();
+        let tray = tray.borrow();
+        assert_eq!(tray.log.len(), 3);
+        assert_eq!(tray.log[0].expression, format!("d20 (Stealth check)"));
+        assert_eq!(tray.log[0].character, Some(format!("Test")));
+        assert_eq!((tray.log[0].modifier, tray.log[0].total), (2, 16));
+        assert_eq!(tray.log[1].expression, format!("2d8+1"));
+        assert_eq!(tray.log[1].dice, vec![3, 5]);
+        assert_eq!(tray.log[2].expression, format!("2d6"));
+        assert_eq!((tray.log[2].dice.clone(), tray.log[2].total), (vec![2, 6], 8));
+        assert!(tray.log.iter().all(|r| r.roller == "test" && r.visibility == Visibility::Private));
+        assert_eq!(tray.recent(Some("test"), 2).len(), 2);
+        assert_eq!(tray.recent(Some("test"), 2)[0].expression, format!("2d8+1"));
+        assert_eq!(tray.recent(Some("TEST"), 5).len(), 3);
+        assert!(tray.recent(Some("test2"), 5).is_empty());
+    }
+
+    #[test]
+    fn private_rolls_are_journaled_without_results() {
+        let j = Journal::new("test").shared();
+        use_journal(Some(j.clone()), "test");
+        script(vec![14]);
+        set_roller("test", Visibility::Private);
+        roll_d20(None, "", 0);
+        use_journal(None, "");
+        assert_eq!(j.borrow().events[0].text, format!("test rolled d20 (private)"));
+    }
+
+    #[test]
+    fn log_is_capped() {
+        let tray = DiceSource::Random.shared();
+        use_source(Some(tray.clone()));
+        for _ in 0..MAX_ROLLS + 5 {
+            roll_d20(None, "", 0);
+        }
+        let tray = tray.borrow();
+        assert_eq!(tray.log.len(), MAX_ROLLS);
+        assert_eq!(tray.recorded, MAX_ROLLS + 5);
+    }
+
+    #[test]
+    fn display_record() {
+        let mut r = RollRecord {
+            time: 45296, roller: format!("test"), character: Some(format!("Test")), expression: format!("d20 (Strength)"),
+            dice: vec![14], modifier: -1, total: 13, visibility: Visibility::Public,
+        };
+        assert_eq!(format!("{}", r), format!("[12:34:56] test rolled d20 (Strength) for Test: [14]-1 = 13"));
+        r.character = None;
+        r.modifier = 0;
+        r.visibility = Visibility::Private;
+        assert_eq!(format!("{}", r), format!("[12:34:56] test rolled d20 (Strength) (private)"));
+        r.visibility = Visibility::Secret;
+        assert_eq!(format!("{}", r), format!("[12:34:56] test rolled d20 (Strength) (secret)"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::ascii::AsciiExt;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::{BotResult, RollType};
+use data::BotError::InvalidInput;
+use data::RollType::{Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma};
+use data::class::CharacterClass;
+use data::class::CharacterClass::{Barbarian, Monk, Rogue};
+use data::player::Player;
+use data::skill::Skill;
+use data::skill::Skill::{Deception, Performance, Stealth};
+use self::FeatModifier::{HealthPerLevel, Save, Speed, Stat};
+use self::Prerequisite::{ClassLevel, MinimumStat, Spellcasting};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Prerequisite {
+    MinimumStat(RollType, u8),
+    ClassLevel(CharacterClass, u8),
+    Spellcasting,
+}
+
+impl Display for Prerequisite {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        match *self {
+            MinimumStat(stat, min) => write!(fmt, "{:?} {}", stat, min),
+            ClassLevel(class, level) => write!(fmt, "a level {} {}", level, class),
+            Spellcasting => write!(fmt, "the ability to cast spells"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeatModifier {
+    Stat(RollType, u8),
+    Save(RollType),
+    Skill(Skill),
+    Speed(u8),
+    HealthPerLevel(u8),
+}
+
+#[derive(Debug)]
+pub struct FeatInfo {
+    pub name: &'static str,
+    pub class_feature: bool,
+    pub description: &'static str,
+    pub prerequisites: &'static [Prerequisite],
+    pub modifiers: &'static [FeatModifier],
+}
+
+pub static FEATS: [FeatInfo; 22] = [
+    FeatInfo {
+        name: "Actor", class_feature: false, prerequisites: &[],
+        modifiers: &[Stat(Charisma, 1), FeatModifier::Skill(Deception), FeatModifier::Skill(Performance)],
+        description: "Advantage on Deception and Performance checks when passing as someone else, and can mimic speech.",
+    },
+    FeatInfo {
+        name: "Alert", class_feature: false, prerequisites: &[], modifiers: &[],
+        description: "+5 to initiative, can't be surprised while conscious, and hidden attackers gain no advantage.",
+    },
+    FeatInfo {
+        name: "Athlete", class_feature: false, prerequisites: &[], modifiers: &[Stat(Strength, 1)],
+        description: "Standing up and climbing cost less movement, and running jumps only need a 5 foot run-up.",
+    },
+    FeatInfo {
+        name: "Defensive Duelist", class_feature: false, prerequisites: &[MinimumStat(Dexterity, 13)], modifiers: &[],
+        description: "While wielding a finesse weapon, use a reaction to add proficiency to AC against a melee attack.",
+    },
+    FeatInfo {
+        name: "Durable", class_feature: false, prerequisites: &[], modifiers: &[Stat(Constitution, 1)],
+        description: "Hit dice spent to heal always restore at least twice the constitution modifier.",
+    },
+    FeatInfo {
+        name: "Elemental Adept", class_feature: false, prerequisites: &[Spellcasting], modifiers: &[],
+        description: "Spells ignore resistance to a chosen damage type and treat any 1 on their damage dice as a 2.",
+    },
+    FeatInfo {
+        name: "Grappler", class_feature: false, prerequisites: &[MinimumStat(Strength, 13)], modifiers: &[],
+        description: "Advantage on attacks against a grappled creature, and can pin it with a further grapple check.",
+    },
+    FeatInfo {
+        name: "Heavily Armored", class_feature: false, prerequisites: &[], modifiers: &[Stat(Strength, 1)],
+        description: "Gains proficiency with heavy armor.",
+    },
+    FeatInfo {
+        name: "Keen Mind", class_feature: false, prerequisites: &[], modifiers: &[Stat(Intellect, 1)],
+        description: "Always knows north, the hours until sunrise or sunset, and recalls anything from the past month.",
+    },
+    FeatInfo {
+        name: "Linguist", class_feature: false, prerequisites: &[], modifiers: &[Stat(Intellect, 1)],
+        description: "Learns three languages and can create written ciphers.",
+    },
+    FeatInfo {
+        name: "Mobile", class_feature: false, prerequisites: &[], modifiers: &[Speed(10)],
+        description: "Dashing ignores difficult terrain, and creatures attacked in melee can't make opportunity attacks.",
+    },
+    FeatInfo {
+        name: "Observant", class_feature: false, prerequisites: &[], modifiers: &[Stat(Wisdom, 1)],
+        description: "+5 to passive Perception and Investigation, and can read lips.",
+    },
+    FeatInfo {
+        name: "Resilient", class_feature: false, prerequisites: &[],
+        modifiers: &[Stat(Constitution, 1), Save(Constitution)],
+        description: "Gains proficiency in constitution saving throws.",
+    },
+    FeatInfo {
+        name: "Ritual Caster", class_feature: false, prerequisites: &[MinimumStat(Intellect, 13)], modifiers: &[],
+        description: "Keeps a ritual book and can cast the rituals written in it.",
+    },
+    FeatInfo {
+        name: "Skulker", class_feature: false, prerequisites: &[MinimumStat(Dexterity, 13)],
+        modifiers: &[FeatModifier::Skill(Stealth)],
+        description: "Can hide when lightly obscured, and missing a ranged attack doesn't give away the position.",
+    },
+    FeatInfo {
+        name: "Tough", class_feature: false, prerequisites: &[], modifiers: &[HealthPerLevel(2)],
+        description: "Maximum health increases by 2 for every level.",
+    },
+    FeatInfo {
+        name: "War Caster", class_feature: false, prerequisites: &[Spellcasting], modifiers: &[],
+        description: "Advantage on concentration saves, and can cast a spell as an opportunity attack.",
+    },
+    FeatInfo {
+        name: "Diamond Soul", class_feature: true, prerequisites: &[ClassLevel(Monk, 14)],
+        modifiers: &[Save(Strength), Save(Dexterity), Save(Constitution), Save(Wisdom), Save(Intellect),
+                     Save(Charisma)],
+        description: "Gains proficiency in all saving throws.",
+    },
+    FeatInfo {
+        name: "Fast Movement", class_feature: true, prerequisites: &[ClassLevel(Barbarian, 5)],
+        modifiers: &[Speed(10)],
+        description: "Speed increases by 10 feet while not wearing heavy armor.",
+    },
+    FeatInfo {
+        name: "Slippery Mind", class_feature: true, prerequisites: &[ClassLevel(Rogue, 15)],
+        modifiers: &[Save(Wisdom)],
+        description: "Gains proficiency in wisdom saving throws.",
+    },
+    FeatInfo {
+        name: "Unarmored Movement", class_feature: true, prerequisites: &[ClassLevel(Monk, 2)],
+        modifiers: &[Speed(10)],
+        description: "Speed increases by 10 feet while not wearing armor or a shield.",
+    },
+    FeatInfo {
+        name: "Uncanny Dodge", class_feature: true, prerequisites: &[ClassLevel(Rogue, 5)], modifiers: &[],
+        description: "Use a reaction to halve the damage of an attack from a visible attacker.",
+    },
+];
+
+impl FeatInfo {
+    pub fn find(name: &str) -> Option<&'static FeatInfo> {
+        FEATS.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+
+    // Checks that the player meets every prerequisite, naming the first one they don't.
+    pub fn check(&self, player: &Player) -> BotResult<()> {
+        for prerequisite in self.prerequisites.iter() {
+            let met = match *prerequisite {
+                MinimumStat(stat, min) => player.stats.get_for(stat) >= min as i32,
+                ClassLevel(class, level) => player.class == Some(class) && player.level >= level,
+                Spellcasting => player.class.and_then(|c| c.spellcasting_ability()).is_some(),
+            };
+            if !met {
+                return Err(InvalidInput(format!("{} requires {}.", self.name, prerequisite)));
+            }
+        }
+        Ok(())
+    }
+
+    // Describes what kind of feat this is and what it needs, e.g. "feat, requires Strength 13".
+    pub fn describe_requirements(&self) -> String {
+        let mut s = if self.class_feature { "class feature" } else { "feat" }.to_string();
+        if !self.prerequisites.is_empty() {
+            let reqs: Vec<_> = self.prerequisites.iter().map(|p| format!("{}", p)).collect();
+            s.push_str(&format!(", requires {}", reqs.join(" and ")));
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FeatInfo;
+    use data::class::CharacterClass::{Monk, Wizard};
+    use data::player::Player;
+
+    #[test]
+    fn find() {
+        assert_eq!(FeatInfo::find("war caster").unwrap().name, "War Caster");
+        assert!(FeatInfo::find("test").is_none());
+    }
+
+    #[test]
+    fn check() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        assert_eq!(format!("{}", FeatInfo::find("Grappler").unwrap().check(&p).unwrap_err()),
+                   format!("Grappler requires Strength 13."));
+        assert_eq!(format!("{}", FeatInfo::find("War Caster").unwrap().check(&p).unwrap_err()),
+                   format!("War Caster requires the ability to cast spells."));
+        assert_eq!(format!("{}", FeatInfo::find("Unarmored Movement").unwrap().check(&p).unwrap_err()),
+                   format!("Unarmored Movement requires a level 2 Monk."));
+        p.class = Some(Wizard);
+        p.stats.strength = 13;
+        assert!(FeatInfo::find("Grappler").unwrap().check(&p).is_ok());
+        assert!(FeatInfo::find("War Caster").unwrap().check(&p).is_ok());
+        p.class = Some(Monk);
+        p.level = 2;
+        assert!(FeatInfo::find("Unarmored Movement").unwrap().check(&p).is_ok());
+    }
+
+    #[test]
+    fn describe_requirements() {
+        assert_eq!(FeatInfo::find("Alert").unwrap().describe_requirements(), format!("feat"));
+        assert_eq!(FeatInfo::find("Grappler").unwrap().describe_requirements(),
+                   format!("feat, requires Strength 13"));
+        assert_eq!(FeatInfo::find("Diamond Soul").unwrap().describe_requirements(),
+                   format!("class feature, requires a level 14 Monk"));
+    }
+}
@@ -1,31 +1,196 @@
+use std::ascii::AsciiExt;
 use std::collections::HashMap;
-use std::io::Result;
+use std::fs::{File, create_dir_all, read_dir, remove_file};
+use std::io::{Error, ErrorKind, Result};
 use std::io::prelude::*;
+use std::path::Path;
+use data::coins::{Purse, Transaction};
+use data::dice::{DiceSource, SharedDice, fresh_seed, roll_d20};
+use data::history::{History, MAX_SESSION_SNAPSHOTS, MAX_SNAPSHOTS, Snapshot};
+use data::journal::{Journal, SharedJournal};
 use data::player::Player;
+use data::rules::RuleSystem;
+use data::spell::Spell;
+use data::utils::{now, slug};
 use data::{BotResult, as_io};
-use data::BotError::PasswordIncorrect;
+use data::BotError::{InvalidInput, PasswordIncorrect};
 use openssl::crypto::hash::{Type, Hasher};
-use rand::thread_rng;
-use rand::distributions::{IndependentSample, Range};
 use rustc_serialize::hex::ToHex;
+use rustc_serialize::json::{decode, encode};
 
 pub struct Game {
     pub name: String,
-    pub dm_nick: String,
+    // Everyone who can run the game, starting with whoever created it or had it handed to them.
+    pub dms: Vec<String>,
     pub users: HashMap<String, Player>,
+    pub purse: Purse,
+    pub ledger: Vec<Transaction>,
+    // Feats players have asked for, as (nickname, feat) pairs, awaiting the DM's approval.
+    pub pending_feats: Vec<(String, String)>,
+    // Spells players have asked to learn, as (nickname, spell) pairs, also awaiting approval.
+    pub pending_spells: Vec<(String, Spell)>,
+    pub rules: RuleSystem,
+    // Where the game's rolls come from, shared with the world while handling its messages.
+    pub dice: SharedDice,
+    pub session: Option<Session>,
+    // What has happened in the game since the current session started, or since it was created.
+    pub journal: SharedJournal,
+    pub history: History,
+    // Oldest first.
+    pub snapshots: Vec<Snapshot>,
+}
+
+// A stretch of play whose rolls are committed to up front: the hash of the seed is published when
+// it starts and the seed itself when it ends, so anyone can replay the rolls made in between.
+pub struct Session {
+    pub seed: usize,
+    pub commitment: String,
+    // Seconds since the Unix epoch.
+    pub started: u64,
+    // How many rolls the game's dice had recorded when the session started.
+    pub first_roll: usize,
+    // How many dice had been drawn from the seed when the game was last saved.
+    pub drawn: usize,
+}
+
+// What's saved of a running session, so a restart carries on with the seed that was committed to.
+#[derive(RustcDecodable, RustcEncodable, Debug, PartialEq)]
+struct SessionRecord {
+    seed: usize,
+    commitment: String,
+    started: u64,
+    drawn: usize,
+    // How many rolls have been made in the session.
+    rolls: usize,
+}
+
+// What's saved of a game so that it can be picked back up after a restart.
+#[derive(RustcDecodable, RustcEncodable, Debug, PartialEq)]
+struct GameRecord {
+    name: String,
+    dms: Vec<String>,
+    purse: Purse,
+    ledger: Vec<Transaction>,
+    // Missing from games saved before the rules were, which used the standard ones.
+    rules: Option<RuleSystem>,
+    session: Option<SessionRecord>,
+}
+
+// What's kept of a campaign once it has ended.
+#[derive(RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Archive {
+    pub name: String,
+    pub dms: Vec<String>,
+    pub rules: String,
+    // The usernames of the players who were in the game when it ended.
+    pub players: Vec<String>,
+    pub purse: Purse,
+    pub ledger: Vec<Transaction>,
+    pub snapshots: Vec<Snapshot>,
+    pub ended: u64,
 }
 
 impl Game {
     pub fn new(name: &str, dm_nick: &str) -> Game {
         Game {
             name: name.to_string(),
-            dm_nick: dm_nick.to_string(),
+            dms: vec![dm_nick.to_string()],
             users: HashMap::new(),
+            purse: Purse::new(),
+            ledger: Vec::new(),
+            pending_feats: Vec::new(),
+            pending_spells: Vec::new(),
+            rules: RuleSystem::standard(),
+            dice: DiceSource::Random.shared(),
+            session: None,
+            journal: Journal::new(name).shared(),
+            history: History::new(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    // Loads the game saved under the name. Anything that isn't saved, like the roll log and what can
+    // be undone, starts fresh. A running session picks up where its dice left off.
+    pub fn load(name: &str) -> Result<Game> {
+        let mut data = String::new();
+        let mut file = try!(File::open(&Path::new(&format!("{}game.json", Game::dir_for(name)))));
+        try!(file.read_to_string(&mut data));
+        let record: GameRecord = try!(decode(&data).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to decode game data."
+        )));
+        let mut game = Game::new(&record.name, "");
+        game.dms = record.dms;
+        game.purse = record.purse;
+        game.ledger = record.ledger;
+        game.rules = record.rules.unwrap_or_else(RuleSystem::standard);
+        if let Some(session) = record.session {
+            {
+                let mut dice = game.dice.borrow_mut();
+                dice.source = DiceSource::Seeded(session.seed, session.drawn);
+                dice.recorded = session.rolls;
+                dice.file = Some(Game::roll_file(&record.name, session.started));
+            }
+            game.journal = Journal::resume(&record.name, session.started).shared();
+            game.session = Some(Session {
+                seed: session.seed, commitment: session.commitment, started: session.started, first_roll: 0,
+                drawn: session.drawn,
+            });
+        }
+        try!(game.load_snapshots());
+        Ok(game)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let record = GameRecord {
+            name: self.name.clone(), dms: self.dms.clone(), purse: self.purse, ledger: self.ledger.clone(),
+            rules: Some(self.rules.clone()), session: self.session.as_ref().map(|s| SessionRecord {
+                seed: s.seed, commitment: s.commitment.clone(), started: s.started, drawn: s.drawn,
+                rolls: self.dice.borrow().recorded - s.first_roll,
+            }),
+        };
+        try!(create_dir_all(&Path::new(&self.dir())));
+        let mut f = try!(File::create(&Path::new(&format!("{}game.json", self.dir()))));
+        f.write_all(try!(encode(&record).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to encode game data."
+        ))).as_bytes())
+    }
+
+    // Where the game's files are kept, e.g. games/my-game/.
+    pub fn dir(&self) -> String {
+        Game::dir_for(&self.name)
+    }
+
+    fn dir_for(name: &str) -> String {
+        format!("games/{}/", slug(name))
+    }
+
+    // Where the rolls of the session started at the given time are written, next to its journal.
+    fn roll_file(name: &str, started: u64) -> String {
+        format!("sessions/{}-{}-rolls.json", slug(name), started)
+    }
+
+    // Saves the game if its session has drawn dice since it was last saved, so that a restart
+    // doesn't lose its place in the seed's rolls.
+    pub fn save_draws(&mut self) -> Result<()> {
+        let drawn = self.dice.borrow().source.drawn();
+        match self.session {
+            Some(ref mut session) if session.drawn != drawn => session.drawn = drawn,
+            _ => return Ok(()),
         }
+        self.save()
+    }
+
+    // Adds coins changing hands to the ledger and saves it, so the record survives a restart.
+    pub fn record(&mut self, transaction: Transaction) -> Result<()> {
+        self.ledger.push(transaction);
+        self.save()
     }
 
     pub fn login(&mut self, account: Player, nickname: &str, password: &str) -> BotResult<&str> {
-        if account.password == try!(as_io(Game::password_hash(password))) {
+        if account.rules().name != self.rules.name {
+            Err(InvalidInput(format!("{} uses {} rules, but {} uses {}.", account.username, account.rules().name,
+                                     self.name, self.rules.name)))
+        } else if account.password == try!(as_io(Game::password_hash(password))) {
             self.users.insert(nickname.to_string(), account);
             Ok("Login successful.")
         } else {
@@ -33,30 +198,238 @@ impl Game {
         }
     }
 
+    pub fn request_feat(&mut self, nickname: &str, feat: &str) {
+        if self.pending_feat(nickname, feat).is_none() {
+            self.pending_feats.push((nickname.to_string(), feat.to_string()));
+        }
+    }
+
+    fn pending_feat(&self, nickname: &str, feat: &str) -> Option<usize> {
+        self.pending_feats.iter().position(|&(ref n, ref f)| n == nickname && f.eq_ignore_ascii_case(feat))
+    }
+
+    pub fn feat_request(&self, nickname: &str, feat: &str) -> Option<&str> {
+        self.pending_feat(nickname, feat).map(|i| &self.pending_feats[i].1[..])
+    }
+
+    // Removes a pending feat request, returning the name of the feat that was requested.
+    pub fn take_feat_request(&mut self, nickname: &str, feat: &str) -> Option<String> {
+        self.pending_feat(nickname, feat).map(|i| self.pending_feats.remove(i).1)
+    }
+
+    // Asking for a spell again replaces the earlier request for it.
+    pub fn request_spell(&mut self, nickname: &str, spell: Spell) {
+        self.pending_spells.retain(|&(ref n, ref s)| n != nickname || !s.is_named(&spell.name));
+        self.pending_spells.push((nickname.to_string(), spell));
+    }
+
+    pub fn spell_request(&self, nickname: &str, spell: &str) -> Option<&Spell> {
+        self.pending_spells.iter().find(|&&(ref n, ref s)| n == nickname && s.is_named(spell)).map(|&(_, ref s)| s)
+    }
+
+    pub fn take_spell_request(&mut self, nickname: &str, spell: &str) -> Option<Spell> {
+        let index = self.pending_spells.iter().position(|&(ref n, ref s)| n == nickname && s.is_named(spell));
+        index.map(|i| self.pending_spells.remove(i).1)
+    }
+
     pub fn password_hash(password: &str) -> Result<String> {
         let mut hasher = Hasher::new(Type::SHA512);
         try!(hasher.write_all(password.as_bytes()));
         Ok(hasher.finish().to_hex())
     }
 
-    pub fn roll() -> u8 {
-        let d20 = Range::new(1i8, 21i8);
-        let mut rng = thread_rng();
-        match d20.ind_sample(&mut rng) as u8 {
-            0 => 1,
-            n => n,
+    // Seeds the game's dice so that its rolls can be reproduced, or makes them random again.
+    pub fn set_seed(&mut self, seed: Option<usize>) {
+        self.dice.borrow_mut().source = seed.map_or(DiceSource::Random, DiceSource::seeded);
+    }
+
+    pub fn seed(&self) -> Option<usize> {
+        self.dice.borrow().source.seed()
+    }
+
+    // Seeds the dice with a fresh seed, returning the hash of it that players can check it against.
+    pub fn start_session(&mut self) -> BotResult<&str> {
+        if self.session.is_some() {
+            return Err(InvalidInput(format!("A session is already running in {}.", self.name)));
         }
+        let seed = fresh_seed();
+        let commitment = try!(as_io(Game::password_hash(&seed.to_string())));
+        self.set_seed(Some(seed));
+        self.journal = Journal::new(&self.name).shared();
+        let started = self.journal.borrow().started;
+        let first_roll = {
+            let mut dice = self.dice.borrow_mut();
+            dice.file = Some(Game::roll_file(&self.name, started));
+            dice.recorded
+        };
+        self.session = Some(Session {
+            seed: seed, commitment: commitment, started: started, first_roll: first_roll, drawn: 0
+        });
+        try!(as_io(self.save()));
+        Ok(&self.session.as_ref().unwrap().commitment)
+    }
+
+    // Ends the session, going back to random rolls, and returns it so that its seed can be revealed.
+    // The session's journal is saved and a new one started.
+    pub fn end_session(&mut self) -> BotResult<Session> {
+        if self.session.is_some() {
+            try!(as_io(self.journal.borrow().save()));
+        }
+        match self.session.take() {
+            Some(session) => {
+                self.set_seed(None);
+                self.dice.borrow_mut().file = None;
+                self.journal = Journal::new(&self.name).shared();
+                try!(as_io(self.save()));
+                Ok(session)
+            },
+            None => Err(InvalidInput(format!("There is no session running in {}.", self.name))),
+        }
+    }
+
+    // Keeps and saves a snapshot, replacing any with the same name, and returns whether one was
+    // replaced. Once there are too many of its kind, the oldest of them is dropped.
+    pub fn add_snapshot(&mut self, snapshot: Snapshot) -> Result<bool> {
+        try!(create_dir_all(&Path::new(&format!("{}snapshots/", self.dir()))));
+        let mut f = try!(File::create(&Path::new(&self.snapshot_path(&snapshot.name))));
+        try!(f.write_all(try!(encode(&snapshot).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to encode snapshot."
+        ))).as_bytes()));
+        // Names that would be saved to the same file are the same snapshot.
+        let position = self.snapshots.iter().position(|s| slug(&s.name) == slug(&snapshot.name));
+        let replaced = match position {
+            Some(i) => {
+                self.snapshots.remove(i);
+                true
+            },
+            None => false,
+        };
+        let automatic = snapshot.automatic;
+        self.snapshots.push(snapshot);
+        let limit = if automatic { MAX_SESSION_SNAPSHOTS } else { MAX_SNAPSHOTS };
+        if self.snapshots.iter().filter(|s| s.automatic == automatic).count() > limit {
+            let oldest = self.snapshots.iter().position(|s| s.automatic == automatic).unwrap();
+            let dropped = self.snapshots.remove(oldest);
+            match remove_file(&Path::new(&self.snapshot_path(&dropped.name))) {
+                Err(ref e) if e.kind() != ErrorKind::NotFound => {
+                    return Err(Error::new(e.kind(), "Failed to remove an old snapshot."));
+                },
+                _ => (),
+            }
+        }
+        Ok(replaced)
+    }
+
+    fn snapshot_path(&self, name: &str) -> String {
+        format!("{}snapshots/{}.json", self.dir(), slug(name))
+    }
+
+    // Loads the snapshots saved for the game, oldest first.
+    fn load_snapshots(&mut self) -> Result<()> {
+        let entries = match read_dir(&Path::new(&format!("{}snapshots/", self.dir()))) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let mut data = String::new();
+            try!(try!(File::open(&try!(entry).path())).read_to_string(&mut data));
+            self.snapshots.push(try!(decode(&data).map_err(|_| Error::new(
+                ErrorKind::InvalidInput, "Failed to decode snapshot."
+            ))));
+        }
+        self.snapshots.sort_by(|a, b| a.time.cmp(&b.time));
+        Ok(())
+    }
+
+    pub fn snapshot(&self, name: &str) -> Option<&Snapshot> {
+        self.snapshots.iter().find(|s| s.name.eq_ignore_ascii_case(name))
+    }
+
+    // Writes a record of the campaign to campaigns/, returning where it was saved.
+    pub fn archive(&self, players: Vec<String>) -> Result<String> {
+        let archive = Archive {
+            name: self.name.clone(), dms: self.dms.clone(), rules: self.rules.name.clone(), players: players,
+            purse: self.purse, ledger: self.ledger.clone(), snapshots: self.snapshots.clone(), ended: now(),
+        };
+        try!(create_dir_all(&Path::new("campaigns/")));
+        let path = format!("campaigns/{}-{}.json", slug(&self.name), archive.ended);
+        let mut f = try!(File::create(&Path::new(&path)));
+        try!(f.write_all(try!(encode(&archive).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to encode campaign archive."
+        ))).as_bytes()));
+        Ok(path)
+    }
+
+    pub fn roll() -> i32 {
+        roll_d20(None, "", 0)
     }
 
     pub fn is_dm(&self, nickname: &str) -> bool {
-        &self.dm_nick == nickname
+        self.dms.iter().any(|dm| dm == nickname)
+    }
+
+    pub fn add_dm(&mut self, nickname: &str) -> BotResult<()> {
+        if self.is_dm(nickname) {
+            return Err(InvalidInput(format!("{} is already a DM of {}.", nickname, self.name)));
+        }
+        self.dms.push(nickname.to_string());
+        Ok(())
+    }
+
+    // Removes a DM on behalf of another. Only the first DM can remove others, though anyone can step
+    // down. A game always needs someone to run it, so the last DM can only hand it over.
+    pub fn remove_dm(&mut self, by: &str, nickname: &str) -> BotResult<()> {
+        let position = self.dms.iter().position(|dm| dm == nickname);
+        match position {
+            Some(0) if by != nickname => Err(InvalidInput(format!(
+                "Only {} can remove themselves as a DM of {}.", nickname, self.name
+            ))),
+            Some(_) if by != nickname && by != self.dms[0] => Err(InvalidInput(format!(
+                "Only {} or {} can remove {} as a DM of {}.", self.dms[0], nickname, nickname, self.name
+            ))),
+            Some(_) if self.dms.len() == 1 => Err(InvalidInput(format!(
+                "{} is the only DM of {}, so they can only hand it over.", nickname, self.name
+            ))),
+            Some(i) => {
+                self.dms.remove(i);
+                Ok(())
+            },
+            None => Err(InvalidInput(format!("{} is not a DM of {}.", nickname, self.name))),
+        }
+    }
+
+    // Gives one DM's place in the game to someone else.
+    pub fn transfer_dm(&mut self, from: &str, to: &str) -> BotResult<()> {
+        if self.is_dm(to) {
+            return Err(InvalidInput(format!("{} is already a DM of {}.", to, self.name)));
+        }
+        let position = self.dms.iter().position(|dm| dm == from);
+        match position {
+            Some(i) => {
+                self.dms[i] = to.to_string();
+                Ok(())
+            },
+            None => Err(InvalidInput(format!("{} is not a DM of {}.", from, self.name))),
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Game;
+    use std::borrow::ToOwned;
+    use std::collections::HashMap;
+    use std::fs::{File, create_dir_all, remove_file};
+    use std::io::prelude::*;
+    use super::{Archive, Game};
+    use data::coins::{Purse, Transaction};
+    use data::dice::{DiceSource, record_remaining, roll_die, use_source};
+    use data::history::{ChannelState, MAX_SESSION_SNAPSHOTS, MAX_SNAPSHOTS, Snapshot};
+    use data::journal::EventKind;
     use data::player::Player;
+    use data::rules::RuleSystem;
+    use data::spell::{Spell, SpellEffect};
+    use rustc_serialize::json::{decode, encode};
 
     #[test]
     fn password_hash() {
@@ -82,10 +455,219 @@ mod test {
         g.login(p, "test", "test").unwrap();
     }
 
+    #[test]
+    fn login_failed_wrong_rules() {
+        let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        let mut g = Game::new("Dungeons and Tests", "test");
+        g.rules = RuleSystem::call_of_cthulhu();
+        assert_eq!(format!("{}", g.login(p, "test", "test").unwrap_err()),
+                   format!("test uses 5e rules, but Dungeons and Tests uses coc."));
+    }
+
     #[test]
     fn is_dm() {
         let g = Game::new("Dungeons and Tests", "test");
         assert!(g.is_dm("test"));
         assert!(!g.is_dm("test2"));
     }
+
+    #[test]
+    fn archive() {
+        let mut g = Game::new("test archive", "test");
+        g.add_dm("test2").unwrap();
+        g.record(Transaction::new("test", "party", g.purse, "loot")).unwrap();
+        g.add_snapshot(snapshot("boss")).unwrap();
+        let path = g.archive(vec![format!("test3")]).unwrap();
+        assert!(path.starts_with("campaigns/test-archive-"));
+        let mut data = String::new();
+        File::open(&path).unwrap().read_to_string(&mut data).unwrap();
+        let archive: Archive = decode(&data).unwrap();
+        assert_eq!(archive.dms, vec![format!("test"), format!("test2")]);
+        assert_eq!(archive.players, vec![format!("test3")]);
+        assert_eq!(archive.rules, format!("5e"));
+        assert_eq!(archive.snapshots, g.snapshots);
+        assert_eq!(archive.ledger, g.ledger);
+    }
+
+    #[test]
+    fn save_load() {
+        let mut g = Game::new("test save game", "test");
+        g.add_dm("test2").unwrap();
+        g.purse.gp = 5;
+        g.rules = RuleSystem::call_of_cthulhu();
+        g.record(Transaction::new("test", "party", g.purse, "loot")).unwrap();
+        assert_eq!(g.dir(), format!("games/test-save-game/"));
+        let l = Game::load("Test Save Game").unwrap();
+        assert_eq!(l.name, g.name);
+        assert_eq!(l.dms, g.dms);
+        assert_eq!(l.purse, g.purse);
+        assert_eq!(l.ledger, g.ledger);
+        assert_eq!(l.rules, g.rules);
+        assert!(Game::load("test missing game").is_err());
+    }
+
+    #[test]
+    fn load_without_rules() {
+        create_dir_all("games/test-old-game").unwrap();
+        let data = format!("{{\"name\":\"test old game\",\"dms\":[\"test\"],\"purse\":{},\"ledger\":[]}}",
+                           encode(&Purse::new()).unwrap());
+        File::create("games/test-old-game/game.json").unwrap().write_all(data.as_bytes()).unwrap();
+        assert!(Game::load("test old game").unwrap().rules.is_standard());
+    }
+
+    #[test]
+    fn co_dms() {
+        let mut g = Game::new("Dungeons and Tests", "test");
+        assert!(g.remove_dm("test", "test").is_err());
+        g.add_dm("test2").unwrap();
+        assert!(g.add_dm("test2").is_err());
+        assert!(g.is_dm("test") && g.is_dm("test2"));
+        g.add_dm("test4").unwrap();
+        assert!(g.remove_dm("test2", "test").is_err());
+        assert!(g.remove_dm("test2", "test4").is_err());
+        g.remove_dm("test", "test4").unwrap();
+        g.remove_dm("test", "test").unwrap();
+        assert!(!g.is_dm("test"));
+        assert!(g.remove_dm("test2", "test").is_err());
+        assert!(g.transfer_dm("test", "test3").is_err());
+        g.transfer_dm("test2", "test3").unwrap();
+        assert_eq!(g.dms, vec![format!("test3")]);
+        assert!(g.transfer_dm("test3", "test3").is_err());
+    }
+
+    fn snapshot(name: &str) -> Snapshot {
+        let state = ChannelState { players: HashMap::new(), monsters: Vec::new() };
+        Snapshot {
+            name: name.to_owned(), automatic: false, time: 0, state: state, purse: Purse::new(), ledger: Vec::new()
+        }
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut g = Game::new("test snapshot game", "test");
+        assert!(!g.add_snapshot(snapshot("boss")).unwrap());
+        let mut again = snapshot("Boss");
+        again.time = 5;
+        assert!(g.add_snapshot(again).unwrap());
+        assert_eq!(g.snapshots.len(), 1);
+        assert_eq!(g.snapshot("BOSS").unwrap().time, 5);
+        assert!(g.snapshot("dragon").is_none());
+        for i in 0..MAX_SESSION_SNAPSHOTS + 1 {
+            let mut automatic = snapshot(&format!("session-{}", i));
+            automatic.automatic = true;
+            g.add_snapshot(automatic).unwrap();
+        }
+        assert!(g.snapshot("boss").is_some());
+        assert!(g.snapshot("session-0").is_none());
+        for i in 0..MAX_SNAPSHOTS {
+            g.add_snapshot(snapshot(&format!("room{}", i))).unwrap();
+        }
+        assert_eq!(g.snapshots.len(), MAX_SNAPSHOTS + MAX_SESSION_SNAPSHOTS);
+        assert!(g.snapshot("boss").is_none());
+        assert!(g.snapshot("session-1").is_some());
+    }
+
+    #[test]
+    fn snapshots_are_saved() {
+        let mut g = Game::new("test saved snapshots", "test");
+        g.save().unwrap();
+        let mut later = snapshot("boss");
+        later.time = 5;
+        g.add_snapshot(later).unwrap();
+        g.add_snapshot(snapshot("Red Dragon")).unwrap();
+        assert!(g.add_snapshot(snapshot("red-dragon")).unwrap());
+        let l = Game::load("test saved snapshots").unwrap();
+        assert_eq!(l.snapshots, vec![g.snapshots[1].clone(), g.snapshots[0].clone()]);
+    }
+
+    #[test]
+    fn feat_requests() {
+        let mut g = Game::new("Dungeons and Tests", "test");
+        g.request_feat("test", "Alert");
+        g.request_feat("test", "alert");
+        assert_eq!(g.pending_feats.len(), 1);
+        assert_eq!(g.take_feat_request("test2", "Alert"), None);
+        assert_eq!(g.feat_request("test", "alert"), Some("Alert"));
+        assert_eq!(g.take_feat_request("test", "ALERT"), Some("Alert".to_string()));
+        assert!(g.pending_feats.is_empty());
+    }
+
+    #[test]
+    fn spell_requests() {
+        let mut g = Game::new("Dungeons and Tests", "test");
+        g.request_spell("test", Spell::new("Sleep", 1, SpellEffect::Utility));
+        g.request_spell("test", Spell::new("sleep", 1, SpellEffect::Utility));
+        assert_eq!(g.pending_spells.len(), 1);
+        assert!(g.spell_request("test", "SLEEP").is_some());
+        assert_eq!(g.take_spell_request("test2", "Sleep"), None);
+        assert_eq!(g.take_spell_request("test", "Sleep").unwrap().name, "sleep");
+        assert!(g.pending_spells.is_empty());
+    }
+
+    #[test]
+    fn session_commitment() {
+        let mut g = Game::new("test session game", "test");
+        let commitment = g.start_session().unwrap().to_owned();
+        let seed = g.seed().unwrap();
+        assert_eq!(commitment, Game::password_hash(&seed.to_string()).unwrap());
+        assert!(g.start_session().is_err());
+        let session = g.end_session().unwrap();
+        assert_eq!(session.seed, seed);
+        assert_eq!(session.commitment, commitment);
+        assert_eq!(g.seed(), None);
+        assert!(g.end_session().is_err());
+    }
+
+    #[test]
+    fn session_resumes() {
+        let mut g = Game::new("test resumed game", "test");
+        let commitment = g.start_session().unwrap().to_owned();
+        use_source(Some(g.dice.clone()));
+        let first: Vec<i32> = (0..3).map(|_| roll_die(20)).collect();
+        record_remaining();
+        g.save_draws().unwrap();
+        let l = Game::load("test resumed game").unwrap();
+        assert_eq!(l.seed(), g.seed());
+        assert_eq!(l.session.as_ref().unwrap().commitment, commitment);
+        assert_eq!(l.dice.borrow().recorded, 1);
+        let mut rolls = String::new();
+        File::open(l.dice.borrow().file.as_ref().unwrap()).unwrap().read_to_string(&mut rolls).unwrap();
+        assert_eq!(rolls.lines().count(), 1);
+        // Nothing has been drawn since, so there's nothing to write.
+        remove_file(&format!("{}game.json", g.dir())).unwrap();
+        g.save_draws().unwrap();
+        assert!(File::open(&format!("{}game.json", g.dir())).is_err());
+        let rest: Vec<i32> = (0..2).map(|_| roll_die(20)).collect();
+        use_source(Some(l.dice.clone()));
+        assert_eq!((0..2).map(|_| roll_die(20)).collect::<Vec<_>>(), rest);
+        use_source(Some(DiceSource::seeded(g.seed().unwrap()).shared()));
+        let replayed: Vec<i32> = (0..5).map(|_| roll_die(20)).collect();
+        assert_eq!(replayed, first.into_iter().chain(rest).collect::<Vec<_>>());
+        g.end_session().unwrap();
+        assert!(Game::load("test resumed game").unwrap().session.is_none());
+    }
+
+    #[test]
+    fn session_journal() {
+        let mut g = Game::new("test journal", "test");
+        g.journal.borrow_mut().log(EventKind::Dm, "test", "Before the session.");
+        g.start_session().unwrap();
+        assert!(g.journal.borrow().events.is_empty());
+        g.journal.borrow_mut().log(EventKind::Dm, "test", "During the session.");
+        let path = g.journal.borrow().path();
+        g.end_session().unwrap();
+        assert!(g.journal.borrow().events.is_empty());
+        assert!(File::open(&format!("{}.json", path)).is_ok());
+    }
+
+    #[test]
+    fn rolls_are_logged() {
+        let g = Game::new("Dungeons and Tests", "test");
+        use_source(Some(g.dice.clone()));
+        let r = Game::roll();
+        let log = &g.dice.borrow().log;
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].dice, vec![r]);
+        assert_eq!(log[0].total, r);
+    }
 }
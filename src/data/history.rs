@@ -0,0 +1,212 @@
+use std::borrow::ToOwned;
+use std::cmp::max;
+use std::collections::HashMap;
+use data::coins::{Purse, Transaction};
+use data::monster::Monster;
+use data::player::Player;
+
+pub const MAX_HISTORY: usize = 50;
+pub const MAX_SNAPSHOTS: usize = 10;
+// Snapshots taken automatically when sessions start are kept separately, so they can't crowd out
+// the DM's own.
+pub const MAX_SESSION_SNAPSHOTS: usize = 5;
+
+// The players and monsters of a channel at one point in time.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct ChannelState {
+    pub players: HashMap<String, Player>,
+    pub monsters: Vec<Monster>,
+}
+
+// One entity as it was before and after an action. Monsters that didn't exist on one side, because
+// the action spawned them, are None.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Player { nick: String, before: Player, after: Player },
+    Monster { index: usize, before: Option<Monster>, after: Option<Monster> },
+}
+
+// An action that can be undone: the command that was used and everything it changed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revision {
+    pub user: String,
+    pub command: String,
+    pub changes: Vec<Change>,
+}
+
+impl ChannelState {
+    // Works out what changed between this state and a later one. Players that logged in or out in
+    // between weren't changed by the action, so they're left out.
+    pub fn changes(&self, after: &ChannelState) -> Vec<Change> {
+        let mut changes = Vec::new();
+        let mut nicks: Vec<_> = self.players.keys().collect();
+        nicks.sort();
+        for nick in nicks.into_iter() {
+            match (self.players.get(nick), after.players.get(nick)) {
+                (Some(before), Some(now)) if before != now => changes.push(Change::Player {
+                    nick: nick.clone(), before: before.clone(), after: now.clone()
+                }),
+                _ => (),
+            }
+        }
+        for i in 0..max(self.monsters.len(), after.monsters.len()) {
+            let (before, now) = (self.monsters.get(i), after.monsters.get(i));
+            if before != now {
+                changes.push(Change::Monster { index: i, before: before.cloned(), after: now.cloned() });
+            }
+        }
+        changes
+    }
+}
+
+// A checkpoint of a whole game that the DM can roll back to.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Snapshot {
+    pub name: String,
+    // Whether it was taken when a session started, rather than by the DM.
+    pub automatic: bool,
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub state: ChannelState,
+    pub purse: Purse,
+    pub ledger: Vec<Transaction>,
+}
+
+// The actions taken in a game that can be undone, and those that have been undone and can be redone.
+pub struct History {
+    undo: Vec<Revision>,
+    redo: Vec<Revision>,
+    // The state before the command being handled, with who used it and how.
+    pending: Option<(String, String, ChannelState)>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History { undo: Vec::new(), redo: Vec::new(), pending: None }
+    }
+
+    pub fn begin(&mut self, user: &str, command: &str, state: ChannelState) {
+        self.pending = Some((user.to_owned(), command.to_owned(), state));
+    }
+
+    // Finishes the pending action given the state after it, recording it if it changed anything.
+    // New actions can't be redone over, so anything undone is forgotten.
+    pub fn settle(&mut self, after: &ChannelState) {
+        if let Some((user, command, before)) = self.pending.take() {
+            let changes = before.changes(after);
+            if !changes.is_empty() {
+                self.redo.clear();
+                self.undo.push(Revision { user: user, command: command, changes: changes });
+                if self.undo.len() > MAX_HISTORY {
+                    self.undo.remove(0);
+                }
+            }
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // The action that would be undone next, or redone next.
+    pub fn peek(&self, redo: bool) -> Option<&Revision> {
+        if redo { self.redo.last() } else { self.undo.last() }
+    }
+
+    // Takes the last action to undo it, keeping it to be redone.
+    pub fn undo(&mut self) -> Option<Revision> {
+        self.undo.pop().map(|revision| {
+            self.redo.push(revision.clone());
+            revision
+        })
+    }
+
+    pub fn redo(&mut self) -> Option<Revision> {
+        self.redo.pop().map(|revision| {
+            self.undo.push(revision.clone());
+            revision
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::ToOwned;
+    use std::collections::HashMap;
+    use super::{Change, ChannelState, History, MAX_HISTORY};
+    use data::Entity;
+    use data::monster::Monster;
+    use data::player::Player;
+
+    fn state() -> ChannelState {
+        let mut players = HashMap::new();
+        players.insert("test".to_owned(), Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        ChannelState { players: players, monsters: vec![Monster::create("Zombie", 20, 30, 12, 12, 12, 12, 12, 12)] }
+    }
+
+    #[test]
+    fn changes() {
+        let before = state();
+        let mut after = state();
+        assert!(before.changes(&after).is_empty());
+        after.players.get_mut("test").unwrap().damage(5, None);
+        after.monsters.push(Monster::create("Ghoul", 20, 30, 12, 12, 12, 12, 12, 12));
+        let changes = before.changes(&after);
+        assert_eq!(changes.len(), 2);
+        match changes[0] {
+            Change::Player { ref nick, ref before, ref after } => {
+                assert_eq!(&nick[..], "test");
+                assert_eq!(before.stats().health - after.stats().health, 5);
+            },
+            _ => panic!("The player's damage wasn't recorded."),
+        }
+        assert_eq!(changes[1], Change::Monster { index: 1, before: None, after: after.monsters.get(1).cloned() });
+    }
+
+    #[test]
+    fn changes_ignore_logins() {
+        let before = state();
+        let mut after = state();
+        after.players.clear();
+        assert!(before.changes(&after).is_empty());
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut h = History::new();
+        let mut after = state();
+        h.begin("test", ".move @0 1 1", state());
+        h.settle(&state());
+        assert!(h.undo().is_none());
+        h.begin("test", ".damage @0 5", state());
+        after.monsters[0].damage(5, None);
+        h.settle(&after);
+        assert!(!h.is_pending());
+        let revision = h.undo().unwrap();
+        assert_eq!(revision.command, format!(".damage @0 5"));
+        assert!(h.undo().is_none());
+        assert_eq!(h.redo(), Some(revision.clone()));
+        assert!(h.redo().is_none());
+        assert_eq!(h.undo(), Some(revision));
+        // A new action means nothing can be redone.
+        h.begin("test", ".damage @0 3", state());
+        h.settle(&after);
+        assert!(h.redo().is_none());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut h = History::new();
+        let mut after = state();
+        after.monsters.clear();
+        for _ in 0..MAX_HISTORY + 5 {
+            h.begin("test", "addmonster", after.clone());
+            h.settle(&state());
+        }
+        let mut count = 0;
+        while h.undo().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, MAX_HISTORY);
+    }
+}
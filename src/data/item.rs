@@ -0,0 +1,288 @@
+use std::ascii::AsciiExt;
+use std::cmp::min;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::BotResult;
+use data::BotError::{InvalidInput, NotFound};
+use data::damage::DamageType;
+use data::dice::Dice;
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum ItemKind {
+    Gear,
+    // Light armor has no dexterity cap, medium armor caps it at 2, and heavy armor at 0.
+    Armor { base: u8, max_dex: Option<u8> },
+    Shield { bonus: u8 },
+    Weapon { damage: Dice, damage_type: DamageType, finesse: bool, reach: u8 },
+}
+
+impl ItemKind {
+    // Parses the kind option on an item, e.g. armor=14/2, shield=2 or weapon=1d8/slashing/finesse.
+    pub fn parse(opt: &str) -> Option<ItemKind> {
+        let split: Vec<_> = opt.splitn(2, '=').collect();
+        if split.len() != 2 {
+            return None;
+        }
+        let parts: Vec<_> = split[1].split('/').collect();
+        match &split[0].to_ascii_lowercase()[..] {
+            "armor" => match (parts[0].parse::<u8>(), parts.get(1).map(|s| s.parse::<u8>())) {
+                (Ok(base), None) => Some(ItemKind::Armor { base: base, max_dex: None }),
+                (Ok(base), Some(Ok(max))) => Some(ItemKind::Armor { base: base, max_dex: Some(max) }),
+                _ => None,
+            },
+            "shield" => parts[0].parse::<u8>().ok().map(|b| ItemKind::Shield { bonus: b }),
+            "weapon" if parts.len() >= 2 => {
+                let finesse = parts[2..].iter().any(|p| p.eq_ignore_ascii_case("finesse"));
+                let reach = if parts[2..].iter().any(|p| p.eq_ignore_ascii_case("reach")) { 10 } else { 5 };
+                match (Dice::parse(parts[0]), DamageType::to_damage_type(parts[1])) {
+                    (Some(damage), Some(damage_type)) => Some(ItemKind::Weapon {
+                        damage: damage, damage_type: damage_type, finesse: finesse, reach: reach
+                    }),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Item {
+    pub name: String,
+    pub quantity: u16,
+    pub weight: f32,
+    pub value: u32,
+    pub notes: String,
+    pub kind: ItemKind,
+    pub equipped: bool,
+}
+
+impl Item {
+    pub fn new(name: &str, quantity: u16, weight: f32, value: u32, kind: ItemKind) -> Item {
+        Item {
+            name: name.to_string(),
+            quantity: quantity,
+            weight: weight,
+            value: value,
+            notes: String::new(),
+            kind: kind,
+            equipped: false,
+        }
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    fn hands(&self) -> u8 {
+        match self.kind {
+            ItemKind::Shield { .. } | ItemKind::Weapon { .. } if self.equipped => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        try!(write!(fmt, "{} x{} ({} lb., {} gp", self.name, self.quantity, self.weight, self.value));
+        if self.equipped {
+            try!(write!(fmt, ", equipped"));
+        }
+        try!(write!(fmt, ")"));
+        if !self.notes.is_empty() {
+            try!(write!(fmt, " - {}", self.notes));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory { items: Vec::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Item> {
+        self.items.iter().find(|i| i.is_named(name))
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.items.iter().fold(0.0, |acc, i| acc + i.weight * i.quantity as f32)
+    }
+
+    // Items with the same name and kind stack, keeping the notes of the first one.
+    pub fn add(&mut self, item: Item) {
+        if let Some(existing) = self.items.iter_mut().find(|i| i.is_named(&item.name) && i.kind == item.kind) {
+            existing.quantity = existing.quantity.saturating_add(item.quantity);
+            return;
+        }
+        self.items.push(item);
+    }
+
+    pub fn remove(&mut self, name: &str, quantity: u16) -> BotResult<Item> {
+        let i = match self.items.iter().position(|i| i.is_named(name)) {
+            Some(i) => i,
+            None => return Err(NotFound(format!("There is no {} in the inventory.", name))),
+        };
+        if self.items[i].quantity < quantity {
+            return Err(InvalidInput(format!("There are only {} {} in the inventory.",
+                                            self.items[i].quantity, self.items[i].name)));
+        } else if self.items[i].quantity == quantity {
+            let mut item = self.items.remove(i);
+            item.equipped = false;
+            return Ok(item);
+        }
+        self.items[i].quantity -= quantity;
+        let mut item = self.items[i].clone();
+        item.quantity = quantity;
+        item.equipped = false;
+        Ok(item)
+    }
+
+    // Armor and shields replace whatever armor or shield was worn before. Shields and weapons
+    // each take a hand, and there are only two of those.
+    pub fn equip(&mut self, name: &str) -> BotResult<()> {
+        let i = match self.items.iter().position(|i| i.is_named(name)) {
+            Some(i) => i,
+            None => return Err(NotFound(format!("There is no {} in the inventory.", name))),
+        };
+        let kind = self.items[i].kind;
+        match kind {
+            ItemKind::Gear => return Err(InvalidInput(format!("{} cannot be equipped.", self.items[i].name))),
+            ItemKind::Armor { .. } => for item in self.items.iter_mut() {
+                if let ItemKind::Armor { .. } = item.kind { item.equipped = false; }
+            },
+            ItemKind::Shield { .. } => for item in self.items.iter_mut() {
+                if let ItemKind::Shield { .. } = item.kind { item.equipped = false; }
+            },
+            ItemKind::Weapon { .. } => (),
+        }
+        if !self.items[i].equipped {
+            let hands = self.items.iter().fold(0, |acc, item| acc + item.hands());
+            let needs = match kind { ItemKind::Armor { .. } => 0, _ => 1 };
+            if hands + needs > 2 {
+                return Err(InvalidInput(format!("Both hands are already full.")));
+            }
+        }
+        self.items[i].equipped = true;
+        Ok(())
+    }
+
+    pub fn unequip(&mut self, name: &str) -> BotResult<()> {
+        match self.items.iter_mut().find(|i| i.is_named(name) && i.equipped) {
+            Some(item) => {
+                item.equipped = false;
+                Ok(())
+            },
+            None => Err(NotFound(format!("{} is not equipped.", name))),
+        }
+    }
+
+    pub fn armor_class(&self, dex_bonus: i32) -> i32 {
+        let mut ac = 10 + dex_bonus;
+        for item in self.items.iter().filter(|i| i.equipped) {
+            match item.kind {
+                ItemKind::Armor { base, max_dex: None } => ac += base as i32 - 10,
+                ItemKind::Armor { base, max_dex: Some(max) } => ac = base as i32 + min(dex_bonus, max as i32),
+                _ => (),
+            }
+        }
+        for item in self.items.iter().filter(|i| i.equipped) {
+            if let ItemKind::Shield { bonus } = item.kind {
+                ac += bonus as i32;
+            }
+        }
+        if ac < 0 { 0 } else { ac }
+    }
+
+    pub fn weapons(&self) -> Vec<&Item> {
+        self.items.iter().filter(|i| i.equipped).filter(|i| match i.kind {
+            ItemKind::Weapon { .. } => true,
+            _ => false,
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Inventory, Item, ItemKind};
+    use data::BotError::NotFound;
+    use data::damage::DamageType::Slashing;
+    use data::dice::Dice;
+
+    fn longsword() -> Item {
+        Item::new("Longsword", 1, 3.0, 15, ItemKind::Weapon {
+            damage: Dice::new(1, 8, 0), damage_type: Slashing, finesse: false, reach: 5
+        })
+    }
+
+    #[test]
+    fn parse_kind() {
+        assert_eq!(ItemKind::parse("armor=14/2"), Some(ItemKind::Armor { base: 14, max_dex: Some(2) }));
+        assert_eq!(ItemKind::parse("shield=2"), Some(ItemKind::Shield { bonus: 2 }));
+        assert_eq!(ItemKind::parse("weapon=1d8/slashing"), Some(longsword().kind));
+        assert_eq!(ItemKind::parse("weapon=1d8"), None);
+        assert_eq!(ItemKind::parse("test"), None);
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let mut inv = Inventory::new();
+        inv.add(Item::new("Torch", 5, 1.0, 0, ItemKind::Gear));
+        inv.add(Item::new("torch", 2, 1.0, 0, ItemKind::Gear));
+        assert_eq!(inv.get("Torch").unwrap().quantity, 7);
+        assert_eq!(inv.weight(), 7.0);
+        assert_eq!(inv.remove("torch", 3).unwrap().quantity, 3);
+        assert!(inv.remove("torch", 5).is_err());
+        assert_eq!(inv.remove("torch", 4).unwrap().quantity, 4);
+        assert_eq!(inv.remove("torch", 1), Err(NotFound(format!("There is no torch in the inventory."))));
+    }
+
+    #[test]
+    fn armor_class() {
+        let mut inv = Inventory::new();
+        assert_eq!(inv.armor_class(3), 13);
+        inv.add(Item::new("Leather", 1, 10.0, 10, ItemKind::Armor { base: 11, max_dex: None }));
+        inv.add(Item::new("Breastplate", 1, 20.0, 400, ItemKind::Armor { base: 14, max_dex: Some(2) }));
+        inv.add(Item::new("Shield", 1, 6.0, 10, ItemKind::Shield { bonus: 2 }));
+        inv.equip("leather").unwrap();
+        assert_eq!(inv.armor_class(3), 14);
+        inv.equip("breastplate").unwrap();
+        assert!(!inv.get("leather").unwrap().equipped);
+        assert_eq!(inv.armor_class(3), 16);
+        inv.equip("shield").unwrap();
+        assert_eq!(inv.armor_class(3), 18);
+    }
+
+    #[test]
+    fn equip_hands() {
+        let mut inv = Inventory::new();
+        inv.add(longsword());
+        inv.add(Item::new("Dagger", 2, 1.0, 2, ItemKind::Weapon {
+            damage: Dice::new(1, 4, 0), damage_type: Slashing, finesse: true, reach: 5
+        }));
+        inv.add(Item::new("Shield", 1, 6.0, 10, ItemKind::Shield { bonus: 2 }));
+        inv.add(Item::new("Rope", 1, 10.0, 1, ItemKind::Gear));
+        inv.equip("longsword").unwrap();
+        inv.equip("dagger").unwrap();
+        assert_eq!(inv.weapons().len(), 2);
+        assert!(inv.equip("shield").is_err());
+        assert!(inv.equip("rope").is_err());
+        inv.unequip("dagger").unwrap();
+        inv.equip("shield").unwrap();
+        assert_eq!(inv.weapons().len(), 1);
+    }
+
+    #[test]
+    fn display() {
+        let mut item = longsword();
+        item.equipped = true;
+        item.notes = "Family heirloom".to_string();
+        assert_eq!(format!("{}", item), format!("Longsword x1 (3 lb., 15 gp, equipped) - Family heirloom"));
+    }
+}
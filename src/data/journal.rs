@@ -0,0 +1,311 @@
+use std::ascii::AsciiExt;
+use std::borrow::ToOwned;
+use std::cell::RefCell;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions, create_dir_all};
+use std::io::{Error, ErrorKind, Result};
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+use std::result::Result as StdResult;
+use data::utils::{clock_time, date, now, slug};
+use rustc_serialize::json::{decode, encode};
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum EventKind {
+    Login,
+    Logout,
+    Roll,
+    Damage,
+    Move,
+    // Anything the DM does to run the game, like awarding experience or changing the rules.
+    Dm,
+}
+
+impl Display for EventKind {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "{}", match *self {
+            EventKind::Login => "login",
+            EventKind::Logout => "logout",
+            EventKind::Roll => "roll",
+            EventKind::Damage => "damage",
+            EventKind::Move => "move",
+            EventKind::Dm => "dm",
+        })
+    }
+}
+
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Event {
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub kind: EventKind,
+    // The user whose message caused the event.
+    pub actor: String,
+    pub text: String,
+}
+
+impl Display for Event {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        write!(fmt, "[{}] {}: {}", clock_time(self.time), self.kind, self.text)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn to_format(s: &str) -> Option<Format> {
+        match &s.to_ascii_lowercase()[..] {
+            "markdown" | "md" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match *self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+        }
+    }
+}
+
+// Everything that happened in a game over one session, in order.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Journal {
+    pub game: String,
+    pub started: u64,
+    pub events: Vec<Event>,
+}
+
+pub type SharedJournal = Rc<RefCell<Journal>>;
+
+fn escape_html(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+// Escapes what Markdown would otherwise treat as formatting or raw HTML.
+fn escape_markdown(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '|' | '~' => {
+                res.push('\\');
+                res.push(c);
+            },
+            '&' | '<' | '>' | '"' => res.push_str(&escape_html(&c.to_string())),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+impl Journal {
+    pub fn new(game: &str) -> Journal {
+        Journal { game: game.to_owned(), started: now(), events: Vec::new() }
+    }
+
+    pub fn shared(self) -> SharedJournal {
+        Rc::new(RefCell::new(self))
+    }
+
+    // Picks a journal back up from the events written to disk since it was started.
+    pub fn resume(game: &str, started: u64) -> Journal {
+        let mut journal = Journal { game: game.to_owned(), started: started, events: Vec::new() };
+        let mut data = String::new();
+        if File::open(&Path::new(&journal.events_path())).and_then(|mut f| f.read_to_string(&mut data)).is_ok() {
+            journal.events = data.lines().filter_map(|line| decode(line).ok()).collect();
+        }
+        journal
+    }
+
+    // Adds an event and appends it to disk, so that it isn't lost if the bot stops before the
+    // journal is saved.
+    pub fn log(&mut self, kind: EventKind, actor: &str, text: &str) {
+        let event = Event { time: now(), kind: kind, actor: actor.to_owned(), text: text.to_owned() };
+        if let Err(e) = self.append(&event) {
+            println!("Failed to save event to {}: {}", self.events_path(), e);
+        }
+        self.events.push(event);
+    }
+
+    fn append(&self, event: &Event) -> Result<()> {
+        try!(create_dir_all(&Path::new("sessions/")));
+        let mut f = try!(OpenOptions::new().create(true).append(true).open(&Path::new(&self.events_path())));
+        let line = try!(encode(event).map_err(|_| Error::new(ErrorKind::InvalidInput, "Failed to encode event.")));
+        f.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    // Where events are appended as they happen, one JSON record per line.
+    fn events_path(&self) -> String {
+        format!("{}-events.json", self.path())
+    }
+
+    fn title(&self) -> String {
+        format!("{} session on {} at {}", self.game, date(self.started), clock_time(self.started))
+    }
+
+    // The path the journal is saved under, without an extension, e.g. sessions/my-game-1700000000.
+    pub fn path(&self) -> String {
+        format!("sessions/{}-{}", slug(&self.game), self.started)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut res = format!("# {}\n\n", escape_markdown(&self.title()));
+        if self.events.is_empty() {
+            res.push_str("Nothing happened.\n");
+        }
+        for event in self.events.iter() {
+            res.push_str(&format!("- `{}` **{}** {}\n", clock_time(event.time), event.kind,
+                                  escape_markdown(&event.text)));
+        }
+        res
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = escape_html(&self.title());
+        let mut res = format!("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{0}</title></head>\n\
+                               <body>\n<h1>{0}</h1>\n", title);
+        if self.events.is_empty() {
+            res.push_str("<p>Nothing happened.</p>\n");
+        } else {
+            res.push_str("<table>\n");
+            for event in self.events.iter() {
+                res.push_str(&format!(
+                    "<tr class=\"{0}\"><td>{1}</td><td>{0}</td><td>{2}</td></tr>\n",
+                    event.kind, clock_time(event.time), escape_html(&event.text)
+                ));
+            }
+            res.push_str("</table>\n");
+        }
+        res.push_str("</body>\n</html>\n");
+        res
+    }
+
+    pub fn save(&self) -> Result<()> {
+        try!(create_dir_all(&Path::new("sessions/")));
+        let mut f = try!(File::create(&Path::new(&format!("{}.json", self.path()))));
+        f.write_all(try!(encode(self).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to encode session journal."
+        ))).as_bytes())
+    }
+
+    // Saves the journal and writes it out in the given format, returning the path of the export.
+    pub fn export(&self, format: Format) -> Result<String> {
+        try!(self.save());
+        let path = format!("{}.{}", self.path(), format.extension());
+        let mut f = try!(File::create(&Path::new(&path)));
+        try!(f.write_all(match format {
+            Format::Markdown => self.to_markdown(),
+            Format::Html => self.to_html(),
+        }.as_bytes()));
+        Ok(path)
+    }
+}
+
+thread_local!(static CURRENT: RefCell<Option<(SharedJournal, String)>> = RefCell::new(None));
+
+// Sets the journal that events are logged to while handling a message, and who sent it.
+pub fn use_journal(journal: Option<SharedJournal>, actor: &str) {
+    CURRENT.with(|current| *current.borrow_mut() = journal.map(|j| (j, actor.to_owned())));
+}
+
+// Logs an event to the current journal, if there is one.
+pub fn log_event(kind: EventKind, text: &str) {
+    CURRENT.with(|current| {
+        if let Some((ref journal, ref actor)) = *current.borrow() {
+            journal.borrow_mut().log(kind, actor, text);
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Event, EventKind, Format, Journal, log_event, use_journal};
+    use std::borrow::ToOwned;
+    use std::fs::{File, remove_file};
+    use std::io::prelude::*;
+
+    fn journal() -> Journal {
+        let mut j = Journal::new("Dungeons & Tests");
+        j.started = 1700000000;
+        j.events.push(Event {
+            time: 1700000000, kind: EventKind::Login, actor: "test".to_owned(), text: "test logged in as <Test>.".to_owned()
+        });
+        j.events.push(Event {
+            time: 1700000065, kind: EventKind::Damage, actor: "test".to_owned(), text: "Test took 5 damage.".to_owned()
+        });
+        j
+    }
+
+    #[test]
+    fn to_markdown() {
+        assert_eq!(journal().to_markdown(), format!(
+            "# Dungeons &amp; Tests session on 2023-11-14 at 22:13:20\n\n\
+             - `22:13:20` **login** test logged in as &lt;Test&gt;.\n\
+             - `22:14:25` **damage** Test took 5 damage.\n"
+        ));
+        assert!(Journal::new("Empty").to_markdown().ends_with("Nothing happened.\n"));
+    }
+
+    #[test]
+    fn to_html() {
+        let html = journal().to_html();
+        assert!(html.contains("<h1>Dungeons &amp; Tests session on 2023-11-14 at 22:13:20</h1>"));
+        assert!(html.contains(
+            "<tr class=\"login\"><td>22:13:20</td><td>login</td><td>test logged in as &lt;Test&gt;.</td></tr>"
+        ));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn log_to_current() {
+        let j = Journal::new("test").shared();
+        use_journal(Some(j.clone()), "test");
+        log_event(EventKind::Move, "Test moved to (1, 2).");
+        use_journal(None, "");
+        log_event(EventKind::Move, "Test moved to (3, 4).");
+        let events = &j.borrow().events;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].actor, format!("test"));
+        assert_eq!(events[0].text, format!("Test moved to (1, 2)."));
+    }
+
+    #[test]
+    fn resume() {
+        let mut j = Journal::new("test resumed journal");
+        let _ = remove_file(j.events_path());
+        j.log(EventKind::Dm, "test", "The *first* event.");
+        j.log(EventKind::Move, "test", "Test moved to (1, 2).");
+        let r = Journal::resume("test resumed journal", j.started);
+        assert_eq!(r.events, j.events);
+        assert_eq!(r.started, j.started);
+    }
+
+    #[test]
+    fn export() {
+        let mut j = journal();
+        j.game = "test export".to_owned();
+        let path = j.export(Format::Markdown).unwrap();
+        assert_eq!(path, format!("sessions/test-export-1700000000.md"));
+        let mut data = String::new();
+        File::open(&path).unwrap().read_to_string(&mut data).unwrap();
+        assert_eq!(data, j.to_markdown());
+        assert!(File::open("sessions/test-export-1700000000.json").is_ok());
+    }
+}
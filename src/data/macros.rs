@@ -0,0 +1,196 @@
+use std::ascii::AsciiExt;
+use std::borrow::ToOwned;
+use data::{BotResult, Entity, RollType};
+use data::BotError::InvalidInput;
+use data::dice::{record, roll_die};
+use data::skill::Skill;
+use self::Term::{Ability, Die, Flat, Proficiency};
+
+pub const MAX_MACRO_ROLLS: usize = 10;
+const MAX_NAME_LENGTH: usize = 20;
+
+// A named set of rolls saved on a character. The rolls are kept as written and only worked out
+// when used, so references to stats follow the character as they change.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Macro {
+    pub name: String,
+    pub rolls: Vec<String>,
+}
+
+// The result of one of a macro's rolls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroRoll {
+    pub expression: String,
+    pub total: i32,
+    // The dice and bonuses that made up the total, as in [14]+1+2.
+    pub breakdown: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Term {
+    Die(u8, u8),
+    Flat(i32),
+    Ability(RollType),
+    Skill(Skill),
+    Proficiency,
+}
+
+impl Term {
+    fn parse(term: &str) -> BotResult<Term> {
+        let lower = term.to_ascii_lowercase();
+        if let Ok(n) = lower.parse() {
+            return Ok(Flat(n));
+        } else if lower == "prof" || lower == "proficiency" {
+            return Ok(Proficiency);
+        } else if let Some(stat) = RollType::to_roll_type(&lower) {
+            return Ok(Ability(stat));
+        } else if let Some(skill) = Skill::to_skill(&lower) {
+            return Ok(Term::Skill(skill));
+        }
+        let invalid = InvalidInput(format!("{} is not a die, number, stat, skill or prof.", term));
+        match lower.find('d') {
+            Some(i) => {
+                let count = if i == 0 { Ok(1) } else { lower[..i].parse() };
+                match (count, lower[i + 1..].parse()) {
+                    (Ok(c), Ok(s)) if c > 0 && s > 0 => Ok(Die(c, s)),
+                    _ => Err(invalid),
+                }
+            },
+            None => Err(invalid),
+        }
+    }
+}
+
+// Splits a roll like 1d20+dex-1 into its terms, each with whether it's subtracted.
+fn parse_roll(roll: &str) -> BotResult<Vec<(bool, Term)>> {
+    let mut terms = Vec::new();
+    let mut start = 0;
+    let mut negative = false;
+    for (i, c) in roll.char_indices().chain(Some((roll.len(), '+')).into_iter()) {
+        if i == 0 && c == '-' {
+            negative = true;
+            start = 1;
+        } else if c == '+' || c == '-' {
+            if i == start {
+                return Err(InvalidInput(format!("{} is not a valid roll.", roll)));
+            }
+            terms.push((negative, try!(Term::parse(&roll[start..i]))));
+            start = i + 1;
+            negative = c == '-';
+        }
+    }
+    Ok(terms)
+}
+
+fn add(total: i32, negative: bool, value: i32) -> i32 {
+    if negative { total.saturating_sub(value) } else { total.saturating_add(value) }
+}
+
+impl Macro {
+    // Makes a macro from rolls separated by semicolons, checking that every roll can be made.
+    pub fn new(name: &str, rolls: &str) -> BotResult<Macro> {
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(InvalidInput(format!(
+                "Macro names must be up to {} letters, numbers or underscores.", MAX_NAME_LENGTH
+            )));
+        }
+        let rolls: Vec<String> = rolls.split(';').map(|r| r.chars().filter(|c| !c.is_whitespace()).collect())
+            .filter(|r: &String| !r.is_empty()).collect();
+        if rolls.is_empty() || rolls.len() > MAX_MACRO_ROLLS {
+            return Err(InvalidInput(format!("Macros must have between 1 and {} rolls.", MAX_MACRO_ROLLS)));
+        }
+        for roll in rolls.iter() {
+            try!(parse_roll(roll));
+        }
+        Ok(Macro { name: name.to_ascii_lowercase(), rolls: rolls })
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    // Makes each of the macro's rolls for the entity, using its bonuses as they are now.
+    pub fn roll(&self, entity: &Entity) -> BotResult<Vec<MacroRoll>> {
+        let mut results = Vec::new();
+        for roll in self.rolls.iter() {
+            let (mut total, mut modifier, mut breakdown) = (0i32, 0i32, String::new());
+            for (negative, term) in try!(parse_roll(roll)).into_iter() {
+                if !breakdown.is_empty() || negative {
+                    breakdown.push_str(if negative { "-" } else { "+" });
+                }
+                let value = match term {
+                    Die(count, sides) => {
+                        let dice: Vec<_> = (0..count).map(|_| roll_die(sides as i32)).collect();
+                        let shown: Vec<_> = dice.iter().map(|d| d.to_string()).collect();
+                        breakdown.push_str(&format!("[{}]", shown.join(", ")));
+                        total = add(total, negative, dice.iter().fold(0, |acc, d| acc + d));
+                        continue;
+                    },
+                    Flat(n) => n,
+                    Ability(stat) => entity.ability_bonus(stat),
+                    Term::Skill(skill) => entity.skill_bonus(skill),
+                    Proficiency => entity.proficiency_bonus(),
+                };
+                breakdown.push_str(&value.to_string());
+                modifier = add(modifier, negative, value);
+                total = add(total, negative, value);
+            }
+            record(&format!("{} ({})", roll, self.name), Some(entity.identifier()), modifier, total);
+            results.push(MacroRoll { expression: roll.to_owned(), total: total, breakdown: breakdown });
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Macro;
+    use data::dice::script;
+    use data::player::Player;
+
+    #[test]
+    fn new_success() {
+        let m = Macro::new("Sneak", "1d20 + dex + prof ; 3d6").unwrap();
+        assert_eq!(m.name, format!("sneak"));
+        assert_eq!(m.rolls, vec![format!("1d20+dex+prof"), format!("3d6")]);
+        assert!(m.is_named("SNEAK"));
+        assert!(Macro::new("stab", "d20+str;2d6+stealth-1;5").is_ok());
+    }
+
+    #[test]
+    fn new_failed() {
+        assert!(Macro::new("sneak", "1d20+luck").is_err());
+        assert!(Macro::new("sneak", "1d20++dex").is_err());
+        assert!(Macro::new("sneak", "1d20+").is_err());
+        assert!(Macro::new("sneak", " ; ").is_err());
+        assert!(Macro::new("sneak", "0d6").is_err());
+        assert!(Macro::new("sneak", "1d300").is_err());
+        assert!(Macro::new("sneak attack", "1d20").is_err());
+        assert!(Macro::new("", "1d20").is_err());
+        assert!(Macro::new("sneak", "d20;d20;d20;d20;d20;d20;d20;d20;d20;d20;d20").is_err());
+    }
+
+    #[test]
+    fn roll_uses_current_stats() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 14, 12, 12, 12, 12);
+        let m = Macro::new("sneak", "1d20+dex+prof;2d6-1").unwrap();
+        script(vec![14, 3, 5]);
+        let rolls = m.roll(&p).unwrap();
+        assert_eq!(rolls[0].total, 18);
+        assert_eq!(rolls[0].breakdown, format!("[14]+2+2"));
+        assert_eq!(rolls[1].total, 7);
+        assert_eq!(rolls[1].breakdown, format!("[3, 5]-1"));
+        p.stats.update_stat("dexterity", 18).unwrap();
+        script(vec![14]);
+        assert_eq!(m.roll(&p).unwrap()[0].total, 20);
+    }
+
+    #[test]
+    fn roll_leading_negative() {
+        let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        script(vec![4]);
+        let rolls = Macro::new("test", "-1+d6").unwrap().roll(&p).unwrap();
+        assert_eq!(rolls[0].total, 3);
+        assert_eq!(rolls[0].breakdown, format!("-1+[4]"));
+    }
+}
@@ -1,18 +1,37 @@
 use std::ascii::AsciiExt;
+use std::cmp::max;
 use std::error::Error as StdError;
 use std::fmt::Error as FmtError;
 use std::fmt::{Display, Formatter};
 use std::io::{Error, Result};
 use std::result::Result as StdResult;
 
+pub mod action;
+pub mod builder;
+pub mod class;
+pub mod coins;
+pub mod damage;
+pub mod dice;
+pub mod feat;
 pub mod game;
+pub mod history;
+pub mod item;
+pub mod journal;
+pub mod macros;
+pub mod modifier;
 pub mod monster;
 pub mod player;
+pub mod race;
+pub mod rules;
+pub mod skill;
+pub mod spell;
 pub mod stats;
 pub mod world;
 
 pub mod utils {
+    use std::ascii::AsciiExt;
     use std::ops::{Add, Sub};
+    use std::time::{SystemTime, UNIX_EPOCH};
     
     #[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
     pub struct Position(pub i32, pub i32);
@@ -63,6 +82,41 @@ pub mod utils {
     pub fn str_to_u8(s: &str) -> u8 {
         s.parse().unwrap_or(0)
     }
+
+    // Parses a stat value, keeping it within the range stats can hold.
+    pub fn str_to_stat(s: &str) -> i32 {
+        s.parse().map(|n| ::data::stats::clamp_stat(n)).unwrap_or(0)
+    }
+
+    // Makes a name safe to use in a file name, e.g. Dungeons & Tests becomes dungeons---tests.
+    pub fn slug(name: &str) -> String {
+        name.to_ascii_lowercase().chars().map(|c| if c.is_alphanumeric() { c } else { '-' }).collect()
+    }
+
+    // Seconds since the Unix epoch.
+    pub fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+
+    // Formats the time of day in UTC, like 21:05:00.
+    pub fn clock_time(secs: u64) -> String {
+        let secs = secs % 86400;
+        format!("{:02}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    }
+
+    // Formats the date in UTC, like 2015-06-01, using Howard Hinnant's days-to-civil algorithm.
+    pub fn date(secs: u64) -> String {
+        let z = (secs / 86400) as i64 + 719468;
+        let era = z / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
 }
 
 pub type BotResult<T> = StdResult<T, BotError>;
@@ -126,16 +180,83 @@ pub fn as_io<T>(res: Result<T>) -> BotResult<T> {
 pub trait Entity {
     fn identifier(&self) -> &str;
     fn position(&self) -> &utils::Position;
-    fn damage(&mut self, amount: u8) -> bool;
-    fn roll(&self, roll_type: RollType) -> u8;
+    fn damage(&mut self, amount: i32, damage_type: Option<damage::DamageType>) -> damage::DamageResult;
+    fn roll(&self, roll_type: RollType) -> i32;
     fn do_move(&mut self, pos: utils::Position) -> BotResult<()>;
-    fn stats(&self) -> stats::Stats;
+    fn base_stats(&self) -> stats::Stats;
+    fn modifiers(&self) -> &Vec<modifier::Modifier>;
+    fn modifiers_mut(&mut self) -> &mut Vec<modifier::Modifier>;
     fn has_temp_stats(&self) -> bool;
     fn set_temp_stats(&mut self, stats: stats::Stats);
     fn clear_temp_stats(&mut self);
+    fn defenses(&self) -> &damage::Defenses;
+    fn defenses_mut(&mut self) -> &mut damage::Defenses;
+    fn saving_throws(&self) -> &Vec<RollType>;
+    fn saving_throws_mut(&mut self) -> &mut Vec<RollType>;
+    fn skill_bonus(&self, skill: skill::Skill) -> i32;
+    fn concentration(&self) -> Option<&spell::Concentration>;
+    fn set_concentration(&mut self, effect: Option<spell::Concentration>) -> Option<spell::Concentration>;
+
+    // The base (or temporary) stats with every modifier folded in.
+    fn stats(&self) -> stats::Stats {
+        modifier::apply(self.base_stats(), self.modifiers())
+    }
+
+    fn explain_stat(&self, stat: &str) -> Option<String> {
+        modifier::explain(self.base_stats(), self.modifiers(), stat)
+    }
+
+    fn ability_bonus(&self, stat: RollType) -> i32 {
+        self.stats().bonus_for(stat)
+    }
+
+    // Rolls a stat by name, returning the result and, for roll-under systems, whether it succeeded.
+    fn roll_named(&self, stat: &str) -> Option<(i32, Option<bool>)> {
+        RollType::to_roll_type(stat).map(|t| (self.roll(t), None))
+    }
+
+    fn armor_class(&self) -> i32 {
+        max(0, 10 + self.ability_bonus(RollType::Dexterity))
+    }
+
+    fn proficiency_bonus(&self) -> i32 {
+        2
+    }
+
+    fn save_bonus(&self, stat: RollType) -> i32 {
+        let prof = if self.saving_throws().contains(&stat) { self.proficiency_bonus() } else { 0 };
+        self.ability_bonus(stat) + prof
+    }
+
+    // Rolls the constitution save that taking damage forces while concentrating, at a DC of half
+    // the damage or 10, whichever is higher. Falling unconscious ends concentration outright.
+    fn check_concentration(&mut self, dealt: i32, conscious: bool) -> Option<damage::ConcentrationCheck> {
+        if dealt <= 0 || self.concentration().is_none() {
+            return None;
+        }
+        let dc = max(10, dealt / 2);
+        let total = if conscious {
+            let bonus = self.save_bonus(RollType::Constitution);
+            dice::roll_d20(Some(self.identifier()), "concentration", bonus) + bonus
+        } else {
+            0
+        };
+        let kept = conscious && total >= dc;
+        let effect = if kept {
+            self.concentration().unwrap().clone()
+        } else {
+            self.set_concentration(None).unwrap()
+        };
+        Some(damage::ConcentrationCheck { effect: effect, total: total, dc: dc, kept: kept })
+    }
+
+    fn roll_skill(&self, skill: skill::Skill) -> i32 {
+        let bonus = self.skill_bonus(skill);
+        max(1, dice::roll_d20(Some(self.identifier()), &format!("{} check", skill), bonus) + bonus)
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
 pub enum RollType {
     Basic,
     Strength,
@@ -164,6 +285,14 @@ impl RollType {
             _ => None,
         }
     }
+
+    // How a roll of this type is described in the roll log.
+    pub fn purpose(&self) -> String {
+        match *self {
+            RollType::Basic => String::new(),
+            roll_type => format!("{:?}", roll_type),
+        }
+    }
 }
 
 
@@ -225,6 +354,29 @@ mod test {
         assert_eq!(utils::str_to_u8("x"), 0);
     }
 
+    #[test]
+    fn str_to_stat() {
+        assert_eq!(utils::str_to_stat("300"), 300);
+        assert_eq!(utils::str_to_stat("-4"), 0);
+        assert_eq!(utils::str_to_stat("100000"), 9999);
+        assert_eq!(utils::str_to_stat("x"), 0);
+    }
+
+    #[test]
+    fn format_times() {
+        assert_eq!(utils::date(0), format!("1970-01-01"));
+        assert_eq!(utils::date(951782400), format!("2000-02-29"));
+        assert_eq!(utils::date(1700000000), format!("2023-11-14"));
+        assert_eq!(utils::clock_time(1700000000), format!("22:13:20"));
+        assert_eq!(utils::clock_time(45296), format!("12:34:56"));
+    }
+
+    #[test]
+    fn slug() {
+        assert_eq!(utils::slug("Dungeons & Tests"), format!("dungeons---tests"));
+        assert_eq!(utils::slug("test2"), format!("test2"));
+    }
+
     #[test]
     fn join_from() {
         assert_eq!(&utils::join_from(vec!["hi","there","friend"], 0)[..], "hi there friend");
@@ -0,0 +1,290 @@
+use std::ascii::AsciiExt;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::stats::{MAX_STAT, MIN_STAT, Stats};
+use self::Expiry::{LongRest, Permanent, ShortRest};
+use self::Operation::{Add, Multiply, Set};
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum Operation {
+    Add(i32),
+    Set(i32),
+    Multiply(f32),
+}
+
+impl Operation {
+    // Parses a change to a stat, e.g. +2, -1, =21 or x2.
+    pub fn parse(change: &str) -> Option<Operation> {
+        if change.len() < 2 {
+            return None;
+        }
+        let (op, value) = change.split_at(1);
+        match op {
+            "+" => value.parse::<i32>().ok().map(|n| Add(n)),
+            "-" => value.parse::<i32>().ok().and_then(|n| n.checked_neg()).map(|n| Add(n)),
+            "=" => value.parse::<i32>().ok().and_then(|n| if n < MIN_STAT || n > MAX_STAT { None } else { Some(Set(n)) }),
+            "x" | "X" | "*" => match value.parse::<f32>() {
+                Ok(n) if n >= 0.0 && n.is_finite() => Some(Multiply(n)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum Expiry {
+    Permanent,
+    ShortRest,
+    LongRest,
+}
+
+impl Expiry {
+    pub fn parse(expiry: &str) -> Option<Expiry> {
+        match &expiry.to_ascii_lowercase()[..] {
+            "never" | "permanent" => Some(Permanent),
+            "short" => Some(ShortRest),
+            "long" => Some(LongRest),
+            _ => None,
+        }
+    }
+}
+
+// A named change to one stat, folded on top of an entity's base stats. Several modifiers can apply
+// to the same stat, e.g. a magic item, a spell and a level-up bonus.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Modifier {
+    pub name: String,
+    pub source: Option<String>,
+    pub stat: String,
+    pub operation: Operation,
+    pub expiry: Expiry,
+}
+
+impl Modifier {
+    // Creates a modifier, or returns None if the stat can't be modified. Health is left out since
+    // damage is applied to the underlying stats.
+    pub fn new(name: &str, source: Option<&str>, stat: &str, operation: Operation, expiry: Expiry)
+               -> Option<Modifier> {
+        Modifier::stat_name(stat).map(|stat| Modifier {
+            name: name.to_string(),
+            source: source.map(|s| s.to_string()),
+            stat: stat.to_string(),
+            operation: operation,
+            expiry: expiry,
+        })
+    }
+
+    pub fn stat_name(stat: &str) -> Option<&'static str> {
+        match &stat.to_ascii_lowercase()[..] {
+            "move" | "movement" => Some("movement"),
+            "str" | "strength" => Some("strength"),
+            "dex" | "dexterity" => Some("dexterity"),
+            "con" | "constitution" => Some("constitution"),
+            "wis" | "wisdom" => Some("wisdom"),
+            "int" | "intellect" => Some("intellect"),
+            "cha" | "charisma" => Some("charisma"),
+            _ => None,
+        }
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    pub fn is_from(&self, source: &str) -> bool {
+        self.source.as_ref().map_or(false, |s| s.eq_ignore_ascii_case(source))
+    }
+
+    // Describes the change on its own, e.g. "+1 strength".
+    pub fn describe_operation(&self) -> String {
+        match self.operation {
+            Add(n) => format!("{:+} {}", n, self.stat),
+            Set(n) => format!("{} set to {}", self.stat, n),
+            Multiply(n) => format!("{} x{}", self.stat, n),
+        }
+    }
+
+    // Whether the modifier wears off after a rest of the given length.
+    pub fn expires(&self, long: bool) -> bool {
+        match self.expiry {
+            Permanent => false,
+            ShortRest => true,
+            LongRest => long,
+        }
+    }
+
+    fn describe_change(&self) -> String {
+        match self.operation {
+            Add(n) => format!("{:+} from {}", n, self.name),
+            Set(n) => format!("set to {} by {}", n, self.name),
+            Multiply(n) => format!("x{} from {}", n, self.name),
+        }
+    }
+}
+
+impl Display for Modifier {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        try!(write!(fmt, "{}", self.name));
+        if let Some(ref source) = self.source {
+            try!(write!(fmt, " ({})", source));
+        }
+        try!(write!(fmt, ": {}", self.describe_operation()));
+        match self.expiry {
+            Permanent => Ok(()),
+            ShortRest => write!(fmt, " until a short rest"),
+            LongRest => write!(fmt, " until a long rest"),
+        }
+    }
+}
+
+// The modifiers that affect one stat, in the order they're folded: the highest set replaces the
+// base value, then additions are summed and finally multipliers are applied.
+fn ordered<'a>(stat: &str, modifiers: &'a [Modifier]) -> Vec<&'a Modifier> {
+    let mut sets: Vec<_> = modifiers.iter().filter(|m| m.stat == stat).filter(|m| match m.operation {
+        Set(_) => true,
+        _ => false,
+    }).collect();
+    sets.sort_by(|a, b| match (a.operation, b.operation) {
+        (Set(x), Set(y)) => y.cmp(&x),
+        _ => unreachable!(),
+    });
+    let mut res: Vec<_> = sets.into_iter().take(1).collect();
+    res.extend(modifiers.iter().filter(|m| m.stat == stat).filter(|m| match m.operation {
+        Add(_) => true,
+        _ => false,
+    }));
+    res.extend(modifiers.iter().filter(|m| m.stat == stat).filter(|m| match m.operation {
+        Multiply(_) => true,
+        _ => false,
+    }));
+    res
+}
+
+// Folds in floating point so that large multipliers can't overflow, then brings the result back
+// within the range a stat can hold.
+fn fold(base: i32, modifiers: &[&Modifier]) -> i32 {
+    let value = modifiers.iter().fold(base as f64, |value, m| match m.operation {
+        Set(n) => n as f64,
+        Add(n) => value + n as f64,
+        Multiply(n) => value * n as f64,
+    });
+    match value.floor() {
+        n if n < MIN_STAT as f64 => MIN_STAT,
+        n if n > MAX_STAT as f64 => MAX_STAT,
+        n => n as i32,
+    }
+}
+
+// Folds every modifier into the base stats to get the effective stats.
+pub fn apply(base: Stats, modifiers: &[Modifier]) -> Stats {
+    let mut stats = base;
+    for stat in ["movement", "strength", "dexterity", "constitution", "wisdom", "intellect", "charisma"].iter() {
+        let value = fold(base.get_stat(stat).unwrap(), &ordered(stat, modifiers));
+        stats.update_stat(stat, value).unwrap(); // fold(...) keeps the value in range.
+    }
+    stats
+}
+
+// Explains where the effective value of a stat comes from, e.g. "14 (base 12, +2 from Bless)",
+// or returns None if nothing modifies it.
+pub fn explain(base: Stats, modifiers: &[Modifier], stat: &str) -> Option<String> {
+    let stat = match Modifier::stat_name(stat) {
+        Some(stat) => stat,
+        None => return None,
+    };
+    let applied = ordered(stat, modifiers);
+    if applied.is_empty() {
+        return None;
+    }
+    let value = base.get_stat(stat).unwrap();
+    let changes: Vec<_> = applied.iter().map(|m| m.describe_change()).collect();
+    Some(format!("{} (base {}, {})", fold(value, &applied), value, changes.join(", ")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Expiry, Modifier, Operation, apply, explain};
+    use super::Operation::{Add, Multiply, Set};
+    use data::stats::{MAX_STAT, Stats};
+
+    fn modifier(name: &str, stat: &str, operation: Operation) -> Modifier {
+        Modifier::new(name, None, stat, operation, Expiry::Permanent).unwrap()
+    }
+
+    #[test]
+    fn parse_operation() {
+        assert_eq!(Operation::parse("+2"), Some(Add(2)));
+        assert_eq!(Operation::parse("-1"), Some(Add(-1)));
+        assert_eq!(Operation::parse("=21"), Some(Set(21)));
+        assert_eq!(Operation::parse("x2"), Some(Multiply(2.0)));
+        assert_eq!(Operation::parse("x-2"), None);
+        assert_eq!(Operation::parse("2"), None);
+        assert_eq!(Operation::parse("+"), None);
+        assert_eq!(Operation::parse("=10000"), None);
+        assert_eq!(Operation::parse("--2147483648"), None);
+        assert_eq!(Operation::parse("xinf"), None);
+    }
+
+    #[test]
+    fn new_modifier() {
+        assert_eq!(modifier("Bless", "STR", Add(1)).stat, "strength");
+        assert!(Modifier::new("Bless", None, "health", Add(1), Expiry::Permanent).is_none());
+    }
+
+    #[test]
+    fn apply_modifiers() {
+        let base = Stats::new(20, 30, 12, 12, 12, 12, 12, 12);
+        let modifiers = vec![
+            modifier("Bless", "str", Add(1)),
+            modifier("Belt of Giant Strength", "str", Set(21)),
+            modifier("Gauntlets of Ogre Power", "str", Set(19)),
+            modifier("Haste", "move", Multiply(2.0)),
+            modifier("Curse", "cha", Add(-20)),
+        ];
+        assert_eq!(apply(base, &modifiers), Stats::new(20, 60, 22, 12, 12, 12, 12, 0));
+    }
+
+    #[test]
+    fn apply_extreme_modifiers() {
+        let base = Stats::new(20, 30, 12, 12, 12, 12, 12, 12);
+        let modifiers = vec![
+            modifier("Wish", "str", Add(i32::max_value())),
+            modifier("Wish", "str", Add(i32::max_value())),
+            modifier("Curse", "dex", Add(i32::min_value())),
+            modifier("Haste", "move", Multiply(1e30)),
+        ];
+        assert_eq!(apply(base, &modifiers), Stats::new(20, MAX_STAT, MAX_STAT, 0, 12, 12, 12, 12));
+    }
+
+    #[test]
+    fn explain_modifiers() {
+        let base = Stats::new(20, 30, 12, 12, 12, 12, 12, 12);
+        let modifiers = vec![modifier("Bless", "str", Add(1)), modifier("Belt", "str", Set(21))];
+        assert_eq!(explain(base, &modifiers, "str"), Some(format!("22 (base 12, set to 21 by Belt, +1 from Bless)")));
+        assert_eq!(explain(base, &modifiers, "dex"), None);
+        assert_eq!(explain(base, &modifiers, "health"), None);
+    }
+
+    #[test]
+    fn display() {
+        let mut bless = Modifier::new("Bless", Some("spell"), "str", Add(1), Expiry::LongRest).unwrap();
+        assert_eq!(format!("{}", bless), format!("Bless (spell): +1 strength until a long rest"));
+        bless.source = None;
+        bless.operation = Set(21);
+        bless.expiry = Expiry::Permanent;
+        assert_eq!(format!("{}", bless), format!("Bless: strength set to 21"));
+    }
+
+    #[test]
+    fn expires() {
+        let mut m = modifier("Bless", "str", Add(1));
+        assert!(!m.expires(true));
+        m.expiry = Expiry::LongRest;
+        assert!(!m.expires(false));
+        assert!(m.expires(true));
+        m.expiry = Expiry::ShortRest;
+        assert!(m.expires(false));
+    }
+}
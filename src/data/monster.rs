@@ -1,31 +1,93 @@
+use std::cmp::max;
+use std::mem::replace;
 use data::{BotResult, Entity, RollType};
-use data::BotError::InvalidInput;
-use data::RollType::{Basic, Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma};
+use data::BotError::{InvalidInput, NotFound};
+use data::action::{Action, ActionKind};
+use data::damage::{DamageResult, DamageType, Defenses};
+use data::dice::roll_d20;
+use data::skill::Skill;
+use data::spell::Concentration;
+use data::modifier::Modifier;
 use data::stats::Stats;
 use data::utils::Position;
-use rand::thread_rng;
-use rand::distributions::{IndependentSample, Range};
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(RustcDecodable, RustcEncodable, Debug, PartialEq, Clone)]
 pub struct Monster {
     pub name: String,
     pub stats: Stats,
     pub temp_stats: Option<Stats>,
+    pub modifiers: Vec<Modifier>,
     pub position: Position,
+    pub defenses: Defenses,
+    pub saves: Vec<RollType>,
+    pub skills: Vec<(Skill, i32)>,
+    pub actions: Vec<Action>,
+    pub concentration: Option<Concentration>,
 }
 
 
 impl Monster {
-    pub fn create(name: &str, health: u8, movement: u8, strength: u8, dexterity: u8,
-                  constitution: u8, wisdom: u8, intellect: u8, charisma: u8) -> Monster {
+    pub fn create(name: &str, health: i32, movement: i32, strength: i32, dexterity: i32,
+                  constitution: i32, wisdom: i32, intellect: i32, charisma: i32) -> Monster {
         Monster {
             name: name.to_string(),
             stats: Stats::new(health, movement, strength, dexterity, constitution, wisdom,
                               intellect, charisma),
             temp_stats: None,
+            modifiers: Vec::new(),
             position: Position(0, 0),
+            defenses: Defenses::new(),
+            saves: Vec::new(),
+            skills: Vec::new(),
+            actions: Vec::new(),
+            concentration: None,
         }
     }
+
+    pub fn add_action(&mut self, action: Action) {
+        self.actions.retain(|a| !a.is_named(&action.name));
+        self.actions.push(action);
+    }
+
+    pub fn get_action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|a| a.is_named(name))
+    }
+
+    // Spends the named action and returns the attacks it resolves to, expanding multiattacks. Each
+    // attack in a multiattack is spent too, and nothing is spent unless all of them can be.
+    pub fn use_action(&mut self, name: &str) -> BotResult<Vec<Action>> {
+        let i = match self.actions.iter().position(|a| a.is_named(name)) {
+            Some(i) => i,
+            None => return Err(NotFound(format!("{} has no action named {}.", self.name, name))),
+        };
+        let mut parts = Vec::new();
+        if let ActionKind::Multiattack(ref names) = self.actions[i].kind {
+            for name in names.iter() {
+                match self.actions.iter().position(|a| a.is_named(name) && !a.is_multiattack()) {
+                    Some(j) => parts.push(j),
+                    None => return Err(NotFound(format!("{} has no attack named {}.", self.name, name))),
+                }
+            }
+        } else {
+            parts.push(i);
+        }
+        let mut actions = self.actions.clone();
+        if actions[i].is_multiattack() {
+            try!(actions[i].spend());
+        }
+        let mut attacks = Vec::new();
+        for &j in parts.iter() {
+            try!(actions[j].spend());
+            attacks.push(actions[j].clone());
+        }
+        self.actions = actions;
+        Ok(attacks)
+    }
+
+    // Restores the monster's actions after a rest, returning the names of those that were spent.
+    pub fn rest(&mut self, long: bool) -> Vec<String> {
+        self.actions.iter_mut().filter_map(|a| if a.rest(long) { Some(a.name.clone()) } else { None }).collect()
+    }
 }
 
 impl Entity for Monster {
@@ -37,36 +99,31 @@ impl Entity for Monster {
         &self.position
     }
 
-    fn damage(&mut self, amount: u8) -> bool {
-        if self.temp_stats.is_some() {
+    fn damage(&mut self, amount: i32, damage_type: Option<DamageType>) -> DamageResult {
+        let (dealt, adjustment) = self.defenses.adjust(amount, damage_type);
+        let conscious = if self.temp_stats.is_some() {
             let mut temp = self.temp_stats.unwrap();
-            let ret = temp.damage(amount);
+            let ret = temp.damage(dealt);
             self.temp_stats = Some(temp);
             ret
         } else {
-            self.stats.damage(amount)
+            self.stats.damage(dealt)
+        };
+        let concentration = self.check_concentration(dealt, conscious);
+        DamageResult {
+            raw: amount, dealt: dealt, adjustment: adjustment, conscious: conscious,
+            concentration: concentration,
         }
     }
 
-    fn roll(&self, roll_type: RollType) -> u8 {
-        let d20 = Range::new(1i8, 21i8);
-        let mut rng = thread_rng();
-        match match roll_type {
-            Basic => d20.ind_sample(&mut rng),
-            Strength => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.strength),
-            Dexterity => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.dexterity),
-            Constitution => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.constitution),
-            Wisdom => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.wisdom),
-            Intellect => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.intellect),
-            Charisma => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.charisma),
-        } as u8 {
-            0 => 1,
-            n => n,
-        }
+    // Low stats can take a roll below one, which counts as one rather than wrapping around.
+    fn roll(&self, roll_type: RollType) -> i32 {
+        let bonus = self.ability_bonus(roll_type);
+        max(1, roll_d20(Some(&self.name), &roll_type.purpose(), bonus) + bonus)
     }
 
     fn do_move(&mut self, pos: Position) -> BotResult<()> {
-        if self.position.distance(&pos) <= self.stats().movement as i32 / 5 {
+        if self.position.distance(&pos) <= self.stats().movement / 5 {
             self.position = pos;
             Ok(())
         } else {
@@ -77,13 +134,21 @@ impl Entity for Monster {
         }
     }
 
-    fn stats(&self) -> Stats {
+    fn base_stats(&self) -> Stats {
         match self.temp_stats {
             Some(stats) => stats,
             None => self.stats,
         }
     }
 
+    fn modifiers(&self) -> &Vec<Modifier> {
+        &self.modifiers
+    }
+
+    fn modifiers_mut(&mut self) -> &mut Vec<Modifier> {
+        &mut self.modifiers
+    }
+
     fn has_temp_stats(&self) -> bool {
         match self.temp_stats {
             Some(_) => true,
@@ -98,13 +163,50 @@ impl Entity for Monster {
     fn clear_temp_stats(&mut self) {
         self.temp_stats = None;
     }
+
+    fn defenses(&self) -> &Defenses {
+        &self.defenses
+    }
+
+    fn defenses_mut(&mut self) -> &mut Defenses {
+        &mut self.defenses
+    }
+
+    fn saving_throws(&self) -> &Vec<RollType> {
+        &self.saves
+    }
+
+    fn saving_throws_mut(&mut self) -> &mut Vec<RollType> {
+        &mut self.saves
+    }
+
+    fn concentration(&self) -> Option<&Concentration> {
+        self.concentration.as_ref()
+    }
+
+    fn set_concentration(&mut self, effect: Option<Concentration>) -> Option<Concentration> {
+        replace(&mut self.concentration, effect)
+    }
+
+    fn skill_bonus(&self, skill: Skill) -> i32 {
+        match self.skills.iter().find(|&&(s, _)| s == skill) {
+            Some(&(_, bonus)) => bonus,
+            None => self.ability_bonus(skill.ability()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::Monster;
     use data::Entity;
-    use data::RollType::{Basic, Dexterity, Constitution};
+    use data::RollType::{Basic, Strength, Dexterity, Constitution};
+    use data::action::Action;
+    use data::damage::Adjustment::{Immune, Resisted};
+    use data::damage::DamageType::{Bludgeoning, Cold, Fire, Piercing, Slashing};
+    use data::damage::Defenses;
+    use data::dice::Dice;
+    use data::skill::Skill::{Perception, Stealth};
     use data::stats::Stats;
     use data::utils::Position;
 
@@ -115,29 +217,109 @@ mod test {
             name: "test".to_string(),
             stats: Stats::new(20, 30, 12, 12, 12, 12, 12, 12),
             temp_stats: None,
+            modifiers: Vec::new(),
             position: Position(0, 0),
+            defenses: Defenses::new(),
+            saves: Vec::new(),
+            skills: Vec::new(),
+            actions: Vec::new(),
+            concentration: None,
         };
         assert_eq!(m, n);
     }
 
+    #[test]
+    fn add_action() {
+        let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
+        m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+        m.add_action(Action::attack("bite", 5, 5, Dice::new(1, 8, 2), Piercing));
+        assert_eq!(m.actions.len(), 1);
+        assert_eq!(m.get_action("BITE"), Some(&Action::attack("bite", 5, 5, Dice::new(1, 8, 2), Piercing)));
+    }
+
+    #[test]
+    fn use_action() {
+        let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
+        let bite = Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing);
+        m.add_action(bite.clone());
+        assert_eq!(m.use_action("bite").unwrap(), vec![bite]);
+        assert!(m.use_action("claw").is_err());
+    }
+
+    #[test]
+    fn use_action_multiattack() {
+        let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
+        let bite = Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing);
+        let claw = Action::attack("Claw", 4, 5, Dice::new(2, 4, 2), Slashing);
+        m.add_action(bite.clone());
+        m.add_action(claw.clone());
+        m.add_action(Action::multiattack("Multiattack", vec!["Bite".to_string(), "Claw".to_string(), "Claw".to_string()]));
+        assert_eq!(m.use_action("multiattack").unwrap(), vec![bite, claw.clone(), claw]);
+        let mut frenzy = Action::multiattack("Frenzy", vec!["Bite".to_string(), "Tail".to_string()]);
+        frenzy.uses = Some(1);
+        m.add_action(frenzy);
+        assert!(m.use_action("frenzy").is_err());
+        assert_eq!(m.get_action("frenzy").unwrap().used, 0);
+    }
+
+    #[test]
+    fn use_action_multiattack_spends_parts() {
+        let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
+        let mut web = Action::attack("Web", 5, 30, Dice::new(0, 1, 0), Bludgeoning);
+        web.uses = Some(1);
+        m.add_action(web);
+        m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+        m.add_action(Action::multiattack("Multiattack", vec!["Web".to_string(), "Bite".to_string()]));
+        assert_eq!(m.use_action("multiattack").unwrap().len(), 2);
+        assert_eq!(m.get_action("web").unwrap().used, 1);
+        assert!(m.use_action("multiattack").is_err());
+        assert!(m.use_action("bite").is_ok());
+        assert!(m.rest(false).is_empty());
+        assert_eq!(m.rest(true), vec!["Web".to_string()]);
+        assert!(m.use_action("multiattack").is_ok());
+    }
+
+    #[test]
+    fn use_action_out_of_uses() {
+        let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
+        let mut web = Action::attack("Web", 5, 30, Dice::new(0, 1, 0), Bludgeoning);
+        web.uses = Some(1);
+        m.add_action(web);
+        assert!(m.use_action("web").is_ok());
+        assert!(m.use_action("web").is_err());
+    }
+
     #[test]
     fn damage() {
         let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
         assert_eq!(m.stats().health, 20);
-        assert!(m.damage(5));
+        assert!(m.damage(5, None).conscious);
         assert_eq!(m.stats().health, 15);
-        assert!(!m.damage(16));
+        assert!(!m.damage(16, None).conscious);
         assert_eq!(m.stats().health, 0);
     }
 
+    #[test]
+    fn damage_with_defenses() {
+        let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
+        m.defenses.add_resistance(Fire);
+        m.defenses.add_immunity(Cold);
+        let r = m.damage(9, Some(Fire));
+        assert_eq!((r.raw, r.dealt, r.adjustment, r.conscious), (9, 4, Resisted, true));
+        assert_eq!(m.stats().health, 16);
+        let r = m.damage(30, Some(Cold));
+        assert_eq!((r.dealt, r.adjustment, r.conscious), (0, Immune, true));
+        assert_eq!(m.stats().health, 16);
+    }
+
     #[test]
     fn damage_temp_health() {
         let mut m = Monster::create("test", 20, 30, 12, 12, 12, 12, 12, 12);
         m.set_temp_stats(Stats::new(40, 30, 10, 10, 10, 10, 10, 10));
         assert_eq!(m.stats().health, 40);
-        assert!(m.damage(5));
+        assert!(m.damage(5, None).conscious);
         assert_eq!(m.stats().health, 35);
-        assert!(!m.damage(35));
+        assert!(!m.damage(35, None).conscious);
         assert_eq!(m.stats().health, 0);
     }
 
@@ -216,6 +398,14 @@ mod test {
         m.clear_temp_stats()
     }
 
+    #[test]
+    fn skill_bonus() {
+        let mut m = Monster::create("test", 20, 30, 12, 14, 12, 8, 12, 12);
+        m.skills.push((Stealth, 6));
+        assert_eq!(m.skill_bonus(Stealth), 6);
+        assert_eq!(m.skill_bonus(Perception), -1);
+    }
+
     #[test]
     fn basic_roll() {
         let m = Monster::create("test", 20, 30, 12, 12, 8, 12, 12, 12);
@@ -244,4 +434,13 @@ mod test {
             assert!(r >= 1 && r <= 19);
         }
     }
+
+    #[test]
+    fn low_stat_roll_does_not_wrap() {
+        let m = Monster::create("test", 20, 30, 0, 12, 12, 12, 12, 12);
+        for _ in 0..1000 {
+            let r = m.roll(Strength);
+            assert!(r >= 1 && r <= 15);
+        }
+    }
 }
@@ -1,16 +1,43 @@
+use std::ascii::AsciiExt;
 use std::borrow::ToOwned;
+use std::cmp::{max, min};
 use std::fs::{File, create_dir_all};
 use std::io::{Error, ErrorKind, Result};
 use std::io::prelude::*;
+use std::mem::replace;
 use std::path::Path;
 use data::{BotResult, Entity, RollType, as_io};
+use data::BotError::InvalidInput;
 use data::RollType::{Basic, Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma};
+use data::action::Action;
+use data::class::{CharacterClass, level_for_xp};
+use data::coins::Purse;
+use data::damage::{DamageResult, DamageType, Defenses};
+use data::dice::{record, roll_d20, roll_die};
+use data::feat::{FeatInfo, FeatModifier};
 use data::game::Game;
-use data::stats::Stats;
+use data::item::{Inventory, ItemKind};
+use data::macros::Macro;
+use data::race::{Race, RaceInfo};
+use data::rules::{RuleSystem, StatDef};
+use data::skill::{Proficiency, Skill};
+use data::spell::{Concentration, Spell};
+use data::modifier::Modifier;
+use data::stats::{Stats, clamp_stat};
 use data::utils::Position;
-use rand::thread_rng;
-use rand::distributions::{IndependentSample, Range};
-use rustc_serialize::json::{decode, encode};
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{Decoder, Json, encode};
+
+// What a character got back from a rest, so that it can be announced to the table.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RestResult {
+    pub healed: i32,
+    pub dice_spent: u8,
+    pub dice_regained: u8,
+    pub slots_recovered: bool,
+    pub ended: Option<Concentration>,
+    pub expired: Vec<Modifier>,
+}
 
 #[derive(RustcDecodable, RustcEncodable, Debug, PartialEq, Clone)]
 pub struct Player {
@@ -19,36 +46,78 @@ pub struct Player {
     pub stats: Stats,
     pub feats: Vec<String>,
     pub temp_stats: Option<Stats>,
+    pub modifiers: Vec<Modifier>,
     pub position: Position,
+    pub defenses: Defenses,
+    pub saves: Vec<RollType>,
+    pub level: u8,
+    pub skills: Vec<(Skill, Proficiency)>,
+    pub class: Option<CharacterClass>,
+    pub xp: u32,
+    pub max_health: i32,
+    pub race: Option<Race>,
+    pub inventory: Inventory,
+    pub coins: Purse,
+    pub spells: Vec<Spell>,
+    pub slots_used: Vec<u8>,
+    pub concentration: Option<Concentration>,
+    pub hit_dice_used: u8,
+    // The game system the character was made for, where None means the standard rules.
+    pub rules: Option<RuleSystem>,
+    // Values of stats from the rule system that aren't built in, stored by name.
+    pub extra_stats: Vec<(String, i32)>,
+    pub macros: Vec<Macro>,
+    // Proficiencies that feats granted, so that removing a feat only takes away what it gave.
+    pub feat_saves: Vec<RollType>,
+    pub feat_skills: Vec<Skill>,
 }
 
 impl Player {
-    pub fn create(username: &str, password: &str, health: u8, movement: u8, strength: u8,
-                  dexterity: u8, constitution: u8, wisdom: u8, intellect: u8, charisma: u8)
+    pub fn create(username: &str, password: &str, health: i32, movement: i32, strength: i32,
+                  dexterity: i32, constitution: i32, wisdom: i32, intellect: i32, charisma: i32)
                   -> BotResult<Player> {
-        Ok(Player {
-            username: username.to_string(),
-            password: try!(as_io(Game::password_hash(password))),
-            stats: Stats::new(health, movement, strength, dexterity, constitution, wisdom,
-                              intellect, charisma),
-            feats: Vec::new(),
-            temp_stats: None,
-            position: Position(0, 0),
-        })
+        Ok(Player::with_password(username, try!(as_io(Game::password_hash(password))), Stats::new(
+            health, movement, strength, dexterity, constitution, wisdom, intellect, charisma
+        )))
     }
 
     #[cfg(test)]
-    pub fn create_test(username: &str, password: &str, health: u8, movement: u8, strength: u8,
-                       dexterity: u8, constitution: u8, wisdom: u8, intellect: u8, charisma: u8)
+    pub fn create_test(username: &str, password: &str, health: i32, movement: i32, strength: i32,
+                       dexterity: i32, constitution: i32, wisdom: i32, intellect: i32, charisma: i32)
                        -> Player {
+        Player::with_password(username, password.to_string(), Stats::new(
+            health, movement, strength, dexterity, constitution, wisdom, intellect, charisma
+        ))
+    }
+
+    fn with_password(username: &str, password: String, stats: Stats) -> Player {
         Player {
             username: username.to_string(),
-            password: password.to_string(),
-            stats: Stats::new(health, movement, strength, dexterity, constitution, wisdom,
-                              intellect, charisma),
+            password: password,
+            stats: stats,
             feats: Vec::new(),
             temp_stats: None,
+            modifiers: Vec::new(),
             position: Position(0, 0),
+            defenses: Defenses::new(),
+            saves: Vec::new(),
+            level: 1,
+            skills: Vec::new(),
+            class: None,
+            xp: 0,
+            max_health: clamp_stat(stats.health),
+            race: None,
+            inventory: Inventory::new(),
+            coins: Purse::new(),
+            spells: Vec::new(),
+            slots_used: Vec::new(),
+            concentration: None,
+            hit_dice_used: 0,
+            rules: None,
+            extra_stats: Vec::new(),
+            macros: Vec::new(),
+            feat_saves: Vec::new(),
+            feat_skills: Vec::new(),
         }
     }
 
@@ -59,11 +128,41 @@ impl Player {
         let mut file = try!(File::open(&Path::new(&path)));
         let mut data = String::new();
         try!(file.read_to_string(&mut data));
-        decode(&data).map_err(|_| Error::new(
+        let mut json = try!(Json::from_str(&data).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to decode player data." 
+        )));
+        try!(Player::upgrade(&mut json));
+        Decodable::decode(&mut Decoder::new(json)).map_err(|_| Error::new(
             ErrorKind::InvalidInput, "Failed to decode player data." 
         ))
     }
 
+    // Saves written before a field was added don't have it, so it's filled in with what a new
+    // character gets. Characters from before maximum health was tracked are taken to be at full
+    // health.
+    fn upgrade(json: &mut Json) -> Result<()> {
+        let blank = try!(encode(&Player::with_password("", String::new(), Stats::new(0, 0, 0, 0, 0, 0, 0, 0)))
+            .ok().and_then(|s| Json::from_str(&s).ok()).ok_or(Error::new(
+                ErrorKind::InvalidInput, "Failed to encode player data."
+            )));
+        if let Json::Object(ref mut fields) = *json {
+            if !fields.contains_key("max_health") {
+                let health = fields.get("stats").and_then(|stats| stats.find("health")).cloned();
+                if let Some(health) = health {
+                    fields.insert("max_health".to_owned(), health);
+                }
+            }
+            if let Json::Object(defaults) = blank {
+                for (field, value) in defaults.into_iter() {
+                    if !fields.contains_key(&field) {
+                        fields.insert(field, value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn save(&self) -> Result<()> {
         let mut path = "users/".to_owned();
         try!(create_dir_all(&Path::new(&path)));
@@ -75,8 +174,399 @@ impl Player {
         ))).as_bytes())
     }
 
-    pub fn add_feat(&mut self, feat: &str) {
-        self.feats.push(feat.to_string())
+    pub fn has_feat(&self, name: &str) -> bool {
+        self.feats.iter().any(|f| f.eq_ignore_ascii_case(name))
+    }
+
+    // Checks whether a feat could be added, without adding it. Feats that aren't in the table
+    // are custom ones with no prerequisites or modifiers.
+    pub fn check_feat(&self, name: &str) -> BotResult<()> {
+        if self.has_feat(name) {
+            return Err(InvalidInput(format!("{} already has {}.", self.username, name)));
+        }
+        FeatInfo::find(name).map_or(Ok(()), |info| info.check(self))
+    }
+
+    // Adds a feat and applies any mechanical modifiers it has, returning the feat's proper name.
+    pub fn add_feat(&mut self, name: &str) -> BotResult<String> {
+        try!(self.check_feat(name));
+        let name = match FeatInfo::find(name) {
+            Some(info) => {
+                for modifier in info.modifiers.iter() {
+                    self.apply_feat_modifier(*modifier, true);
+                }
+                info.name.to_string()
+            },
+            None => name.to_string(),
+        };
+        self.feats.push(name.clone());
+        Ok(name)
+    }
+
+    // Removes a feat and reverses its modifiers, returning the feat's proper name.
+    pub fn remove_feat(&mut self, name: &str) -> BotResult<String> {
+        let index = match self.feats.iter().position(|f| f.eq_ignore_ascii_case(name)) {
+            Some(index) => index,
+            None => return Err(InvalidInput(format!("{} doesn't have {}.", self.username, name))),
+        };
+        let name = self.feats.remove(index);
+        if let Some(info) = FeatInfo::find(&name) {
+            for modifier in info.modifiers.iter() {
+                self.apply_feat_modifier(*modifier, false);
+            }
+        }
+        Ok(name)
+    }
+
+    // Saves the macro, replacing any with the same name, and returns whether one was replaced.
+    pub fn set_macro(&mut self, m: Macro) -> bool {
+        let before = self.macros.len();
+        self.macros.retain(|old| !old.is_named(&m.name));
+        self.macros.push(m);
+        self.macros.len() == before
+    }
+
+    pub fn remove_macro(&mut self, name: &str) -> BotResult<Macro> {
+        match self.macros.iter().position(|m| m.is_named(name)) {
+            Some(index) => Ok(self.macros.remove(index)),
+            None => Err(InvalidInput(format!("{} has no macro named {}.", self.username, name))),
+        }
+    }
+
+    pub fn get_macro(&self, name: &str) -> Option<&Macro> {
+        self.macros.iter().find(|m| m.is_named(name))
+    }
+
+    // Applies or reverses a single modifier. Proficiency is only granted when the player doesn't
+    // already have it, and is only taken away again if a feat granted it and no remaining feat
+    // still does. Skill expertise is never taken away.
+    fn apply_feat_modifier(&mut self, modifier: FeatModifier, apply: bool) {
+        match modifier {
+            FeatModifier::Stat(stat, value) if apply => self.stats.increase_for(stat, value as i32),
+            FeatModifier::Stat(stat, value) => self.stats.decrease_for(stat, value as i32),
+            FeatModifier::Save(stat) if apply => if !self.saves.contains(&stat) {
+                self.saves.push(stat);
+                self.feat_saves.push(stat);
+            },
+            FeatModifier::Save(stat) => if !self.feat_grants(modifier) {
+                if let Some(i) = self.feat_saves.iter().position(|s| *s == stat) {
+                    self.feat_saves.remove(i);
+                    self.saves.retain(|s| *s != stat);
+                }
+            },
+            FeatModifier::Skill(skill) if apply => if self.skill_proficiency(skill).is_none() {
+                self.set_skill_proficiency(skill, Some(Proficiency::Proficient));
+                self.feat_skills.push(skill);
+            },
+            FeatModifier::Skill(skill) => if !self.feat_grants(modifier) {
+                if let Some(i) = self.feat_skills.iter().position(|s| *s == skill) {
+                    self.feat_skills.remove(i);
+                    if self.skill_proficiency(skill) == Some(Proficiency::Proficient) {
+                        self.set_skill_proficiency(skill, None);
+                    }
+                }
+            },
+            FeatModifier::Speed(value) if apply => self.stats.movement = clamp_stat(self.stats.movement + value as i32),
+            FeatModifier::Speed(value) => self.stats.movement = clamp_stat(self.stats.movement - value as i32),
+            FeatModifier::HealthPerLevel(value) => {
+                let amount = value as i32 * self.level as i32;
+                if apply {
+                    self.max_health = clamp_stat(self.max_health + amount);
+                    self.stats.health = clamp_stat(self.stats.health + amount);
+                } else {
+                    self.max_health = clamp_stat(self.max_health - amount);
+                    self.stats.health = min(self.stats.health, self.max_health);
+                }
+            },
+        }
+    }
+
+    // Whether any of the character's feats has the modifier.
+    fn feat_grants(&self, modifier: FeatModifier) -> bool {
+        self.feats.iter().filter_map(|f| FeatInfo::find(f)).any(|info| info.modifiers.contains(&modifier))
+    }
+
+    // The extra health each level grants from feats like Tough.
+    fn feat_health_per_level(&self) -> i32 {
+        self.feats.iter().filter_map(|f| FeatInfo::find(f)).flat_map(|info| info.modifiers.iter())
+            .fold(0, |total, m| match *m {
+                FeatModifier::HealthPerLevel(value) => total + value as i32,
+                _ => total,
+            })
+    }
+
+    pub fn rules(&self) -> RuleSystem {
+        self.rules.clone().unwrap_or_else(RuleSystem::standard)
+    }
+
+    // Looks up a stat by any of the names the character's rule system gives it. Health and
+    // movement are always available.
+    pub fn stat_value(&self, name: &str) -> Option<i32> {
+        let rules = self.rules();
+        match rules.stat(name) {
+            Some(&StatDef { builtin: Some(stat), .. }) => Some(self.stats().get_for(stat)),
+            Some(def) => self.extra_stats.iter().find(|&&(ref n, _)| *n == def.name).map(|&(_, v)| v),
+            None if self.rules.is_none() || Modifier::stat_name(name) == Some("movement") => {
+                self.stats().get_stat(name)
+            },
+            None => match &name.to_ascii_lowercase()[..] {
+                "health" | "hp" => Some(self.stats().health),
+                _ => None,
+            },
+        }
+    }
+
+    // Sets a stat by name, checking it against the rule system's range, and returns the stat's
+    // proper name.
+    pub fn set_stat_value(&mut self, name: &str, value: i32) -> BotResult<String> {
+        let rules = self.rules();
+        match rules.stat(name) {
+            Some(def) => {
+                try!(def.validate(value));
+                match def.builtin {
+                    Some(stat) => {
+                        let current = self.stats.get_for(stat);
+                        self.stats.decrease_for(stat, current);
+                        self.stats.increase_for(stat, value);
+                    },
+                    None => {
+                        self.extra_stats.retain(|&(ref n, _)| *n != def.name);
+                        self.extra_stats.push((def.name.clone(), value));
+                    },
+                }
+                Ok(def.name.clone())
+            },
+            None if self.stats.get_stat(name).is_some() && self.stat_value(name).is_some() => {
+                try!(self.stats.update_stat(name, value));
+                Ok(name.to_ascii_lowercase())
+            },
+            None => Err(InvalidInput(format!("{} is not a valid stat.", name))),
+        }
+    }
+
+    pub fn skill_proficiency(&self, skill: Skill) -> Option<Proficiency> {
+        self.skills.iter().find(|&&(s, _)| s == skill).map(|&(_, p)| p)
+    }
+
+    pub fn set_skill_proficiency(&mut self, skill: Skill, proficiency: Option<Proficiency>) {
+        self.skills.retain(|&(s, _)| s != skill);
+        if let Some(p) = proficiency {
+            self.skills.push((skill, p));
+        }
+    }
+
+    // Applies the ability bonuses and resistances of a race, which should only happen once when the
+    // character is created. The race's speed is what the character is created with, so it's left
+    // to the caller rather than overwriting whatever the character's movement has become.
+    pub fn apply_race(&mut self, info: &RaceInfo) {
+        for &(stat, bonus) in info.bonuses.iter() {
+            self.stats.increase_for(stat, bonus as i32);
+        }
+        for damage_type in info.resistances.iter() {
+            self.defenses.add_resistance(*damage_type);
+        }
+        self.race = Some(info.to_race());
+    }
+
+    pub fn carrying_capacity(&self) -> f32 {
+        self.stats().strength as f32 * 15.0
+    }
+
+    // Builds an attack from an equipped weapon. Finesse weapons use the better of strength and
+    // dexterity, and every character is assumed to be proficient with what they've equipped.
+    pub fn weapon_attack(&self, name: &str) -> Option<Action> {
+        let weapon = match self.inventory.weapons().into_iter().find(|w| w.is_named(name)) {
+            Some(weapon) => weapon,
+            None => return None,
+        };
+        if let ItemKind::Weapon { damage, damage_type, finesse, reach } = weapon.kind {
+            let str_bonus = self.ability_bonus(Strength);
+            let dex_bonus = self.ability_bonus(Dexterity);
+            let ability = if finesse && dex_bonus > str_bonus { dex_bonus } else { str_bonus };
+            let mut damage = damage;
+            damage.bonus += ability;
+            Some(Action::attack(&weapon.name, ability + self.proficiency_bonus(), reach,
+                                damage, damage_type))
+        } else {
+            None
+        }
+    }
+
+    // Players without a class use a d8, the most common hit die.
+    pub fn hit_die(&self) -> u8 {
+        self.class.map_or(8, |c| c.hit_die())
+    }
+
+    pub fn hit_points_per_level(&self, rolled: bool) -> i32 {
+        let die = self.hit_die() as i32;
+        let bonus = self.ability_bonus(Constitution);
+        if rolled {
+            let total = max(1, roll_die(die) + bonus);
+            record(&format!("d{} (hit points)", die), Some(&self.username), bonus, total);
+            total
+        } else {
+            max(1, die / 2 + 1 + bonus)
+        }
+    }
+
+    // Adds experience and levels up as many times as the new total allows, returning the level
+    // reached and hit points gained for each new level.
+    pub fn award_xp(&mut self, amount: u32, rolled: bool) -> Vec<(u8, i32)> {
+        self.xp = self.xp.saturating_add(amount);
+        let mut gains = Vec::new();
+        while self.level < level_for_xp(self.xp) {
+            let hp = self.hit_points_per_level(rolled) + self.feat_health_per_level();
+            self.level += 1;
+            self.max_health = clamp_stat(self.max_health + hp);
+            self.stats.health = clamp_stat(self.stats.health + hp);
+            gains.push((self.level, hp));
+        }
+        gains
+    }
+
+    pub fn spell(&self, name: &str) -> Option<&Spell> {
+        self.spells.iter().find(|s| s.is_named(name))
+    }
+
+    // Checks whether a spell could be learned, without learning it. Only casters can learn spells,
+    // and only cantrips or spells of a level they have slots for.
+    pub fn check_spell(&self, spell: &Spell) -> BotResult<()> {
+        if self.class.and_then(|c| c.spellcasting_ability()).is_none() {
+            return Err(InvalidInput(format!("{} can't cast spells.", self.username)));
+        }
+        let highest = self.spell_slots().iter().rposition(|n| *n > 0).map_or(0, |i| i as u8 + 1);
+        if !spell.is_cantrip() && spell.level > highest {
+            return Err(InvalidInput(format!("{} has no level {} spell slots.", self.username, spell.level)));
+        }
+        Ok(())
+    }
+
+    // Learning a spell again replaces the old definition. Prepared casters learn spells
+    // unprepared, and everyone else can cast what they know.
+    pub fn learn_spell(&mut self, mut spell: Spell) {
+        spell.prepared = !self.class.map_or(false, |c| c.prepares_spells()) || spell.is_cantrip();
+        self.spells.retain(|s| !s.is_named(&spell.name));
+        self.spells.push(spell);
+    }
+
+    pub fn max_prepared(&self) -> i32 {
+        let (class, ability) = match self.class.and_then(|c| c.spellcasting_ability().map(|a| (c, a))) {
+            Some(pair) => pair,
+            None => return 0,
+        };
+        let level = if class == CharacterClass::Paladin { self.level / 2 } else { self.level };
+        max(1, level as i32 + self.ability_bonus(ability))
+    }
+
+    pub fn prepare_spell(&mut self, name: &str, prepared: bool) -> BotResult<()> {
+        let count = self.spells.iter().filter(|s| s.prepared && !s.is_cantrip()).count();
+        let max = self.max_prepared();
+        let spell = match self.spells.iter_mut().find(|s| s.is_named(name)) {
+            Some(spell) => spell,
+            None => return Err(InvalidInput(format!("{} doesn't know {}.", self.username, name))),
+        };
+        if spell.is_cantrip() {
+            return Err(InvalidInput(format!("Cantrips are always prepared.")));
+        } else if prepared && !spell.prepared && count >= max as usize {
+            return Err(InvalidInput(format!("{} can only prepare {} spells.", self.username, max)));
+        }
+        spell.prepared = prepared;
+        Ok(())
+    }
+
+    pub fn spell_slots(&self) -> [u8; 9] {
+        self.class.map_or([0; 9], |c| c.spell_slots(self.level))
+    }
+
+    pub fn slots_remaining(&self, level: u8) -> u8 {
+        if level < 1 || level > 9 {
+            return 0;
+        }
+        let used = self.slots_used.get(level as usize - 1).map_or(0, |u| *u);
+        self.spell_slots()[level as usize - 1].saturating_sub(used)
+    }
+
+    pub fn spend_slot(&mut self, level: u8) -> BotResult<()> {
+        if self.slots_remaining(level) == 0 {
+            return Err(InvalidInput(format!("{} has no level {} spell slots left.", self.username, level)));
+        }
+        while self.slots_used.len() < level as usize {
+            self.slots_used.push(0);
+        }
+        self.slots_used[level as usize - 1] += 1;
+        Ok(())
+    }
+
+    pub fn recover_slots(&mut self) {
+        self.slots_used.clear();
+    }
+
+    pub fn hit_dice_remaining(&self) -> u8 {
+        self.level.saturating_sub(self.hit_dice_used)
+    }
+
+    // Returns how much health was actually restored. Negative amounts heal nothing.
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        let before = self.stats.health;
+        if self.stats.health < self.max_health {
+            self.stats.health = min(self.max_health, self.stats.health.saturating_add(max(0, amount)));
+        }
+        self.stats.health - before
+    }
+
+    // Spends hit dice one at a time until health is full, the limit is reached, or there are no
+    // more dice to spend. Warlocks also get their pact slots back.
+    pub fn short_rest(&mut self, max_dice: u8) -> RestResult {
+        let mut result = RestResult {
+            healed: 0, dice_spent: 0, dice_regained: 0, slots_recovered: false, ended: None,
+            expired: self.expire_modifiers(false),
+        };
+        while result.dice_spent < max_dice && self.hit_dice_remaining() > 0 && self.stats.health < self.max_health {
+            let bonus = self.ability_bonus(Constitution);
+            let amount = max(0, roll_die(self.hit_die() as i32) + bonus);
+            record(&format!("d{} (hit die)", self.hit_die()), Some(&self.username), bonus, amount);
+            self.hit_dice_used += 1;
+            result.dice_spent += 1;
+            result.healed += self.heal(amount);
+        }
+        if self.class.map_or(false, |c| c.recovers_slots_on_short_rest()) && !self.slots_used.is_empty() {
+            self.recover_slots();
+            result.slots_recovered = true;
+        }
+        result
+    }
+
+    // Restores all health and spell slots and half of the character's hit dice. Temporary stats and
+    // concentration don't last through the night.
+    pub fn long_rest(&mut self) -> RestResult {
+        let max_health = self.max_health;
+        let healed = self.heal(max_health);
+        let regained = min(self.hit_dice_used, max(1, self.level / 2));
+        self.hit_dice_used -= regained;
+        let slots_recovered = !self.slots_used.is_empty();
+        self.recover_slots();
+        self.temp_stats = None;
+        RestResult {
+            healed: healed, dice_spent: 0, dice_regained: regained, slots_recovered: slots_recovered,
+            ended: self.concentration.take(), expired: self.expire_modifiers(true),
+        }
+    }
+
+    // Removes and returns the modifiers that wear off after a rest of the given length.
+    fn expire_modifiers(&mut self, long: bool) -> Vec<Modifier> {
+        let (expired, kept): (Vec<_>, Vec<_>) = replace(&mut self.modifiers, Vec::new()).into_iter()
+            .partition(|m| m.expires(long));
+        self.modifiers = kept;
+        expired
+    }
+
+    pub fn spell_attack_bonus(&self) -> i32 {
+        let ability = self.class.and_then(|c| c.spellcasting_ability()).unwrap_or(Intellect);
+        self.ability_bonus(ability) + self.proficiency_bonus()
+    }
+
+    pub fn spell_save_dc(&self) -> i32 {
+        max(1, 8 + self.spell_attack_bonus())
     }
 }
 
@@ -89,36 +579,52 @@ impl Entity for Player {
         &self.position
     }
 
-    fn damage(&mut self, amount: u8) -> bool {
-        if self.temp_stats.is_some() {
+    fn damage(&mut self, amount: i32, damage_type: Option<DamageType>) -> DamageResult {
+        let (dealt, adjustment) = self.defenses.adjust(amount, damage_type);
+        let conscious = if self.temp_stats.is_some() {
             let mut temp = self.temp_stats.unwrap();
-            let ret = temp.damage(amount);
+            let ret = temp.damage(dealt);
             self.temp_stats = Some(temp);
             ret
         } else {
-            self.stats.damage(amount)
+            self.stats.damage(dealt)
+        };
+        let concentration = self.check_concentration(dealt, conscious);
+        DamageResult {
+            raw: amount, dealt: dealt, adjustment: adjustment, conscious: conscious,
+            concentration: concentration,
         }
     }
 
-    fn roll(&self, roll_type: RollType) -> u8 {
-        let d20 = Range::new(1i8, 21i8);
-        let mut rng = thread_rng();
-        match match roll_type {
-            Basic => d20.ind_sample(&mut rng),
-            Strength => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.strength),
-            Dexterity => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.dexterity),
-            Constitution => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.constitution),
-            Wisdom => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.wisdom),
-            Intellect => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.intellect),
-            Charisma => d20.ind_sample(&mut rng) + Stats::calc_bonus(self.stats.charisma),
-        } as u8 {
-            0 => 1,
-            n => n
+    // Low stats can take a roll below one, which counts as one rather than wrapping around.
+    fn roll(&self, roll_type: RollType) -> i32 {
+        if let Some(ref rules) = self.rules {
+            let value = if roll_type == Basic { None } else { Some(self.stats().get_for(roll_type)) };
+            return rules.roll_for(&self.username, &roll_type.purpose(), value).0;
+        }
+        let bonus = self.ability_bonus(roll_type);
+        max(1, roll_d20(Some(&self.username), &roll_type.purpose(), bonus) + bonus)
+    }
+
+    fn roll_named(&self, stat: &str) -> Option<(i32, Option<bool>)> {
+        match self.rules {
+            Some(ref rules) => rules.stat(stat).and_then(|def| {
+                self.stat_value(&def.name).map(|v| rules.roll_for(&self.username, &def.name, Some(v)))
+            }),
+            None => RollType::to_roll_type(stat).map(|t| (self.roll(t), None)),
+        }
+    }
+
+    fn ability_bonus(&self, stat: RollType) -> i32 {
+        match (stat, self.rules.as_ref()) {
+            (Basic, _) => 0,
+            (_, Some(rules)) => rules.bonus(self.stats().get_for(stat)),
+            (_, None) => self.stats().bonus_for(stat),
         }
     }
 
     fn do_move(&mut self, pos: Position) -> BotResult<()> {
-        if self.position.distance(&pos) <= self.stats().movement as i32 / 5 {
+        if self.position.distance(&pos) <= self.stats().movement / 5 {
             self.position = pos;
             Ok(())
         } else {
@@ -128,13 +634,21 @@ impl Entity for Player {
         }
     }
 
-    fn stats(&self) -> Stats {
+    fn base_stats(&self) -> Stats {
         match self.temp_stats {
             Some(stats) => stats,
             None => self.stats,
         }
     }
 
+    fn modifiers(&self) -> &Vec<Modifier> {
+        &self.modifiers
+    }
+
+    fn modifiers_mut(&mut self) -> &mut Vec<Modifier> {
+        &mut self.modifiers
+    }
+
     fn has_temp_stats(&self) -> bool {
         match self.temp_stats {
             Some(_) => true,
@@ -149,14 +663,73 @@ impl Entity for Player {
     fn clear_temp_stats(&mut self) {
         self.temp_stats = None;
     }
+
+    fn defenses(&self) -> &Defenses {
+        &self.defenses
+    }
+
+    fn defenses_mut(&mut self) -> &mut Defenses {
+        &mut self.defenses
+    }
+
+    fn saving_throws(&self) -> &Vec<RollType> {
+        &self.saves
+    }
+
+    fn saving_throws_mut(&mut self) -> &mut Vec<RollType> {
+        &mut self.saves
+    }
+
+    fn concentration(&self) -> Option<&Concentration> {
+        self.concentration.as_ref()
+    }
+
+    fn set_concentration(&mut self, effect: Option<Concentration>) -> Option<Concentration> {
+        replace(&mut self.concentration, effect)
+    }
+
+    fn skill_bonus(&self, skill: Skill) -> i32 {
+        let prof = match self.skill_proficiency(skill) {
+            Some(Proficiency::Proficient) => self.proficiency_bonus(),
+            Some(Proficiency::Expertise) => self.proficiency_bonus() * 2,
+            None => 0,
+        };
+        self.ability_bonus(skill.ability()) + prof
+    }
+
+    fn proficiency_bonus(&self) -> i32 {
+        2 + (self.level.saturating_sub(1) / 4) as i32
+    }
+
+    fn armor_class(&self) -> i32 {
+        self.inventory.armor_class(self.ability_bonus(Dexterity))
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::borrow::ToOwned;
+    use std::fs::{File, create_dir_all};
+    use std::io::prelude::*;
     use super::Player;
+    use data::action::Action;
     use data::Entity;
-    use data::RollType::{Basic, Dexterity, Constitution};
+    use data::RollType::{Basic, Dexterity, Constitution, Wisdom};
+    use data::damage::Adjustment::{Immune, Resisted};
+    use data::damage::DamageType::{Cold, Fire, Piercing, Poison};
+    use data::class::CharacterClass::{Fighter, Wizard};
+    use data::damage::Defenses;
+    use data::dice::Dice;
     use data::game::Game;
+    use data::item::{Item, ItemKind};
+    use data::macros::Macro;
+    use data::modifier::Modifier;
+    use data::modifier::Expiry::{LongRest, Permanent, ShortRest};
+    use data::modifier::Operation::{Add, Set};
+    use data::race::RaceInfo;
+    use data::skill::Proficiency::{Expertise, Proficient};
+    use data::skill::Skill::{Athletics, Deception, Perception, Stealth};
+    use data::spell::{Concentration, Spell, SpellEffect};
     use data::stats::Stats;
     use data::utils::Position;
 
@@ -169,7 +742,27 @@ mod test {
             stats: Stats::new(20, 30, 12, 12, 12, 12, 12, 12),
             feats: Vec::new(),
             temp_stats: None,
+            modifiers: Vec::new(),
             position: Position(0, 0),
+            defenses: Defenses::new(),
+            saves: Vec::new(),
+            level: 1,
+            skills: Vec::new(),
+            class: None,
+            xp: 0,
+            max_health: 20,
+            race: None,
+            inventory: Inventory::new(),
+            coins: Purse::new(),
+            spells: Vec::new(),
+            slots_used: Vec::new(),
+            concentration: None,
+            hit_dice_used: 0,
+            rules: None,
+            extra_stats: Vec::new(),
+            macros: Vec::new(),
+            feat_saves: Vec::new(),
+            feat_skills: Vec::new(),
         };
         assert_eq!(p, m);
     }
@@ -182,33 +775,132 @@ mod test {
         assert_eq!(l, p);
     }
 
+    #[test]
+    fn load_old_player() {
+        // A character saved before any of the fields added since the first release.
+        let data = "{\"username\":\"test_old\",\"password\":\"test\",\"stats\":{\"health\":15,\"movement\":30,\
+                    \"strength\":12,\"dexterity\":12,\"constitution\":12,\"wisdom\":12,\"intellect\":12,\
+                    \"charisma\":12},\"feats\":[\"Alert\"],\"temp_stats\":null,\"position\":{\"_field0\":1,\
+                    \"_field1\":2}}";
+        create_dir_all("users/").unwrap();
+        File::create("users/test_old.json").unwrap().write_all(data.as_bytes()).unwrap();
+        let mut exp = Player::create_test("test_old", "test", 15, 30, 12, 12, 12, 12, 12, 12);
+        exp.feats.push("Alert".to_owned());
+        exp.position = Position(1, 2);
+        assert_eq!(Player::load("test_old").unwrap(), exp);
+    }
+
+    #[test]
+    fn save_load_macros() {
+        let mut p = Player::create_test("test_macros", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.set_macro(Macro::new("sneak", "1d20+dex+prof;3d6").unwrap());
+        p.save().unwrap();
+        assert_eq!(Player::load("test_macros").unwrap(), p);
+    }
+
+    #[test]
+    fn macros() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        assert!(!p.set_macro(Macro::new("sneak", "1d20+dex").unwrap()));
+        assert!(p.set_macro(Macro::new("Sneak", "1d20+dex+prof").unwrap()));
+        assert_eq!(p.macros.len(), 1);
+        assert_eq!(p.get_macro("SNEAK").unwrap().rolls, vec![format!("1d20+dex+prof")]);
+        assert!(p.remove_macro("stab").is_err());
+        assert_eq!(p.remove_macro("sneak").unwrap().name, format!("sneak"));
+        assert!(p.get_macro("sneak").is_none());
+    }
+
     #[test]
     fn add_feat() {
         let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
         assert_eq!(p.feats.len(), 0);
-        p.add_feat("Test Feat");
+        assert_eq!(p.add_feat("Test Feat").unwrap(), "Test Feat");
         assert_eq!(p.feats.len(), 1);
         assert_eq!(&p.feats[0][..], "Test Feat");
+        assert!(p.add_feat("test feat").is_err());
+        assert_eq!(p.remove_feat("test feat").unwrap(), "Test Feat");
+        assert!(p.feats.is_empty());
+    }
+
+    #[test]
+    fn feat_modifiers() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        assert_eq!(p.add_feat("resilient").unwrap(), "Resilient");
+        assert_eq!(p.stats.constitution, 13);
+        assert_eq!(p.saves, vec![Constitution]);
+        p.add_feat("Mobile").unwrap();
+        p.add_feat("Actor").unwrap();
+        assert_eq!(p.stats.movement, 40);
+        assert_eq!(p.skill_proficiency(Deception), Some(Proficient));
+        p.remove_feat("Resilient").unwrap();
+        p.remove_feat("Mobile").unwrap();
+        p.remove_feat("Actor").unwrap();
+        assert_eq!(p.stats.constitution, 12);
+        assert!(p.saves.is_empty());
+        assert_eq!(p.stats.movement, 30);
+        assert_eq!(p.skill_proficiency(Deception), None);
+        assert_eq!(format!("{}", p.add_feat("Grappler").unwrap_err()), format!("Grappler requires Strength 13."));
+    }
+
+    #[test]
+    fn feat_modifiers_keep_existing_proficiency() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.saves.push(Constitution);
+        p.set_skill_proficiency(Deception, Some(Proficient));
+        p.add_feat("Resilient").unwrap();
+        p.add_feat("Actor").unwrap();
+        assert_eq!(p.saves, vec![Constitution]);
+        p.remove_feat("Resilient").unwrap();
+        p.remove_feat("Actor").unwrap();
+        assert_eq!(p.saves, vec![Constitution]);
+        assert_eq!(p.skill_proficiency(Deception), Some(Proficient));
+        assert!(p.feat_skills.is_empty());
+    }
+
+    #[test]
+    fn tough_feat() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.add_feat("Tough").unwrap();
+        assert_eq!(p.max_health, 22);
+        assert_eq!(p.stats.health, 22);
+        p.award_xp(300, false);
+        assert_eq!(p.max_health, 30);
+        p.remove_feat("Tough").unwrap();
+        assert_eq!(p.max_health, 26);
+        assert_eq!(p.stats.health, 26);
     }
 
     #[test]
     fn damage() {
         let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
         assert_eq!(p.stats().health, 20);
-        assert!(p.damage(5));
+        assert!(p.damage(5, None).conscious);
         assert_eq!(p.stats().health, 15);
-        assert!(!p.damage(16));
+        assert!(!p.damage(16, None).conscious);
         assert_eq!(p.stats().health, 0);
     }
 
+    #[test]
+    fn damage_with_defenses() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.defenses.add_resistance(Fire);
+        p.defenses.add_immunity(Cold);
+        let r = p.damage(9, Some(Fire));
+        assert_eq!((r.raw, r.dealt, r.adjustment, r.conscious), (9, 4, Resisted, true));
+        assert_eq!(p.stats().health, 16);
+        let r = p.damage(30, Some(Cold));
+        assert_eq!((r.dealt, r.adjustment, r.conscious), (0, Immune, true));
+        assert_eq!(p.stats().health, 16);
+    }
+
     #[test]
     fn damage_temp_health() {
         let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
         p.set_temp_stats(Stats::new(40, 30, 10, 10, 10, 10, 10, 10));
         assert_eq!(p.stats().health, 40);
-        assert!(p.damage(5));
+        assert!(p.damage(5, None).conscious);
         assert_eq!(p.stats().health, 35);
-        assert!(!p.damage(35));
+        assert!(!p.damage(35, None).conscious);
         assert_eq!(p.stats().health, 0);
     }
 
@@ -287,6 +979,197 @@ mod test {
         p.clear_temp_stats()
     }
 
+    #[test]
+    fn save_bonus() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 14, 8, 12, 12, 12);
+        assert_eq!(p.save_bonus(Dexterity), 2);
+        assert_eq!(p.save_bonus(Constitution), -1);
+        p.saving_throws_mut().push(Dexterity);
+        p.saves.push(Constitution);
+        assert_eq!(p.save_bonus(Dexterity), 4);
+        assert_eq!(p.save_bonus(Constitution), 1);
+        assert_eq!(p.save_bonus(Wisdom), 1);
+    }
+
+    #[test]
+    fn proficiency_bonus() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        assert_eq!(p.proficiency_bonus(), 2);
+        p.level = 5;
+        assert_eq!(p.proficiency_bonus(), 3);
+        p.level = 17;
+        assert_eq!(p.proficiency_bonus(), 6);
+    }
+
+    #[test]
+    fn skill_bonus() {
+        let mut p = Player::create_test("test", "test", 20, 30, 8, 14, 12, 12, 12, 12);
+        p.set_skill_proficiency(Perception, Some(Proficient));
+        p.set_skill_proficiency(Stealth, Some(Expertise));
+        assert_eq!(p.skill_bonus(Athletics), -1);
+        assert_eq!(p.skill_bonus(Perception), 3);
+        assert_eq!(p.skill_bonus(Stealth), 6);
+        p.set_skill_proficiency(Stealth, None);
+        assert_eq!(p.skill_proficiency(Stealth), None);
+        assert_eq!(p.skill_bonus(Stealth), 2);
+    }
+
+    #[test]
+    fn apply_race() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.apply_race(RaceInfo::find("dwarf", Some("hill")).unwrap());
+        assert_eq!(p.stats, Stats::new(20, 30, 12, 12, 14, 13, 12, 12));
+        assert_eq!(p.defenses.resistances, vec![Poison]);
+        assert_eq!(&p.race.unwrap().name[..], "Dwarf");
+    }
+
+    #[test]
+    fn armor_class_from_equipment() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 16, 12, 12, 12, 12);
+        assert_eq!(p.armor_class(), 13);
+        p.inventory.add(Item::new("Chain", 1, 55.0, 75, ItemKind::Armor { base: 16, max_dex: Some(0) }));
+        p.inventory.equip("chain").unwrap();
+        assert_eq!(p.armor_class(), 16);
+    }
+
+    #[test]
+    fn weapon_attack() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 16, 12, 12, 12, 12);
+        p.inventory.add(Item::new("Rapier", 1, 2.0, 25, ItemKind::Weapon {
+            damage: Dice::new(1, 8, 0), damage_type: Piercing, finesse: true, reach: 5
+        }));
+        assert_eq!(p.weapon_attack("rapier"), None);
+        p.inventory.equip("rapier").unwrap();
+        assert_eq!(p.weapon_attack("rapier"), Some(Action::attack("Rapier", 5, 5, Dice::new(1, 8, 3), Piercing)));
+        assert_eq!(p.carrying_capacity(), 180.0);
+    }
+
+    #[test]
+    fn award_xp() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 14, 12, 12, 12);
+        p.class = Some(Fighter);
+        assert!(p.award_xp(299, false).is_empty());
+        assert_eq!(p.award_xp(700, false), vec![(2, 8), (3, 8)]);
+        assert_eq!(p.level, 3);
+        assert_eq!(p.xp, 999);
+        assert_eq!(p.max_health, 36);
+        assert_eq!(p.stats.health, 36);
+    }
+
+    #[test]
+    fn hit_points_per_level_rolled() {
+        let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        let hp = p.hit_points_per_level(true);
+        assert!(hp >= 2 && hp <= 9);
+    }
+
+    #[test]
+    fn spell_slots() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 16, 12);
+        assert_eq!(p.slots_remaining(1), 0);
+        p.class = Some(Wizard);
+        p.level = 3;
+        assert_eq!(p.slots_remaining(2), 2);
+        p.spend_slot(2).unwrap();
+        p.spend_slot(2).unwrap();
+        assert!(p.spend_slot(2).is_err());
+        assert_eq!(p.slots_remaining(1), 4);
+        p.recover_slots();
+        assert_eq!(p.slots_remaining(2), 2);
+        assert_eq!(p.spell_attack_bonus(), 5);
+        assert_eq!(p.spell_save_dc(), 13);
+    }
+
+    #[test]
+    fn check_spell() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 16, 12);
+        let fireball = Spell::new("Fireball", 3, SpellEffect::Utility);
+        assert!(p.check_spell(&Spell::new("Light", 0, SpellEffect::Utility)).is_err());
+        p.class = Some(Wizard);
+        assert!(p.check_spell(&Spell::new("Light", 0, SpellEffect::Utility)).is_ok());
+        assert!(p.check_spell(&fireball).is_err());
+        p.level = 5;
+        assert!(p.check_spell(&fireball).is_ok());
+    }
+
+    #[test]
+    fn prepare_spells() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 8, 12);
+        p.class = Some(Wizard);
+        p.learn_spell(Spell::new("Shield", 1, SpellEffect::Utility));
+        p.learn_spell(Spell::new("Sleep", 1, SpellEffect::Utility));
+        p.learn_spell(Spell::new("Light", 0, SpellEffect::Utility));
+        assert!(!p.spell("shield").unwrap().prepared);
+        assert!(p.spell("light").unwrap().prepared);
+        assert_eq!(p.max_prepared(), 1);
+        p.prepare_spell("shield", true).unwrap();
+        assert!(p.prepare_spell("sleep", true).is_err());
+        p.prepare_spell("shield", false).unwrap();
+        p.prepare_spell("sleep", true).unwrap();
+        assert!(p.prepare_spell("light", false).is_err());
+    }
+
+    #[test]
+    fn damage_breaks_concentration() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.concentration = Some(Concentration::new("Bless", &["test2"]));
+        p.defenses.add_immunity(Fire);
+        assert_eq!(p.damage(10, Some(Fire)).concentration, None);
+        let r = p.damage(30, None);
+        let check = r.concentration.unwrap();
+        assert!(!check.kept);
+        assert_eq!(check.dc, 15);
+        assert_eq!(check.effect.spell, format!("Bless"));
+        assert_eq!(p.concentration, None);
+    }
+
+    #[test]
+    fn rest_expires_modifiers() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.modifiers.push(Modifier::new("Bless", None, "str", Add(1), ShortRest).unwrap());
+        p.modifiers.push(Modifier::new("Aid", None, "con", Add(1), LongRest).unwrap());
+        p.modifiers.push(Modifier::new("Belt", None, "str", Set(21), Permanent).unwrap());
+        assert_eq!(p.stats().strength, 22);
+        let r = p.short_rest(0);
+        assert_eq!(r.expired.len(), 1);
+        assert_eq!(p.stats().strength, 21);
+        let r = p.long_rest();
+        assert_eq!(r.expired[0].name, "Aid");
+        assert_eq!(p.modifiers.len(), 1);
+    }
+
+    #[test]
+    fn short_rest() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.level = 3;
+        p.stats.health = 19;
+        let r = p.short_rest(3);
+        assert_eq!((r.dice_spent, r.healed), (1, 1));
+        assert_eq!(p.hit_dice_remaining(), 2);
+        let r = p.short_rest(3);
+        assert_eq!((r.dice_spent, r.healed), (0, 0));
+        p.stats.health = 1;
+        p.short_rest(5);
+        assert_eq!(p.hit_dice_remaining(), 0);
+    }
+
+    #[test]
+    fn long_rest() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.class = Some(Wizard);
+        p.level = 4;
+        p.hit_dice_used = 4;
+        p.stats.health = 5;
+        p.spend_slot(1).unwrap();
+        p.concentration = Some(Concentration::new("Bless", &[]));
+        let r = p.long_rest();
+        assert_eq!((r.healed, r.dice_regained, r.slots_recovered), (15, 2, true));
+        assert_eq!(r.ended, Some(Concentration::new("Bless", &[])));
+        assert_eq!(p.hit_dice_remaining(), 2);
+        assert_eq!(p.slots_remaining(1), 4);
+        assert_eq!(p.concentration, None);
+    }
+
     #[test]
     fn basic_roll() {
         let p = Player::create_test("test", "test", 20, 30, 12, 12, 8, 12, 12, 12);
@@ -315,4 +1198,24 @@ mod test {
             assert!(r >= 1 && r <= 19);
         }
     }
+
+    #[test]
+    fn low_stat_roll_does_not_wrap() {
+        let p = Player::create_test("test", "test", 20, 30, 12, 12, 0, 12, 12, 12);
+        for _ in 0..1000 {
+            let r = p.roll(Constitution);
+            assert!(r >= 1 && r <= 15);
+        }
+    }
+
+    #[test]
+    fn heal_large_amounts() {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        p.max_health = 400;
+        assert_eq!(p.heal(i32::max_value()), 380);
+        assert_eq!(p.stats.health, 400);
+        p.damage(10, None);
+        assert_eq!(p.heal(-5), 0);
+        assert_eq!(p.stats.health, 390);
+    }
 }
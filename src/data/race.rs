@@ -0,0 +1,197 @@
+use std::ascii::AsciiExt;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::BotResult;
+use data::BotError::InvalidInput;
+use data::RollType;
+use data::RollType::{Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma};
+use data::damage::DamageType;
+use data::damage::DamageType::{Fire, Poison};
+use self::Size::{Small, Medium};
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum Size {
+    Small,
+    Medium,
+}
+
+// A row of the race table. Races with subraces have one row per subrace, which includes the
+// bonuses and traits of the parent race.
+#[derive(Debug)]
+pub struct RaceInfo {
+    pub name: &'static str,
+    pub subrace: Option<&'static str>,
+    pub bonuses: &'static [(RollType, u8)],
+    pub speed: u8,
+    pub size: Size,
+    pub darkvision: u8,
+    pub resistances: &'static [DamageType],
+    pub traits: &'static [&'static str],
+}
+
+pub static RACES: [RaceInfo; 15] = [
+    RaceInfo {
+        name: "Dragonborn", subrace: None, bonuses: &[(Strength, 2), (Charisma, 1)],
+        speed: 30, size: Medium, darkvision: 0, resistances: &[],
+        traits: &["Breath Weapon", "Draconic Ancestry"],
+    },
+    RaceInfo {
+        name: "Dwarf", subrace: Some("Hill"), bonuses: &[(Constitution, 2), (Wisdom, 1)],
+        speed: 25, size: Medium, darkvision: 60, resistances: &[Poison],
+        traits: &["Dwarven Resilience", "Stonecunning", "Dwarven Toughness"],
+    },
+    RaceInfo {
+        name: "Dwarf", subrace: Some("Mountain"), bonuses: &[(Constitution, 2), (Strength, 2)],
+        speed: 25, size: Medium, darkvision: 60, resistances: &[Poison],
+        traits: &["Dwarven Resilience", "Stonecunning", "Dwarven Armor Training"],
+    },
+    RaceInfo {
+        name: "Elf", subrace: Some("High"), bonuses: &[(Dexterity, 2), (Intellect, 1)],
+        speed: 30, size: Medium, darkvision: 60, resistances: &[],
+        traits: &["Fey Ancestry", "Trance", "Keen Senses", "Cantrip"],
+    },
+    RaceInfo {
+        name: "Elf", subrace: Some("Wood"), bonuses: &[(Dexterity, 2), (Wisdom, 1)],
+        speed: 35, size: Medium, darkvision: 60, resistances: &[],
+        traits: &["Fey Ancestry", "Trance", "Keen Senses", "Mask of the Wild"],
+    },
+    RaceInfo {
+        name: "Elf", subrace: Some("Dark"), bonuses: &[(Dexterity, 2), (Charisma, 1)],
+        speed: 30, size: Medium, darkvision: 120, resistances: &[],
+        traits: &["Fey Ancestry", "Trance", "Keen Senses", "Sunlight Sensitivity"],
+    },
+    RaceInfo {
+        name: "Gnome", subrace: Some("Forest"), bonuses: &[(Intellect, 2), (Dexterity, 1)],
+        speed: 25, size: Small, darkvision: 60, resistances: &[],
+        traits: &["Gnome Cunning", "Natural Illusionist", "Speak with Small Beasts"],
+    },
+    RaceInfo {
+        name: "Gnome", subrace: Some("Rock"), bonuses: &[(Intellect, 2), (Constitution, 1)],
+        speed: 25, size: Small, darkvision: 60, resistances: &[],
+        traits: &["Gnome Cunning", "Artificer's Lore", "Tinker"],
+    },
+    RaceInfo {
+        name: "Half-Elf", subrace: None, bonuses: &[(Charisma, 2)],
+        speed: 30, size: Medium, darkvision: 60, resistances: &[],
+        traits: &["Fey Ancestry", "Skill Versatility"],
+    },
+    RaceInfo {
+        name: "Half-Orc", subrace: None, bonuses: &[(Strength, 2), (Constitution, 1)],
+        speed: 30, size: Medium, darkvision: 60, resistances: &[],
+        traits: &["Menacing", "Relentless Endurance", "Savage Attacks"],
+    },
+    RaceInfo {
+        name: "Halfling", subrace: Some("Lightfoot"), bonuses: &[(Dexterity, 2), (Charisma, 1)],
+        speed: 25, size: Small, darkvision: 0, resistances: &[],
+        traits: &["Lucky", "Brave", "Halfling Nimbleness", "Naturally Stealthy"],
+    },
+    RaceInfo {
+        name: "Halfling", subrace: Some("Stout"), bonuses: &[(Dexterity, 2), (Constitution, 1)],
+        speed: 25, size: Small, darkvision: 0, resistances: &[Poison],
+        traits: &["Lucky", "Brave", "Halfling Nimbleness", "Stout Resilience"],
+    },
+    RaceInfo {
+        name: "Human", subrace: None,
+        bonuses: &[(Strength, 1), (Dexterity, 1), (Constitution, 1), (Wisdom, 1), (Intellect, 1),
+                   (Charisma, 1)],
+        speed: 30, size: Medium, darkvision: 0, resistances: &[],
+        traits: &[],
+    },
+    RaceInfo {
+        name: "Human", subrace: Some("Variant"), bonuses: &[],
+        speed: 30, size: Medium, darkvision: 0, resistances: &[],
+        traits: &["Two ability scores of your choice increase by 1", "Bonus Skill", "Bonus Feat"],
+    },
+    RaceInfo {
+        name: "Tiefling", subrace: None, bonuses: &[(Intellect, 1), (Charisma, 2)],
+        speed: 30, size: Medium, darkvision: 60, resistances: &[Fire],
+        traits: &["Hellish Resistance", "Infernal Legacy"],
+    },
+];
+
+impl RaceInfo {
+    // Races with a row that has no subrace can be picked without one, e.g. "human" as opposed
+    // to "human variant".
+    pub fn find(race: &str, subrace: Option<&str>) -> BotResult<&'static RaceInfo> {
+        let rows: Vec<_> = RACES.iter().filter(|r| r.name.eq_ignore_ascii_case(race)).collect();
+        if rows.is_empty() {
+            return Err(InvalidInput(format!("{} is not a valid race.", race)));
+        }
+        let name = rows[0].name;
+        match subrace {
+            Some(sub) => rows.into_iter().find(|r| r.subrace.map_or(false, |s| s.eq_ignore_ascii_case(sub)))
+                .ok_or(InvalidInput(format!("{} is not a valid subrace of {}.", sub, name))),
+            None => match rows.iter().find(|r| r.subrace.is_none()) {
+                Some(row) => Ok(*row),
+                None => {
+                    let subs: Vec<_> = rows.iter().filter_map(|r| r.subrace).collect();
+                    Err(InvalidInput(format!("{} requires a subrace: {}.", name, subs.join(", "))))
+                },
+            },
+        }
+    }
+
+    pub fn to_race(&self) -> Race {
+        Race {
+            name: self.name.to_string(),
+            subrace: self.subrace.map(|s| s.to_string()),
+            size: self.size,
+            speed: self.speed,
+            darkvision: self.darkvision,
+            traits: self.traits.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+// The race as stored on a player, so that the table can change without breaking saved players.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Race {
+    pub name: String,
+    pub subrace: Option<String>,
+    pub size: Size,
+    pub speed: u8,
+    pub darkvision: u8,
+    pub traits: Vec<String>,
+}
+
+impl Display for Race {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        match self.subrace {
+            Some(ref sub) => write!(fmt, "{} {}", sub, self.name),
+            None => write!(fmt, "{}", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RaceInfo;
+    use super::Size::{Small, Medium};
+    use data::BotError::InvalidInput;
+    use data::RollType::{Dexterity, Constitution};
+
+    #[test]
+    fn find_race() {
+        let tiefling = RaceInfo::find("tiefling", None).unwrap();
+        assert_eq!(tiefling.name, "Tiefling");
+        assert_eq!(tiefling.size, Medium);
+        let stout = RaceInfo::find("HALFLING", Some("stout")).unwrap();
+        assert_eq!(stout.bonuses.to_vec(), vec![(Dexterity, 2), (Constitution, 1)]);
+        assert_eq!(stout.size, Small);
+        assert_eq!(RaceInfo::find("human", None).unwrap().subrace, None);
+    }
+
+    #[test]
+    fn find_race_failed() {
+        assert_eq!(RaceInfo::find("test", None).err(), Some(InvalidInput(format!("test is not a valid race."))));
+        assert_eq!(RaceInfo::find("dwarf", None).err(), Some(InvalidInput(format!("Dwarf requires a subrace: Hill, Mountain."))));
+        assert_eq!(RaceInfo::find("dwarf", Some("test")).err(), Some(InvalidInput(format!("test is not a valid subrace of Dwarf."))));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", RaceInfo::find("elf", Some("wood")).unwrap().to_race()), format!("Wood Elf"));
+        assert_eq!(format!("{}", RaceInfo::find("half-orc", None).unwrap().to_race()), format!("Half-Orc"));
+    }
+}
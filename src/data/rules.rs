@@ -0,0 +1,400 @@
+use std::ascii::AsciiExt;
+use std::borrow::ToOwned;
+use std::cmp::max;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, create_dir_all};
+use std::io::{Error, ErrorKind, Result};
+use std::io::prelude::*;
+use std::path::Path;
+use std::result::Result as StdResult;
+use data::{BotResult, RollType};
+use data::BotError::InvalidInput;
+use data::dice::{record, roll_die};
+use data::RollType::{Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma};
+use rustc_serialize::json::{decode, encode};
+use self::BonusFormula::{Flat, Step};
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum BonusFormula {
+    // (value - base) / step, as in 5e and Pathfinder.
+    Step { base: i32, step: i32 },
+    // The value is added to rolls as it is.
+    Flat,
+}
+
+impl BonusFormula {
+    pub fn bonus(&self, value: i32) -> i32 {
+        match *self {
+            Step { base, step } => {
+                let step = if step == 0 { 1 } else { step };
+                let diff = value.saturating_sub(base);
+                // Bonuses round down, so a 5e score of 9 gives -1 rather than 0.
+                match (diff.checked_div(step), diff.checked_rem(step)) {
+                    (Some(quotient), Some(rem)) if rem != 0 && (rem < 0) != (step < 0) => quotient - 1,
+                    (Some(quotient), _) => quotient,
+                    (None, _) => i32::max_value(),
+                }
+            },
+            Flat => value,
+        }
+    }
+}
+
+impl Display for BonusFormula {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        match *self {
+            Step { base, step } => write!(fmt, "(value - {}) / {}", base, step),
+            Flat => write!(fmt, "value"),
+        }
+    }
+}
+
+// How a check, save or attack rolled with a rule system came out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outcome {
+    pub natural: i32,
+    pub bonus: i32,
+    pub total: i32,
+    // What the roll was up against: the DC or armor class, or the stat in roll-under systems.
+    pub target: i32,
+    pub success: bool,
+    pub roll_under: bool,
+}
+
+impl Outcome {
+    // Higher scores win contests. Roll-under systems rank a success over a failure, and then rolls
+    // that came in further under the stat.
+    pub fn score(&self) -> (bool, i32) {
+        if self.roll_under {
+            (self.success, self.target - self.natural)
+        } else {
+            (false, self.total)
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        if self.roll_under {
+            write!(fmt, "{} (needing {} or less)", self.natural, self.target)
+        } else {
+            write!(fmt, "{} ({}{:+})", self.total, self.natural, self.bonus)
+        }
+    }
+}
+
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct StatDef {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub min: i32,
+    pub max: i32,
+    // The built-in ability this stat is stored as, if any. Other stats are stored by name.
+    pub builtin: Option<RollType>,
+}
+
+impl StatDef {
+    pub fn new(name: &str, aliases: &[&str], min: i32, max: i32, builtin: Option<RollType>) -> StatDef {
+        StatDef {
+            name: name.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            min: min,
+            max: max,
+            builtin: builtin,
+        }
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name) || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+
+    pub fn validate(&self, value: i32) -> BotResult<()> {
+        if value < self.min || value > self.max {
+            Err(InvalidInput(format!("{} must be between {} and {}.", self.name, self.min, self.max)))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// The stats a campaign's game system uses and how they turn into bonuses. Health and movement are
+// shared by every system and aren't part of the schema.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct RuleSystem {
+    pub name: String,
+    pub stats: Vec<StatDef>,
+    pub bonus: BonusFormula,
+    pub die: u8,
+    // Whether rolls succeed at or under the stat instead of adding a bonus, as in Call of Cthulhu.
+    pub roll_under: bool,
+}
+
+impl RuleSystem {
+    pub fn standard() -> RuleSystem {
+        RuleSystem::d20("5e", 1, 30)
+    }
+
+    pub fn pathfinder() -> RuleSystem {
+        RuleSystem::d20("pathfinder", 1, 45)
+    }
+
+    fn d20(name: &str, min: i32, max: i32) -> RuleSystem {
+        RuleSystem {
+            name: name.to_string(),
+            stats: vec![
+                StatDef::new("Strength", &["str"], min, max, Some(Strength)),
+                StatDef::new("Dexterity", &["dex"], min, max, Some(Dexterity)),
+                StatDef::new("Constitution", &["con"], min, max, Some(Constitution)),
+                StatDef::new("Wisdom", &["wis"], min, max, Some(Wisdom)),
+                StatDef::new("Intellect", &["int", "intelligence"], min, max, Some(Intellect)),
+                StatDef::new("Charisma", &["cha"], min, max, Some(Charisma)),
+            ],
+            bonus: Step { base: 10, step: 2 },
+            die: 20,
+            roll_under: false,
+        }
+    }
+
+    pub fn call_of_cthulhu() -> RuleSystem {
+        RuleSystem {
+            name: "coc".to_string(),
+            stats: vec![
+                StatDef::new("STR", &["strength"], 1, 99, Some(Strength)),
+                StatDef::new("CON", &["constitution"], 1, 99, Some(Constitution)),
+                StatDef::new("SIZ", &["size"], 1, 99, None),
+                StatDef::new("DEX", &["dexterity"], 1, 99, Some(Dexterity)),
+                StatDef::new("APP", &["appearance"], 1, 99, None),
+                StatDef::new("INT", &["intelligence"], 1, 99, Some(Intellect)),
+                StatDef::new("POW", &["power"], 1, 99, None),
+                StatDef::new("EDU", &["education"], 1, 99, None),
+            ],
+            bonus: Flat,
+            die: 100,
+            roll_under: true,
+        }
+    }
+
+    // Finds a built-in system by name, or loads a homebrew one from rules/name.json.
+    pub fn find(name: &str) -> BotResult<RuleSystem> {
+        match &name.to_ascii_lowercase()[..] {
+            "5e" | "dnd" => Ok(RuleSystem::standard()),
+            "pathfinder" | "pf" => Ok(RuleSystem::pathfinder()),
+            "coc" | "cthulhu" => Ok(RuleSystem::call_of_cthulhu()),
+            _ => RuleSystem::load(name).map_err(|_| InvalidInput(format!("{} is not a known rule system.", name))),
+        }
+    }
+
+    pub fn load(name: &str) -> Result<RuleSystem> {
+        let mut file = try!(File::open(&Path::new(&try!(RuleSystem::path(name)))));
+        let mut data = String::new();
+        try!(file.read_to_string(&mut data));
+        decode(&data).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to decode rule system."
+        ))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = try!(RuleSystem::path(&self.name));
+        try!(create_dir_all(&Path::new("rules/")));
+        let mut f = try!(File::create(&Path::new(&path)));
+        f.write_all(try!(encode(self).map_err(|_| Error::new(
+            ErrorKind::InvalidInput, "Failed to encode rule system."
+        ))).as_bytes())
+    }
+
+    // Where a homebrew system is kept. Names come from players, so they can only use letters,
+    // digits, dashes and underscores, and can't reach outside of rules/.
+    fn path(name: &str) -> Result<String> {
+        let valid = !name.is_empty() && name.chars().all(|c| match c.to_ascii_lowercase() {
+            'a'...'z' | '0'...'9' | '-' | '_' => true,
+            _ => false,
+        });
+        if valid {
+            Ok(format!("rules/{}.json", name))
+        } else {
+            Err(Error::new(ErrorKind::InvalidInput, "Invalid rule system name."))
+        }
+    }
+
+    pub fn is_standard(&self) -> bool {
+        *self == RuleSystem::standard()
+    }
+
+    pub fn stat(&self, name: &str) -> Option<&StatDef> {
+        self.stats.iter().find(|s| s.is_named(name))
+    }
+
+    pub fn bonus(&self, value: i32) -> i32 {
+        if self.roll_under { 0 } else { self.bonus.bonus(value) }
+    }
+
+    // The stats in the order they're given when registering, e.g. "str dex con wis int cha".
+    pub fn stat_list(&self) -> String {
+        let names: Vec<_> = self.stats.iter().map(|s| {
+            s.aliases.iter().find(|a| a.len() <= 3).unwrap_or(&s.name).to_ascii_lowercase()
+        }).collect();
+        names.join(" ")
+    }
+
+    // The bonus a roll against the stat's value gets, which roll-under systems don't use.
+    pub fn modifier(&self, value: Option<i32>) -> i32 {
+        match value {
+            Some(value) if !self.roll_under => self.bonus.bonus(value),
+            _ => 0,
+        }
+    }
+
+    // Rolls the system's die, adding the bonus for the stat if there is one. Roll-under systems
+    // report whether the roll succeeded instead.
+    pub fn roll(&self, value: Option<i32>) -> (i32, Option<bool>) {
+        let roll = roll_die(self.die as i32);
+        match value {
+            Some(value) if self.roll_under => (roll, Some(roll <= value)),
+            Some(value) => (max(1, roll.saturating_add(self.bonus.bonus(value))), None),
+            None => (roll, None),
+        }
+    }
+
+    // Like roll(...), but records the roll for the character under the stat's name.
+    pub fn roll_for(&self, character: &str, stat: &str, value: Option<i32>) -> (i32, Option<bool>) {
+        let res = self.roll(value);
+        let expression = if stat.is_empty() { format!("d{}", self.die) } else { format!("d{} ({})", self.die, stat) };
+        record(&expression, Some(character), self.modifier(value), res.0);
+        res
+    }
+
+    // Rolls a check, save or attack for a character whose stat has the value, recording it under
+    // the purpose. Systems that add bonuses succeed when the roll plus the bonus meets the target
+    // number, and roll-under systems when the roll is at or under the stat.
+    pub fn test(&self, character: &str, purpose: &str, value: i32, bonus: i32, target: i32) -> Outcome {
+        let natural = roll_die(self.die as i32);
+        let expression = format!("d{} ({})", self.die, purpose);
+        if self.roll_under {
+            record(&expression, Some(character), 0, natural);
+            Outcome {
+                natural: natural, bonus: 0, total: natural, target: value, success: natural <= value,
+                roll_under: true,
+            }
+        } else {
+            let total = natural.saturating_add(bonus);
+            record(&expression, Some(character), bonus, total);
+            Outcome {
+                natural: natural, bonus: bonus, total: total, target: target, success: total >= target,
+                roll_under: false,
+            }
+        }
+    }
+
+    // Natural rolls that always hit, for double damage: the highest face, or a 1 when rolling under.
+    pub fn is_critical(&self, natural: i32) -> bool {
+        natural == if self.roll_under { 1 } else { self.die as i32 }
+    }
+
+    // Natural rolls that always miss.
+    pub fn is_fumble(&self, natural: i32) -> bool {
+        natural == if self.roll_under { self.die as i32 } else { 1 }
+    }
+}
+
+impl Display for RuleSystem {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        let stats: Vec<_> = self.stats.iter().map(|s| format!("{} ({}-{})", s.name, s.min, s.max)).collect();
+        try!(write!(fmt, "{}: {}. ", self.name, stats.join(", ")));
+        if self.roll_under {
+            write!(fmt, "Roll a d{} at or under the stat.", self.die)
+        } else {
+            write!(fmt, "Roll a d{} plus {}.", self.die, self.bonus)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RuleSystem;
+    use super::BonusFormula::{Flat, Step};
+    use data::RollType::Strength;
+    use data::dice::{DiceSource, use_source};
+
+    #[test]
+    fn bonus() {
+        let step = Step { base: 10, step: 2 };
+        assert_eq!(step.bonus(10), 0);
+        assert_eq!(step.bonus(13), 1);
+        assert_eq!(step.bonus(8), -1);
+        assert_eq!(step.bonus(9), -1);
+        assert_eq!(step.bonus(1), -5);
+        assert_eq!(Step { base: 10, step: -2 }.bonus(13), -2);
+        assert_eq!(Flat.bonus(60), 60);
+        assert_eq!(Flat.bonus(200), 200);
+        assert_eq!(step.bonus(i32::min_value()), i32::min_value() / 2);
+    }
+
+    #[test]
+    fn find() {
+        assert!(RuleSystem::find("5E").unwrap().is_standard());
+        assert_eq!(RuleSystem::find("cthulhu").unwrap().name, "coc");
+        assert_eq!(format!("{}", RuleSystem::find("test").unwrap_err()), format!("test is not a known rule system."));
+        assert_eq!(format!("{}", RuleSystem::find("../users/test").unwrap_err()),
+                   format!("../users/test is not a known rule system."));
+        assert!(RuleSystem::load("..").is_err());
+        assert!(RuleSystem::load("a\\b").is_err());
+    }
+
+    #[test]
+    fn save_load() {
+        let mut r = RuleSystem::call_of_cthulhu();
+        r.name = "homebrew".to_string();
+        r.save().unwrap();
+        assert_eq!(RuleSystem::find("homebrew").unwrap(), r);
+    }
+
+    #[test]
+    fn stat() {
+        let r = RuleSystem::call_of_cthulhu();
+        assert_eq!(r.stat("strength").unwrap().builtin, Some(Strength));
+        assert_eq!(r.stat("pow").unwrap().name, "POW");
+        assert!(r.stat("wis").is_none());
+        assert_eq!(format!("{}", r.stat("siz").unwrap().validate(100).unwrap_err()),
+                   format!("SIZ must be between 1 and 99."));
+        assert_eq!(RuleSystem::standard().stat_list(), "str dex con wis int cha");
+        assert_eq!(r.stat_list(), "str con siz dex app int pow edu");
+    }
+
+    #[test]
+    fn roll() {
+        let r = RuleSystem::call_of_cthulhu();
+        for _ in 0..1000 {
+            let (roll, success) = r.roll(Some(50));
+            assert!(roll >= 1 && roll <= 100);
+            assert_eq!(success, Some(roll <= 50));
+        }
+        let (roll, success) = RuleSystem::standard().roll(Some(30));
+        assert!(roll >= 11 && roll <= 30);
+        assert_eq!(success, None);
+    }
+
+    #[test]
+    fn test_outcomes() {
+        use_source(Some(DiceSource::scripted(vec![12, 40, 60]).shared()));
+        let outcome = RuleSystem::standard().test("test", "Strength check", 14, 2, 15);
+        assert_eq!((outcome.total, outcome.success), (14, false));
+        assert_eq!(format!("{}", outcome), format!("14 (12+2)"));
+        let coc = RuleSystem::call_of_cthulhu();
+        let pass = coc.test("test", "STR check", 50, 0, 15);
+        let fail = coc.test("test", "STR check", 50, 0, 15);
+        use_source(None);
+        assert!(pass.success && !fail.success);
+        assert!(pass.score() > fail.score());
+        assert_eq!(format!("{}", pass), format!("40 (needing 50 or less)"));
+        assert!(coc.is_critical(1) && coc.is_fumble(100));
+        assert!(RuleSystem::standard().is_critical(20));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", RuleSystem::standard()),
+                   format!("5e: Strength (1-30), Dexterity (1-30), Constitution (1-30), Wisdom (1-30), \
+                            Intellect (1-30), Charisma (1-30). Roll a d20 plus (value - 10) / 2."));
+    }
+}
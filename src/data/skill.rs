@@ -0,0 +1,110 @@
+use std::ascii::AsciiExt;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::RollType;
+use data::RollType::{Strength, Dexterity, Wisdom, Intellect, Charisma};
+use self::Skill::*;
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum Skill {
+    Acrobatics,
+    AnimalHandling,
+    Arcana,
+    Athletics,
+    Deception,
+    History,
+    Insight,
+    Intimidation,
+    Investigation,
+    Medicine,
+    Nature,
+    Perception,
+    Performance,
+    Persuasion,
+    Religion,
+    SleightOfHand,
+    Stealth,
+    Survival,
+}
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum Proficiency {
+    Proficient,
+    Expertise,
+}
+
+impl Skill {
+    pub fn to_skill(skill: &str) -> Option<Skill> {
+        let name: String = skill.chars().filter(|c| *c != ' ' && *c != '_').collect();
+        match &name.to_ascii_lowercase()[..] {
+            "acrobatics" => Some(Acrobatics),
+            "animalhandling" | "animal" => Some(AnimalHandling),
+            "arcana" => Some(Arcana),
+            "athletics" => Some(Athletics),
+            "deception" => Some(Deception),
+            "history" => Some(History),
+            "insight" => Some(Insight),
+            "intimidation" => Some(Intimidation),
+            "investigation" => Some(Investigation),
+            "medicine" => Some(Medicine),
+            "nature" => Some(Nature),
+            "perception" => Some(Perception),
+            "performance" => Some(Performance),
+            "persuasion" => Some(Persuasion),
+            "religion" => Some(Religion),
+            "sleightofhand" | "sleight" => Some(SleightOfHand),
+            "stealth" => Some(Stealth),
+            "survival" => Some(Survival),
+            _ => None,
+        }
+    }
+
+    pub fn ability(&self) -> RollType {
+        match *self {
+            Athletics => Strength,
+            Acrobatics | SleightOfHand | Stealth => Dexterity,
+            Arcana | History | Investigation | Nature | Religion => Intellect,
+            AnimalHandling | Insight | Medicine | Perception | Survival => Wisdom,
+            Deception | Intimidation | Performance | Persuasion => Charisma,
+        }
+    }
+}
+
+impl Display for Skill {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        match *self {
+            AnimalHandling => write!(fmt, "Animal Handling"),
+            SleightOfHand => write!(fmt, "Sleight of Hand"),
+            skill => write!(fmt, "{:?}", skill),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Skill;
+    use super::Skill::{AnimalHandling, Athletics, SleightOfHand, Stealth};
+    use data::RollType::{Strength, Dexterity, Wisdom};
+
+    #[test]
+    fn to_skill() {
+        assert_eq!(Skill::to_skill("stealth"), Some(Stealth));
+        assert_eq!(Skill::to_skill("Sleight of Hand"), Some(SleightOfHand));
+        assert_eq!(Skill::to_skill("animal_handling"), Some(AnimalHandling));
+        assert_eq!(Skill::to_skill("test"), None);
+    }
+
+    #[test]
+    fn ability() {
+        assert_eq!(Athletics.ability(), Strength);
+        assert_eq!(Stealth.ability(), Dexterity);
+        assert_eq!(AnimalHandling.ability(), Wisdom);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", Stealth), format!("Stealth"));
+        assert_eq!(format!("{}", SleightOfHand), format!("Sleight of Hand"));
+    }
+}
@@ -0,0 +1,210 @@
+use std::ascii::AsciiExt;
+use std::cmp::min;
+use std::fmt::Error as FmtError;
+use std::fmt::{Display, Formatter};
+use std::result::Result as StdResult;
+use data::RollType;
+use data::damage::DamageType;
+use data::dice::Dice;
+use data::modifier::Modifier;
+
+#[derive(Clone, Copy, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub enum SpellEffect {
+    Utility,
+    Attack { damage: Dice, damage_type: DamageType },
+    Save { stat: RollType, damage: Dice, damage_type: DamageType, half: bool },
+}
+
+impl SpellEffect {
+    // Parses the effect option on a spell, e.g. attack=1d10/fire or save=dex/8d6/fire/half.
+    pub fn parse(opt: &str) -> Option<SpellEffect> {
+        let split: Vec<_> = opt.splitn(2, '=').collect();
+        if split.len() != 2 {
+            return None;
+        }
+        let parts: Vec<_> = split[1].split('/').collect();
+        match &split[0].to_ascii_lowercase()[..] {
+            "attack" if parts.len() == 2 => {
+                match (Dice::parse(parts[0]), DamageType::to_damage_type(parts[1])) {
+                    (Some(damage), Some(damage_type)) => Some(SpellEffect::Attack {
+                        damage: damage, damage_type: damage_type
+                    }),
+                    _ => None,
+                }
+            },
+            "save" if parts.len() == 3 || parts.len() == 4 => {
+                let half = match parts.get(3) {
+                    None => false,
+                    Some(p) if p.eq_ignore_ascii_case("half") => true,
+                    Some(_) => return None,
+                };
+                match (RollType::to_roll_type(parts[0]), Dice::parse(parts[1]),
+                       DamageType::to_damage_type(parts[2])) {
+                    (Some(stat), Some(damage), Some(damage_type)) => Some(SpellEffect::Save {
+                        stat: stat, damage: damage, damage_type: damage_type, half: half
+                    }),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    pub fn damage(&self) -> Option<Dice> {
+        match *self {
+            SpellEffect::Utility => None,
+            SpellEffect::Attack { damage, .. } | SpellEffect::Save { damage, .. } => Some(damage),
+        }
+    }
+}
+
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Spell {
+    pub name: String,
+    pub level: u8,
+    pub effect: SpellEffect,
+    // Extra damage dice for each slot level above the spell's own.
+    pub upcast: Option<Dice>,
+    pub prepared: bool,
+    pub concentration: bool,
+    // Put on each target when cast, with the spell as its source so it can be lifted again.
+    pub modifier: Option<Modifier>,
+}
+
+impl Spell {
+    pub fn new(name: &str, level: u8, effect: SpellEffect) -> Spell {
+        Spell {
+            name: name.to_string(),
+            level: level,
+            effect: effect,
+            upcast: None,
+            prepared: true,
+            concentration: false,
+            modifier: None,
+        }
+    }
+
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    pub fn is_cantrip(&self) -> bool {
+        self.level == 0
+    }
+
+    // The damage dealt when cast with a slot of the given level. Upcast dice only stack onto
+    // damage dice with the same number of sides.
+    pub fn damage_at(&self, slot_level: u8) -> Option<Dice> {
+        self.effect.damage().map(|mut damage| {
+            if let Some(extra) = self.upcast {
+                if extra.sides == damage.sides && slot_level > self.level {
+                    let count = extra.count as u16 * (slot_level - self.level) as u16;
+                    damage.count = min(255, damage.count as u16 + count) as u8;
+                }
+            }
+            damage
+        })
+    }
+}
+
+impl Display for Spell {
+    fn fmt(&self, fmt: &mut Formatter) -> StdResult<(), FmtError> {
+        if self.is_cantrip() {
+            try!(write!(fmt, "{} (cantrip", self.name));
+        } else {
+            try!(write!(fmt, "{} (level {}", self.name, self.level));
+        }
+        try!(match self.effect {
+            SpellEffect::Utility => Ok(()),
+            SpellEffect::Attack { damage, damage_type } => {
+                write!(fmt, ", attack for {} {}", damage, damage_type)
+            },
+            SpellEffect::Save { stat, damage, damage_type, half } => {
+                write!(fmt, ", {:?} save for {} {}{}", stat, damage, damage_type,
+                       if half { ", half on success" } else { "" })
+            },
+        });
+        if let Some(upcast) = self.upcast {
+            try!(write!(fmt, ", +{} per slot level", upcast));
+        }
+        if let Some(ref modifier) = self.modifier {
+            try!(write!(fmt, ", {}", modifier.describe_operation()));
+        }
+        if self.concentration {
+            try!(write!(fmt, ", concentration"));
+        }
+        if !self.prepared {
+            try!(write!(fmt, ", unprepared"));
+        }
+        write!(fmt, ")")
+    }
+}
+
+// A spell an entity is concentrating on, along with whoever it affects. Each entity can only
+// concentrate on one spell at a time.
+#[derive(Clone, RustcDecodable, RustcEncodable, Debug, PartialEq)]
+pub struct Concentration {
+    pub spell: String,
+    pub targets: Vec<String>,
+}
+
+impl Concentration {
+    pub fn new(spell: &str, targets: &[&str]) -> Concentration {
+        Concentration { spell: spell.to_string(), targets: targets.iter().map(|t| t.to_string()).collect() }
+    }
+
+    // Describes who stops being affected when the concentration ends, e.g. ", ending it for test2".
+    pub fn ending(&self) -> String {
+        if self.targets.is_empty() {
+            String::new()
+        } else {
+            format!(", ending it for {}", self.targets.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Spell, SpellEffect};
+    use data::RollType::Dexterity;
+    use data::damage::DamageType::{Fire, Radiant};
+    use data::dice::Dice;
+
+    #[test]
+    fn parse_effect() {
+        assert_eq!(SpellEffect::parse("attack=1d10/fire"), Some(SpellEffect::Attack {
+            damage: Dice::new(1, 10, 0), damage_type: Fire
+        }));
+        assert_eq!(SpellEffect::parse("save=dex/8d6/fire/half"), Some(SpellEffect::Save {
+            stat: Dexterity, damage: Dice::new(8, 6, 0), damage_type: Fire, half: true
+        }));
+        assert_eq!(SpellEffect::parse("save=dex/8d6/fire/test"), None);
+        assert_eq!(SpellEffect::parse("attack=1d10"), None);
+        assert_eq!(SpellEffect::parse("test"), None);
+    }
+
+    #[test]
+    fn damage_at() {
+        let mut fireball = Spell::new("Fireball", 3, SpellEffect::parse("save=dex/8d6/fire/half").unwrap());
+        fireball.upcast = Some(Dice::new(1, 6, 0));
+        assert_eq!(fireball.damage_at(3), Some(Dice::new(8, 6, 0)));
+        assert_eq!(fireball.damage_at(5), Some(Dice::new(10, 6, 0)));
+        assert_eq!(Spell::new("Light", 0, SpellEffect::Utility).damage_at(1), None);
+    }
+
+    #[test]
+    fn display() {
+        let mut fireball = Spell::new("Fireball", 3, SpellEffect::parse("save=dex/8d6/fire/half").unwrap());
+        fireball.upcast = Some(Dice::new(1, 6, 0));
+        assert_eq!(format!("{}", fireball),
+                   format!("Fireball (level 3, Dexterity save for 8d6 fire, half on success, +1d6 per slot level)"));
+        let mut flame = Spell::new("Sacred Flame", 0, SpellEffect::Save {
+            stat: Dexterity, damage: Dice::new(1, 8, 0), damage_type: Radiant, half: false
+        });
+        flame.prepared = false;
+        assert_eq!(format!("{}", flame), format!("Sacred Flame (cantrip, Dexterity save for 1d8 radiant, unprepared)"));
+        let mut bless = Spell::new("Bless", 1, SpellEffect::Utility);
+        bless.concentration = true;
+        assert_eq!(format!("{}", bless), format!("Bless (level 1, concentration)"));
+    }
+}
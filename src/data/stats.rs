@@ -1,56 +1,70 @@
 use std::ascii::AsciiExt;
+use std::cmp::{max, min};
+use data::{BotResult, RollType};
+use data::BotError::InvalidInput;
+use data::RollType::{Basic, Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma};
+use data::rules::BonusFormula;
+
+// Every stat is kept within these bounds, so that arithmetic on them can't overflow and nothing
+// (health in particular) ever drops below zero.
+pub const MIN_STAT: i32 = 0;
+pub const MAX_STAT: i32 = 9999;
+
+pub fn clamp_stat(value: i32) -> i32 {
+    max(MIN_STAT, min(MAX_STAT, value))
+}
 
 #[derive(Copy, RustcDecodable, RustcEncodable, Debug, PartialEq, Clone)]
 pub struct Stats {
-    pub health: u8,
-    pub movement: u8,
-    pub strength: u8,
-    pub dexterity: u8,
-    pub constitution: u8,
-    pub wisdom: u8,
-    pub intellect: u8,
-    pub charisma: u8,
+    pub health: i32,
+    pub movement: i32,
+    pub strength: i32,
+    pub dexterity: i32,
+    pub constitution: i32,
+    pub wisdom: i32,
+    pub intellect: i32,
+    pub charisma: i32,
 }
 
 impl Stats {
-    pub fn new(health: u8, movement: u8, strength: u8, dexterity: u8, constitution: u8, wisdom: u8,
-               intellect: u8, charisma: u8) -> Stats {
+    pub fn new(health: i32, movement: i32, strength: i32, dexterity: i32, constitution: i32, wisdom: i32,
+               intellect: i32, charisma: i32) -> Stats {
         Stats {
-            health: health,
-            movement: movement,
-            strength: strength,
-            dexterity: dexterity,
-            constitution: constitution,
-            wisdom: wisdom,
-            intellect: intellect,
-            charisma: charisma,
+            health: clamp_stat(health),
+            movement: clamp_stat(movement),
+            strength: clamp_stat(strength),
+            dexterity: clamp_stat(dexterity),
+            constitution: clamp_stat(constitution),
+            wisdom: clamp_stat(wisdom),
+            intellect: clamp_stat(intellect),
+            charisma: clamp_stat(charisma),
         }
     }
 
-    fn stat_func<F>(&mut self, stat: &str, f: F) where F: FnOnce(&mut u8) -> () {
+    fn stat_mut(&mut self, stat: &str) -> Option<&mut i32> {
         match &stat.to_ascii_lowercase()[..] {
-            "health" => f(&mut self.health),
-            "hp" => f(&mut self.health),
-            "move" => f(&mut self.movement),
-            "movement" => f(&mut self.movement),
-            "strength" => f(&mut self.strength),
-            "str" => f(&mut self.strength),
-            "dexterity" => f(&mut self.dexterity),
-            "dex" => f(&mut self.dexterity),
-            "constitution" => f(&mut self.constitution),
-            "con" => f(&mut self.constitution),
-            "wisdom" => f(&mut self.wisdom),
-            "wis" => f(&mut self.wisdom),
-            "intellect" => f(&mut self.intellect),
-            "int" => f(&mut self.intellect),
-            "charisma" => f(&mut self.charisma),
-            "cha" => f(&mut self.charisma),
-            _ => (),
-        }
-    }
-
-    // This should be updated if there's a way to use stat_func(...) without making it mutable.
-    pub fn get_stat(&self, stat: &str) -> Option<u8> {
+            "health" => Some(&mut self.health),
+            "hp" => Some(&mut self.health),
+            "move" => Some(&mut self.movement),
+            "movement" => Some(&mut self.movement),
+            "strength" => Some(&mut self.strength),
+            "str" => Some(&mut self.strength),
+            "dexterity" => Some(&mut self.dexterity),
+            "dex" => Some(&mut self.dexterity),
+            "constitution" => Some(&mut self.constitution),
+            "con" => Some(&mut self.constitution),
+            "wisdom" => Some(&mut self.wisdom),
+            "wis" => Some(&mut self.wisdom),
+            "intellect" => Some(&mut self.intellect),
+            "int" => Some(&mut self.intellect),
+            "charisma" => Some(&mut self.charisma),
+            "cha" => Some(&mut self.charisma),
+            _ => None,
+        }
+    }
+
+    // This should be updated if there's a way to use stat_mut(...) without making it mutable.
+    pub fn get_stat(&self, stat: &str) -> Option<i32> {
         match &stat.to_ascii_lowercase()[..] {
             "health" => Some(self.health),
             "hp" => Some(self.health),
@@ -72,20 +86,79 @@ impl Stats {
         }
     }
 
-    pub fn update_stat(&mut self, stat: &str, value: u8) {
-        self.stat_func(stat, |s: &mut u8| { *s = value; });
+    pub fn update_stat(&mut self, stat: &str, value: i32) -> BotResult<()> {
+        if value < MIN_STAT || value > MAX_STAT {
+            return Err(InvalidInput(format!("{} must be between {} and {}.", stat, MIN_STAT, MAX_STAT)));
+        }
+        match self.stat_mut(stat) {
+            Some(s) => { *s = value; Ok(()) },
+            None => Err(InvalidInput(format!("{} is not a valid stat.", stat))),
+        }
     }
 
-    pub fn increase_stat(&mut self, stat: &str, value: u8) {
-        self.stat_func(stat, |s: &mut u8| { *s += value; });
+    // Adds to a stat (or takes away from it, for negative values), returning the new value.
+    pub fn increase_stat(&mut self, stat: &str, value: i32) -> BotResult<i32> {
+        let new = match self.get_stat(stat) {
+            Some(current) => current.checked_add(value),
+            None => return Err(InvalidInput(format!("{} is not a valid stat.", stat))),
+        };
+        match new {
+            Some(n) => self.update_stat(stat, n).map(|_| n),
+            None => Err(InvalidInput(format!("{} must be between {} and {}.", stat, MIN_STAT, MAX_STAT))),
+        }
     }
 
-    pub fn calc_bonus(stat: u8) -> i8 {
-        let st = stat as i8;
-        (st - 10) / 2
+    fn for_mut(&mut self, roll_type: RollType) -> Option<&mut i32> {
+        match roll_type {
+            Basic => None,
+            Strength => Some(&mut self.strength),
+            Dexterity => Some(&mut self.dexterity),
+            Constitution => Some(&mut self.constitution),
+            Wisdom => Some(&mut self.wisdom),
+            Intellect => Some(&mut self.intellect),
+            Charisma => Some(&mut self.charisma),
+        }
     }
 
-    pub fn damage(&mut self, amount: u8) -> bool {
+    pub fn increase_for(&mut self, roll_type: RollType, value: i32) {
+        if let Some(s) = self.for_mut(roll_type) {
+            *s = clamp_stat(s.saturating_add(value));
+        }
+    }
+
+    pub fn decrease_for(&mut self, roll_type: RollType, value: i32) {
+        if let Some(s) = self.for_mut(roll_type) {
+            *s = clamp_stat(s.saturating_sub(value));
+        }
+    }
+
+    pub fn get_for(&self, roll_type: RollType) -> i32 {
+        match roll_type {
+            Basic => 0,
+            Strength => self.strength,
+            Dexterity => self.dexterity,
+            Constitution => self.constitution,
+            Wisdom => self.wisdom,
+            Intellect => self.intellect,
+            Charisma => self.charisma,
+        }
+    }
+
+    // The bonus for a stat under the standard rules. Other game systems define their own formula.
+    pub fn calc_bonus(stat: i32) -> i32 {
+        BonusFormula::Step { base: 10, step: 2 }.bonus(stat)
+    }
+
+    pub fn bonus_for(&self, roll_type: RollType) -> i32 {
+        match roll_type {
+            Basic => 0,
+            _ => Stats::calc_bonus(self.get_for(roll_type)),
+        }
+    }
+
+    // Negative amounts of damage are treated as none rather than healing.
+    pub fn damage(&mut self, amount: i32) -> bool {
+        let amount = max(0, amount);
         if amount >= self.health {
             self.health = 0;
             false
@@ -98,7 +171,27 @@ impl Stats {
 
 #[cfg(test)]
 mod test {
-    use super::Stats;
+    use super::{MAX_STAT, MIN_STAT, Stats};
+    use data::BotError::InvalidInput;
+    use data::RollType::{Basic, Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma};
+    use rand::thread_rng;
+    use rand::distributions::{IndependentSample, Range};
+
+    static STATS: [&'static str; 8] = ["health", "movement", "str", "dex", "con", "wis", "int", "cha"];
+
+    fn random_stats() -> Stats {
+        let r = Range::new(MIN_STAT, MAX_STAT + 1);
+        let mut rng = thread_rng();
+        Stats::new(r.ind_sample(&mut rng), r.ind_sample(&mut rng), r.ind_sample(&mut rng), r.ind_sample(&mut rng),
+                   r.ind_sample(&mut rng), r.ind_sample(&mut rng), r.ind_sample(&mut rng), r.ind_sample(&mut rng))
+    }
+
+    fn in_range(s: &Stats) -> bool {
+        STATS.iter().all(|stat| {
+            let value = s.get_stat(stat).unwrap();
+            value >= MIN_STAT && value <= MAX_STAT
+        })
+    }
 
     #[test]
     fn get_stat() {
@@ -111,30 +204,172 @@ mod test {
     #[test]
     fn update_stat() {
         let mut s = Stats::new(20, 30, 12, 12, 12, 12, 12, 12);
-        s.update_stat("str", 10);
+        s.update_stat("str", 10).unwrap();
         assert_eq!(s.get_stat("str"), Some(10));
-        s.update_stat("Con", 8);
+        s.update_stat("Con", 8).unwrap();
         assert_eq!(s.get_stat("constitution"), Some(8));
-        s.update_stat("InTeLlEcT", 14);
+        s.update_stat("InTeLlEcT", 14).unwrap();
         assert_eq!(s.get_stat("INTELLECT"), Some(14));
     }
 
     #[test]
     fn increase_stat() {
         let mut s = Stats::new(20, 30, 12, 12, 7, 12, 12, 12);
-        s.increase_stat("str", 2);
+        assert_eq!(s.increase_stat("str", 2), Ok(14));
         assert_eq!(s.get_stat("str"), Some(14));
-        s.increase_stat("Con", 1);
+        assert_eq!(s.increase_stat("Con", 1), Ok(8));
         assert_eq!(s.get_stat("constitution"), Some(8));
-        s.increase_stat("InTeLlEcT", 6);
+        assert_eq!(s.increase_stat("InTeLlEcT", 6), Ok(18));
         assert_eq!(s.get_stat("INTELLECT"), Some(18));
     }
 
+    #[test]
+    fn increase_for() {
+        let mut s = Stats::new(20, 30, 12, 12, 7, 12, 12, 12);
+        s.increase_for(Constitution, 2);
+        s.increase_for(Basic, 2);
+        assert_eq!(s, Stats::new(20, 30, 12, 12, 9, 12, 12, 12));
+        s.decrease_for(Constitution, 10);
+        assert_eq!(s.get_for(Constitution), 0);
+        assert_eq!(s.get_for(Basic), 0);
+        // Racial and feat increases can't push a stat past its range.
+        s.increase_for(Strength, 2000000000);
+        assert_eq!(s.get_for(Strength), MAX_STAT);
+    }
+
     #[test]
     fn calc_bonus() {
-        assert_eq!(Stats::calc_bonus(14), 2i8);
-        assert_eq!(Stats::calc_bonus(11), 0i8);
-        assert_eq!(Stats::calc_bonus(8), -1i8);
+        assert_eq!(Stats::calc_bonus(14), 2);
+        assert_eq!(Stats::calc_bonus(11), 0);
+        assert_eq!(Stats::calc_bonus(8), -1);
+        assert_eq!(Stats::calc_bonus(7), -2);
+    }
+
+    #[test]
+    fn bonus_for() {
+        let s = Stats::new(20, 30, 12, 16, 7, 12, 12, 12);
+        assert_eq!(s.bonus_for(Basic), 0);
+        assert_eq!(s.bonus_for(Dexterity), 3);
+        assert_eq!(s.bonus_for(Constitution), -2);
+    }
+
+    #[test]
+    fn update_stat_failed() {
+        let mut s = Stats::new(20, 30, 12, 12, 12, 12, 12, 12);
+        assert_eq!(s.update_stat("str", -1), Err(InvalidInput(format!("str must be between 0 and 9999."))));
+        assert_eq!(s.update_stat("str", 10000), Err(InvalidInput(format!("str must be between 0 and 9999."))));
+        assert_eq!(s.update_stat("test", 10), Err(InvalidInput(format!("test is not a valid stat."))));
+        assert_eq!(s, Stats::new(20, 30, 12, 12, 12, 12, 12, 12));
+    }
+
+    #[test]
+    fn increase_stat_failed() {
+        let mut s = Stats::new(20, 30, 12, 12, 12, 12, 12, 12);
+        assert!(s.increase_stat("str", 255).is_ok());
+        assert!(s.increase_stat("str", i32::max_value()).is_err());
+        assert!(s.increase_stat("dex", -13).is_err());
+        assert_eq!(s.get_stat("str"), Some(267));
+        assert_eq!(s.get_stat("dex"), Some(12));
+    }
+
+    #[test]
+    fn new_clamps() {
+        assert_eq!(Stats::new(-5, 30, 12, 12, 12, 12, 12, 20000), Stats::new(0, 30, 12, 12, 12, 12, 12, MAX_STAT));
+    }
+
+    // The properties below are checked against random stats and changes, including values far
+    // outside the range a stat can hold.
+
+    #[test]
+    fn prop_stats_stay_in_range() {
+        let change = Range::new(-100000, 100001);
+        let stat = Range::new(0, STATS.len());
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let mut s = random_stats();
+            for _ in 0..20 {
+                let (name, value) = (STATS[stat.ind_sample(&mut rng)], change.ind_sample(&mut rng));
+                let before = s;
+                match s.increase_stat(name, value) {
+                    Ok(n) => assert_eq!(s.get_stat(name), Some(n)),
+                    Err(_) => assert_eq!(s, before),
+                }
+                let _ = s.update_stat(name, value);
+                s.increase_for(Strength, value);
+                s.decrease_for(Wisdom, value);
+                s.damage(value);
+                assert!(in_range(&s));
+            }
+        }
+    }
+
+    #[test]
+    fn prop_extreme_changes_do_not_panic() {
+        let extremes = [i32::min_value(), -1, 0, 1, MAX_STAT, i32::max_value()];
+        for &value in extremes.iter() {
+            let mut s = Stats::new(value, value, value, value, value, value, value, value);
+            for stat in STATS.iter() {
+                let _ = s.increase_stat(stat, value);
+                let _ = s.update_stat(stat, value);
+            }
+            for roll_type in [Basic, Strength, Dexterity, Constitution, Wisdom, Intellect, Charisma].iter() {
+                s.increase_for(*roll_type, value);
+                s.decrease_for(*roll_type, value);
+                let _ = s.bonus_for(*roll_type);
+            }
+            s.damage(value);
+            assert!(in_range(&s));
+        }
+    }
+
+    #[test]
+    fn prop_update_then_get() {
+        let value = Range::new(MIN_STAT, MAX_STAT + 1);
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let mut s = random_stats();
+            for stat in STATS.iter() {
+                let v = value.ind_sample(&mut rng);
+                s.update_stat(stat, v).unwrap();
+                assert_eq!(s.get_stat(stat), Some(v));
+            }
+        }
+    }
+
+    #[test]
+    fn prop_increase_decrease_for() {
+        let value = Range::new(0, MAX_STAT + 1);
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let mut s = random_stats();
+            let before = s.get_for(Dexterity);
+            let v = value.ind_sample(&mut rng);
+            s.increase_for(Dexterity, v);
+            assert_eq!(s.get_for(Dexterity), if before + v > MAX_STAT { MAX_STAT } else { before + v });
+            s.decrease_for(Dexterity, v);
+            assert!(s.get_for(Dexterity) <= before);
+        }
+    }
+
+    #[test]
+    fn prop_bonus_is_monotonic() {
+        for value in MIN_STAT..MAX_STAT {
+            assert!(Stats::calc_bonus(value) <= Stats::calc_bonus(value + 1));
+        }
+        assert_eq!(Stats::calc_bonus(MIN_STAT), -5);
+    }
+
+    #[test]
+    fn prop_damage() {
+        let amount = Range::new(-100, 20000);
+        let mut rng = thread_rng();
+        for _ in 0..1000 {
+            let mut s = random_stats();
+            let (before, a) = (s.health, amount.ind_sample(&mut rng));
+            let conscious = s.damage(a);
+            assert_eq!(conscious, s.health > 0);
+            assert_eq!(s.health, if a < 0 { before } else if a >= before { 0 } else { before - a });
+        }
     }
 
     #[test]
@@ -2,16 +2,26 @@ use std::collections::HashMap;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::io::{Error, ErrorKind, Result};
 use data::{BotResult, Entity, as_io};
-use data::BotError::{Io, NotFound};
+use data::BotError::{InvalidInput, Io, NotFound};
+use data::builder::CharacterBuilder;
+use data::dice::{DiceSource, SharedDice};
 use data::game::Game;
+use data::history::{Change, ChannelState, History, Revision, Snapshot};
+use data::journal::SharedJournal;
 use data::monster::Monster;
 use data::player::Player;
+use data::rules::RuleSystem;
+use data::spell::Concentration;
+use data::utils::now;
 
 pub struct World {
     pub users: HashMap<String, Player>,
     pub user_channels: HashMap<String, String>,
     pub games: HashMap<String, Game>,
     pub monsters: HashMap<String, Vec<Monster>>,
+    pub builders: HashMap<String, CharacterBuilder>,
+    // Used for rolls made outside of any game.
+    pub dice: SharedDice,
 }
 
 impl World {
@@ -21,6 +31,8 @@ impl World {
             user_channels: HashMap::new(),
             games: HashMap::new(),
             monsters: HashMap::new(),
+            builders: HashMap::new(),
+            dice: DiceSource::Random.shared(),
         }
     }
 
@@ -28,6 +40,11 @@ impl World {
         self.users.contains_key(&nickname.to_string())
     }
 
+    // Whether the player is logged in to the channel's game, rather than another one.
+    pub fn user_in(&self, nickname: &str, chan: &str) -> bool {
+        self.is_user_logged_in(nickname) && self.user_channels.get(nickname).map_or(false, |c| c == chan)
+    }
+
     pub fn add_user(&mut self, nickname: &str, chan: &str, player: Player) {
         self.users.insert(nickname.to_string(), player);
         self.user_channels.insert(nickname.to_string(), chan.to_string());
@@ -49,15 +66,51 @@ impl World {
         }
     }
 
+    // The nicknames of every logged in player in the channel, in alphabetical order.
+    pub fn party(&self, chan: &str) -> Vec<String> {
+        let mut party: Vec<_> = self.user_channels.iter()
+            .filter(|&(nick, c)| c == chan && self.users.contains_key(nick))
+            .map(|(nick, _)| nick.clone()).collect();
+        party.sort();
+        party
+    }
+
     pub fn game_exists(&self, chan: &str) -> bool {
         self.games.contains_key(&chan.to_string())
     }
 
     pub fn add_game(&mut self, name: &str, dm_nick: &str, chan: &str) {
-        let game = Game::new(&name, &dm_nick);
+        self.insert_game(Game::new(&name, &dm_nick), chan);
+    }
+
+    pub fn insert_game(&mut self, game: Game, chan: &str) {
         self.games.insert(chan.to_string(), game);
     }
 
+    // The game a message is for: the channel's game, or in a query, the game the user is logged into.
+    fn game_for(&self, chan: &str, user: &str) -> Option<&Game> {
+        let chan = if chan.starts_with("#") { Some(chan) } else { self.user_channels.get(user).map(|c| &c[..]) };
+        chan.and_then(|c| self.games.get(c))
+    }
+
+    pub fn dice_for(&self, chan: &str, user: &str) -> SharedDice {
+        self.game_for(chan, user).map_or(self.dice.clone(), |game| game.dice.clone())
+    }
+
+    pub fn journal_for(&self, chan: &str, user: &str) -> Option<SharedJournal> {
+        self.game_for(chan, user).map(|game| game.journal.clone())
+    }
+
+    // Makes every roll in the world, in or out of games, come out as given.
+    #[cfg(test)]
+    pub fn script_dice(&mut self, rolls: Vec<i32>) {
+        let dice = DiceSource::scripted(rolls).shared();
+        for game in self.games.values_mut() {
+            game.dice = dice.clone();
+        }
+        self.dice = dice;
+    }
+
     pub fn get_game(&mut self, chan: &str) -> BotResult<&mut Game> {
         let ch = chan.to_string();
         if self.games.contains_key(&ch) {
@@ -67,6 +120,11 @@ impl World {
         }
     }
 
+    // The rules rolls in the channel use, which are the standard ones outside of a game.
+    pub fn rules(&self, chan: &str) -> RuleSystem {
+        self.games.get(chan).map_or_else(RuleSystem::standard, |game| game.rules.clone())
+    }
+
     pub fn add_monster(&mut self, monster: Monster, chan: &str) -> usize {
         let chan = chan.to_string();
         let result = match self.monsters.entry(chan) {
@@ -77,27 +135,32 @@ impl World {
         result.len() - 1
     }
 
+    pub fn get_monster(&mut self, identifier: &str, chan: &str) -> BotResult<&mut Monster> {
+        let i: usize = match identifier.trim_left_matches('@').parse() {
+            Ok(n) => n,
+            Err(_) => return Err(Io(Error::new(
+                ErrorKind::InvalidInput, "Non-integer identifier."
+            ))),
+        };
+        let chan_str = chan.to_string();
+        if self.monsters.contains_key(&chan_str) && i < self.monsters[&chan_str].len() {
+            Ok(self.monsters.get_mut(&chan_str).unwrap().get_mut(i).unwrap())
+        } else {
+            Err(NotFound("No such monster.".to_string()))
+        }
+    }
+
     pub fn get_entity(&mut self, identifier: &str, chan: Option<&str>) -> BotResult<&mut Entity> {
         if identifier.starts_with("@") {
-            let i: usize = match identifier[1..].parse() {
-                Ok(n) => n,
-                Err(_) => return Err(Io(Error::new(
-                    ErrorKind::InvalidInput, "Non-integer identifier."
-                ))),
-            };
             if chan.is_some() {
-                let chan_str = chan.unwrap().to_string();
-                if self.monsters.contains_key(&chan_str) && i < self.monsters[&chan_str].len() {
-                    Ok(self.monsters.get_mut(&chan_str).unwrap().get_mut(i).unwrap())
-                } else {
-                    Err(NotFound("No such monster.".to_string()))
-                }
+                Ok(try!(self.get_monster(identifier, chan.unwrap())))
             } else {
                 Err(Io(Error::new(ErrorKind::InvalidInput, "Monsters require a channel.")))
             }
         } else {
             let nick = identifier.to_string();
-            if self.users.contains_key(&nick) {
+            // Players in other games are out of reach of anything done in this one.
+            if self.users.contains_key(&nick) && chan.map_or(true, |c| self.user_in(&nick, c)) {
                 Ok(self.users.get_mut(&nick).unwrap())
             } else {
                 Err(NotFound("User not found.".to_string()))
@@ -105,10 +168,228 @@ impl World {
         }
     }
 
+    // Lifts the modifiers a spell put on its targets once its caster stops concentrating on it.
+    pub fn end_concentration(&mut self, effect: &Concentration, chan: &str) {
+        for target in effect.targets.iter() {
+            if let Ok(entity) = self.get_entity(target, Some(chan)) {
+                entity.modifiers_mut().retain(|m| !m.is_from(&effect.spell));
+            }
+        }
+    }
+
+    // The players logged into the channel's game and its monsters, as they are now.
+    pub fn channel_state(&self, chan: &str) -> ChannelState {
+        ChannelState {
+            players: self.user_channels.iter().filter(|&(nick, c)| c == chan && self.users.contains_key(nick))
+                .map(|(nick, _)| (nick.clone(), self.users[nick].clone())).collect(),
+            monsters: self.monsters.get(chan).cloned().unwrap_or_else(Vec::new),
+        }
+    }
+
+    // Remembers the channel as it is before a command that can be undone.
+    pub fn begin_change(&mut self, chan: &str, user: &str, command: &str) {
+        let state = self.channel_state(chan);
+        if let Some(game) = self.games.get_mut(chan) {
+            game.history.begin(user, command, state);
+        }
+    }
+
+    // Records what the last command changed in each game. Commands hold onto the world until
+    // they're done, so this happens before the next message is handled.
+    pub fn settle_changes(&mut self) {
+        let chans: Vec<_> = self.games.iter().filter(|&(_, game)| game.history.is_pending())
+            .map(|(chan, _)| chan.clone()).collect();
+        for chan in chans.iter() {
+            let state = self.channel_state(chan);
+            self.games.get_mut(chan).unwrap().history.settle(&state);
+        }
+    }
+
+    // Undoes the last action in the channel's game, returning it along with the entities it changed
+    // that couldn't be put back. Nothing is undone if what it changed has been changed again since.
+    pub fn undo(&mut self, chan: &str) -> BotResult<Option<(Revision, Vec<String>)>> {
+        self.step(chan, false)
+    }
+
+    pub fn redo(&mut self, chan: &str) -> BotResult<Option<(Revision, Vec<String>)>> {
+        self.step(chan, true)
+    }
+
+    fn step(&mut self, chan: &str, redo: bool) -> BotResult<Option<(Revision, Vec<String>)>> {
+        let revision = match self.games.get(chan).and_then(|game| game.history.peek(redo)) {
+            Some(revision) => revision.clone(),
+            None => return Ok(None),
+        };
+        if !self.is_unchanged_since(chan, &revision, redo) {
+            return Err(InvalidInput(format!(
+                "{} by {} can't be {}, since what it changed has been changed again.", revision.command,
+                revision.user, if redo { "redone" } else { "undone" }
+            )));
+        }
+        {
+            let history = &mut self.games.get_mut(chan).unwrap().history;
+            if redo { history.redo() } else { history.undo() };
+        }
+        let skipped = self.apply(chan, &revision, redo);
+        Ok(Some((revision, skipped)))
+    }
+
+    // Whether the player with the nickname is still the same one, logged in to the channel.
+    fn is_here(&self, nick: &str, chan: &str, player: &Player) -> bool {
+        self.user_in(nick, chan) && self.users.get(nick).map_or(false, |p| p.username == player.username)
+    }
+
+    // Whether everything an action changed is still as it left it, or as it was before it when
+    // redoing. Players who aren't here anymore don't count, since they'll be left alone.
+    fn is_unchanged_since(&self, chan: &str, revision: &Revision, redo: bool) -> bool {
+        let monsters = self.monsters.get(chan);
+        revision.changes.iter().all(|change| match *change {
+            Change::Player { ref nick, ref before, ref after } => {
+                let expected = if redo { before } else { after };
+                !self.is_here(nick, chan, expected) || &self.users[nick] == expected
+            },
+            Change::Monster { index, ref before, ref after } => {
+                let expected = if redo { before } else { after };
+                monsters.and_then(|m| m.get(index)) == expected.as_ref()
+            },
+        })
+    }
+
+    // Puts the entities an action changed back as they were before it, or after it when redoing,
+    // returning those that couldn't be: players who are no longer here, and monsters that can't be
+    // removed without moving the ones after them.
+    fn apply(&mut self, chan: &str, revision: &Revision, redo: bool) -> Vec<String> {
+        let mut skipped = Vec::new();
+        let mut changes: Vec<_> = revision.changes.iter().collect();
+        if !redo {
+            changes.reverse();
+        }
+        for change in changes.into_iter() {
+            match *change {
+                Change::Player { ref nick, ref before, ref after } => {
+                    let target = if redo { after } else { before };
+                    if self.is_here(nick, chan, target) {
+                        self.users.insert(nick.clone(), target.clone());
+                    } else {
+                        skipped.push(nick.clone());
+                    }
+                },
+                Change::Monster { index, ref before, ref after } => {
+                    let monsters = match self.monsters.entry(chan.to_string()) {
+                        Vacant(entry) => entry.insert(Vec::new()),
+                        Occupied(entry) => entry.into_mut(),
+                    };
+                    let applied = match *if redo { after } else { before } {
+                        Some(ref monster) if index < monsters.len() => {
+                            monsters[index] = monster.clone();
+                            true
+                        },
+                        Some(ref monster) if index == monsters.len() => {
+                            monsters.push(monster.clone());
+                            true
+                        },
+                        None if index + 1 == monsters.len() => {
+                            monsters.pop();
+                            true
+                        },
+                        None => index >= monsters.len(),
+                        _ => false,
+                    };
+                    if !applied {
+                        skipped.push(format!("@{}", index));
+                    }
+                },
+            }
+        }
+        skipped
+    }
+
+    // Saves the channel's game as it is now under the name, returning whether it replaced a snapshot.
+    pub fn take_snapshot(&mut self, chan: &str, name: &str, automatic: bool) -> BotResult<bool> {
+        let state = self.channel_state(chan);
+        let game = try!(self.get_game(chan));
+        let snapshot = Snapshot {
+            name: name.to_string(), automatic: automatic, time: now(), state: state, purse: game.purse,
+            ledger: game.ledger.clone()
+        };
+        as_io(game.add_snapshot(snapshot))
+    }
+
+    // Puts the channel's game back as it was in the snapshot, returning the nicknames of players in
+    // it who have since logged out and so couldn't be restored. What was done before can't be
+    // undone afterwards.
+    pub fn restore_snapshot(&mut self, chan: &str, name: &str) -> BotResult<Vec<String>> {
+        let snapshot = match try!(self.get_game(chan)).snapshot(name) {
+            Some(snapshot) => snapshot.clone(),
+            None => return Err(InvalidInput(format!("There is no snapshot named {}.", name))),
+        };
+        {
+            let game = try!(self.get_game(chan));
+            game.purse = snapshot.purse;
+            game.ledger = snapshot.ledger;
+            game.history = History::new();
+        }
+        self.monsters.insert(chan.to_string(), snapshot.state.monsters);
+        let mut missing = Vec::new();
+        for (nick, player) in snapshot.state.players.into_iter() {
+            if self.is_here(&nick, chan, &player) {
+                self.users.insert(nick, player);
+            } else {
+                missing.push(nick);
+            }
+        }
+        missing.sort();
+        Ok(missing)
+    }
+
+    // Ends the channel's game, saving its players, journal and archive, then logging out its players
+    // and clearing its monsters and any characters still being built for it. Everything is saved
+    // before anything is torn down, so if saving fails the game carries on as it was. Returns the
+    // game, the nickname and username of each player that was logged out and where the game was
+    // archived.
+    pub fn end_game(&mut self, chan: &str) -> BotResult<(Game, Vec<(String, String)>, String)> {
+        if !self.game_exists(chan) {
+            return Err(NotFound(format!("There is no game in {}.", chan)));
+        }
+        let players: Vec<_> = self.party(chan).into_iter()
+            .map(|nick| { let username = self.users[&nick].username.clone(); (nick, username) }).collect();
+        for &(ref nick, _) in players.iter() {
+            try!(as_io(self.users[nick].save()));
+        }
+        let path = {
+            let game = &self.games[chan];
+            try!(as_io(game.journal.borrow().save()));
+            try!(as_io(game.archive(players.iter().map(|&(_, ref username)| username.clone()).collect())))
+        };
+        for &(ref nick, _) in players.iter() {
+            self.users.remove(nick);
+            self.user_channels.remove(nick);
+        }
+        self.monsters.remove(chan);
+        let building: Vec<_> = self.builders.iter().filter(|&(_, b)| b.chan == chan).map(|(nick, _)| nick.clone())
+                                   .collect();
+        for nick in building.iter() {
+            self.builders.remove(nick);
+        }
+        Ok((self.games.remove(chan).unwrap(), players, path))
+    }
+
+    // Saves where each running session is up to in its seed's rolls. Games without a session have
+    // nothing to save, and the rest are only written when they've drawn dice since the last save.
+    pub fn save_draws(&mut self) -> Result<()> {
+        for game in self.games.values_mut().filter(|game| game.session.is_some()) {
+            try!(game.save_draws());
+        }
+        Ok(())
+    }
+
     pub fn save_all(&self) -> Result<()> {
         for user in self.users.values() {
             try!(user.save());
         }
+        for game in self.games.values() {
+            try!(game.save());
+        }
         Ok(())
     }
 }
@@ -120,6 +401,16 @@ mod test {
     use data::player::Player;
     use data::world::World;
 
+    #[test]
+    fn party() {
+        let mut w = World::new();
+        w.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.add_user("test", "#test", Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.add_user("test3", "#test2", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        assert_eq!(w.party("#test"), vec!["test".to_string(), "test2".to_string()]);
+        assert!(w.party("#test3").is_empty());
+    }
+
     #[test]
     fn world_user() {
         let mut w = World::new();
@@ -156,6 +447,17 @@ mod test {
         assert_eq!(w.add_monster(Monster::create("test2", 20, 30, 12, 12, 12, 12, 12, 12), "#test"), 1);
     }
 
+    #[test]
+    fn get_monster() {
+        let mut w = World::new();
+        let m = Monster::create("TestZombie", 20, 30, 12, 12, 12, 12, 12, 12);
+        w.add_monster(m.clone(), "#test");
+        assert_eq!(*w.get_monster("@0", "#test").unwrap(), m);
+        assert!(w.get_monster("@1", "#test").is_err());
+        assert!(w.get_monster("@0", "#test2").is_err());
+        assert!(w.get_monster("@a", "#test").is_err());
+    }
+
     #[test]
     fn get_entity() {
         let mut w = World::new();
@@ -166,6 +468,116 @@ mod test {
         assert_eq!(w.get_entity("test", None).unwrap().identifier(), p.identifier());
         assert_eq!(w.get_entity("@0", Some("#test")).unwrap().identifier(), m.identifier());
         assert_eq!(w.get_entity("test", Some("#test")).unwrap().identifier(), p.identifier());
+        assert!(w.get_entity("test", Some("#test2")).is_err());
+        assert!(w.user_in("test", "#test") && !w.user_in("test", "#test2") && !w.user_in("test2", "#test"));
+    }
+
+    #[test]
+    fn journal_for() {
+        let mut w = World::new();
+        w.add_game("Dungeons and Tests", "test", "#test");
+        w.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        assert!(w.journal_for("#test", "test").is_some());
+        assert!(w.journal_for("test2", "test2").is_some());
+        assert!(w.journal_for("test", "test").is_none());
+        assert!(w.journal_for("#other", "test2").is_none());
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut w = World::new();
+        w.add_game("Dungeons and Tests", "test", "#test");
+        w.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.begin_change("#test", "test", "addmonster #test Zombie");
+        w.add_monster(Monster::create("Zombie", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+        w.settle_changes();
+        w.begin_change("#test", "test", ".damage test2 5");
+        w.get_user("test2").unwrap().damage(5, None);
+        w.settle_changes();
+        assert_eq!(w.undo("#test").unwrap().unwrap().0.command, format!(".damage test2 5"));
+        assert_eq!(w.get_user("test2").unwrap().stats().health, 20);
+        assert_eq!(w.undo("#test").unwrap().unwrap().0.command, format!("addmonster #test Zombie"));
+        assert!(w.get_monster("@0", "#test").is_err());
+        assert!(w.undo("#test").unwrap().is_none());
+        w.redo("#test").unwrap().unwrap();
+        assert!(w.get_monster("@0", "#test").is_ok());
+        w.redo("#test").unwrap().unwrap();
+        assert_eq!(w.get_user("test2").unwrap().stats().health, 15);
+        assert!(w.redo("#test").unwrap().is_none());
+    }
+
+    #[test]
+    fn undo_refused_after_other_changes() {
+        let mut w = World::new();
+        w.add_game("Dungeons and Tests", "test", "#test");
+        w.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.begin_change("#test", "test", ".damage test2 5");
+        w.get_user("test2").unwrap().damage(5, None);
+        w.settle_changes();
+        // Nothing recorded this, so undoing the damage would quietly throw it away.
+        w.get_user("test2").unwrap().damage(3, None);
+        assert!(w.undo("#test").is_err());
+        assert_eq!(w.get_user("test2").unwrap().stats().health, 12);
+    }
+
+    #[test]
+    fn undo_partly() {
+        let mut w = World::new();
+        w.add_game("Dungeons and Tests", "test", "#test");
+        w.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.add_user("test3", "#test", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.begin_change("#test", "test", ".damage test2 5");
+        w.get_user("test2").unwrap().damage(5, None);
+        w.get_user("test3").unwrap().damage(5, None);
+        w.settle_changes();
+        w.remove_user("test3").unwrap();
+        // Someone else logging in under the nickname isn't the player who was changed.
+        w.add_user("test3", "#test", Player::create_test("test4", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        let (_, skipped) = w.undo("#test").unwrap().unwrap();
+        assert_eq!(skipped, vec![format!("test3")]);
+        assert_eq!(w.get_user("test2").unwrap().stats().health, 20);
+        assert_eq!(w.get_user("test3").unwrap().username, format!("test4"));
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut w = World::new();
+        w.add_game("Dungeons and Tests", "test", "#test");
+        w.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.add_user("test3", "#test", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.add_monster(Monster::create("Zombie", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+        assert!(!w.take_snapshot("#test", "boss", false).unwrap());
+        w.get_user("test2").unwrap().damage(5, None);
+        w.get_monster("@0", "#test").unwrap().damage(5, None);
+        w.add_monster(Monster::create("Ghoul", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+        w.remove_user("test3").unwrap();
+        // Someone else has taken the nickname, so they're left as they are.
+        w.add_user("test3", "#test", Player::create_test("test4", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        assert_eq!(w.restore_snapshot("#test", "Boss").unwrap(), vec![format!("test3")]);
+        assert_eq!(w.get_user("test3").unwrap().username, format!("test4"));
+        assert_eq!(w.get_user("test2").unwrap().stats().health, 20);
+        assert_eq!(w.get_monster("@0", "#test").unwrap().stats().health, 20);
+        assert!(w.get_monster("@1", "#test").is_err());
+        assert!(w.restore_snapshot("#test", "dragon").is_err());
+        assert!(w.take_snapshot("#other", "boss", false).is_err());
+    }
+
+    #[test]
+    fn end_game() {
+        let mut w = World::new();
+        w.add_game("Dungeons and Tests", "test", "#test");
+        w.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.add_user("test3", "#test2", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+        w.add_monster(Monster::create("Zombie", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+        let (game, players, path) = w.end_game("#test").unwrap();
+        assert_eq!(game.name, format!("Dungeons and Tests"));
+        assert!(path.starts_with("campaigns/dungeons-and-tests-"));
+        assert_eq!(players, vec![(format!("test2"), format!("test2"))]);
+        assert!(!w.game_exists("#test"));
+        assert!(!w.is_user_logged_in("test2") && !w.user_channels.contains_key("test2"));
+        assert!(w.is_user_logged_in("test3") && w.user_channels.contains_key("test3"));
+        assert!(w.get_monster("@0", "#test").is_err());
+        assert!(w.end_game("#test").is_err());
     }
 
     #[test]
@@ -0,0 +1,373 @@
+use data::{BotResult, as_io};
+use data::BotError::Propagated;
+use data::coins::{Denomination, Purse, Transaction};
+use data::world::World;
+use func::Functionality;
+use func::utils::{incorrect_format, permissions_test};
+use irc::client::prelude::*;
+
+pub struct Loot<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    coins: Purse,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Loot<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        }
+        Ok(Box::new(Loot {
+            bot: bot, user: user, chan: chan, world: world,
+            coins: match Purse::parse(&args[1..]) {
+                Some(coins) => coins,
+                None => return Err(incorrect_format(chan, ".loot", "amount denomination [amount denomination ...]")),
+            },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Loot<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let game = try!(self.world.get_game(self.chan));
+        game.purse.add_purse(&self.coins);
+        try!(as_io(game.record(Transaction::new(self.user, "party", self.coins, "loot"))));
+        let s = format!("The party found {}. The party purse now holds {}.", self.coins, game.purse);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Split<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Split<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() != 1 {
+            return Err(incorrect_format(chan, ".split", ""));
+        }
+        Ok(Box::new(Split { bot: bot, chan: chan, world: world }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Split<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let party = self.world.party(self.chan);
+        if party.is_empty() {
+            return Err(Propagated(format!("{}", self.chan), format!("There are no players in {}.", self.chan)));
+        }
+        let share = {
+            let game = try!(self.world.get_game(self.chan));
+            let share = game.purse.split(party.len() as u32);
+            for nick in party.iter() {
+                game.ledger.push(Transaction::new("party", nick, share, "split"));
+            }
+            try!(as_io(game.save()));
+            share
+        };
+        for nick in party.iter() {
+            try!(self.world.get_user(nick)).coins.add_purse(&share);
+        }
+        let remaining = try!(self.world.get_game(self.chan)).purse;
+        let s = format!("Each of {} received {}. The party purse holds {}.", party.join(", "), share, remaining);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Pay<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+    coins: Purse,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Pay<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() < 2 {
+            return Err(incorrect_format(chan, ".pay", "player amount denomination [amount denomination ...]"));
+        }
+        Ok(Box::new(Pay {
+            bot: bot, user: user, chan: chan, world: world, target_str: args[1],
+            coins: match Purse::parse(&args[2..]) {
+                Some(coins) => coins,
+                None => return Err(incorrect_format(chan, ".pay", "player amount denomination [amount denomination ...]")),
+            },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Pay<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        // Coins only change hands between players at the same table.
+        let party = self.world.party(self.chan);
+        if !self.world.game_exists(self.chan) {
+            return Err(Propagated(format!("{}", self.chan), format!("There is no game in {}.", self.chan)));
+        } else if !party.iter().any(|nick| nick == self.user) {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        } else if !party.iter().any(|nick| nick == self.target_str) {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+        } else if self.user == self.target_str {
+            return Err(Propagated(format!("{}", self.chan), format!("You can't pay yourself.")));
+        }
+        if let Err(e) = try!(self.world.get_user(self.user)).coins.remove_purse(&self.coins) {
+            return Err(Propagated(format!("{}", self.chan), format!("{}", e)));
+        }
+        try!(self.world.get_user(self.target_str)).coins.add_purse(&self.coins);
+        let transaction = Transaction::new(self.user, self.target_str, self.coins, "pay");
+        try!(as_io(try!(self.world.get_game(self.chan)).record(transaction)));
+        let s = format!("{} paid {} to {}.", self.user, self.coins, self.target_str);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Convert<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    amount: u32,
+    from: Denomination,
+    to: Denomination,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Convert<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let parsed = if args.len() == 4 {
+            (args[1].parse::<u32>().ok(), Denomination::to_denomination(args[2]), Denomination::to_denomination(args[3]))
+        } else {
+            (None, None, None)
+        };
+        match parsed {
+            (Some(amount), Some(from), Some(to)) => Ok(Box::new(Convert {
+                bot: bot, user: user, chan: chan, world: world, amount: amount, from: from, to: to,
+            })),
+            _ => Err(incorrect_format(chan, ".convert", "amount from to")),
+        }
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Convert<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        if !self.world.game_exists(self.chan) {
+            return Err(Propagated(format!("{}", self.chan), format!("There is no game in {}.", self.chan)));
+        }
+        let res = self.world.get_user(self.user);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        }
+        let result = match try!(res).coins.convert(self.amount, self.from, self.to) {
+            Ok(result) => result,
+            Err(e) => return Err(Propagated(format!("{}", self.chan), format!("{}", e))),
+        };
+        let mut spent = Purse::new();
+        spent.add(self.from, self.amount);
+        let note = format!("converted to {} {}", result, self.to);
+        try!(as_io(try!(self.world.get_game(self.chan)).record(Transaction::new(self.user, self.user, spent, &note))));
+        let s = format!("{} converted {} {} into {} {}.", self.user, self.amount, self.from, result, self.to);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct ShowPurse<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ShowPurse<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 2 {
+            return Err(incorrect_format(chan, ".purse", "[player|party]"));
+        }
+        Ok(Box::new(ShowPurse {
+            bot: bot, chan: chan, world: world,
+            target_str: if args.len() == 2 { args[1] } else { user },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ShowPurse<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let coins = if self.target_str == "party" {
+            let res = self.world.get_game(self.chan);
+            if res.is_err() {
+                return Err(Propagated(format!("{}", self.chan), format!("There is no game in {}.", self.chan)));
+            }
+            try!(res).purse
+        } else {
+            let res = self.world.get_user(self.target_str);
+            if res.is_err() {
+                return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+            }
+            try!(res).coins
+        };
+        let s = format!("{} has {}.", self.target_str, coins);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Ledger<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    count: usize,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Ledger<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let count = match args.get(1).map(|s| s.parse::<usize>()) {
+            None => 10,
+            Some(Ok(n)) if args.len() == 2 => n,
+            _ => return Err(incorrect_format(chan, ".ledger", "[count]")),
+        };
+        Ok(Box::new(Ledger { bot: bot, user: user, chan: chan, world: world, count: count }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Ledger<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_game(self.chan);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("There is no game in {}.", self.chan)));
+        }
+        let game = try!(res);
+        if game.ledger.is_empty() {
+            return as_io(self.bot.send_privmsg(self.user, "No coins have changed hands yet."));
+        }
+        let skip = game.ledger.len().saturating_sub(self.count);
+        for transaction in game.ledger[skip..].iter() {
+            try!(as_io(self.bot.send_privmsg(self.user, &format!("{}", transaction))));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use data::coins::Purse;
+    use data::game::Game;
+    use data::player::Player;
+    use func::test::test_helper;
+
+    #[test]
+    fn loot_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.loot 150 gp 30 sp\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :The party found 150 gp, 30 sp. The party purse now holds 150 gp, 30 sp.\r\n"));
+    }
+
+    #[test]
+    fn loot_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.loot 150 gp\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn split_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.split\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                try!(world.get_game("#test")).purse = Purse::parse(&["101", "gp"]).unwrap();
+                world.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                world.add_user("test3", "#test", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Each of test2, test3 received 50 gp. The party purse holds 1 gp.\r\n"));
+    }
+
+    #[test]
+    fn pay_success() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.pay test3 5 gp\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.coins.gp = 10;
+                world.add_user("test2", "#test", p);
+                world.add_user("test3", "#test", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 paid 5 gp to test3.\r\n"));
+    }
+
+    #[test]
+    fn pay_failed_not_enough() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.pay test3 5 gp\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                world.add_user("test3", "#test", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Not enough gp (only 0).\r\n"));
+    }
+
+    #[test]
+    fn pay_failed_outside_party() {
+        let mut input = ":test2!test@test PRIVMSG #test :.pay test3 5 gp\r\n".to_string();
+        input.push_str(":test2!test@test PRIVMSG #test :.pay test2 5 gp\r\n");
+        let data = test_helper(&input,
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.coins.gp = 10;
+                world.add_user("test2", "#test", p);
+                world.add_user("test3", "#test2", Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test3 is not logged in.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :You can't pay yourself.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn convert_success() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.convert 2 gp sp\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.coins.gp = 10;
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 converted 2 gp into 20 sp.\r\n"));
+    }
+
+    #[test]
+    fn ledger_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.loot 5 pp\r\n:test!test@test PRIVMSG #test :.ledger\r\n",
+            |world| {
+                world.add_game("Ledger Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG #test :The party found 5 pp. The party purse now holds 5 pp.");
+        assert!(lines[1].starts_with("PRIVMSG test :[") && lines[1].ends_with("] test -> party: 5 pp (loot)"));
+        let game = Game::load("Ledger Tests").unwrap();
+        assert_eq!(game.ledger.last().map(|t| t.note.clone()), Some(format!("loot")));
+    }
+}
@@ -1,103 +1,693 @@
+use std::ascii::AsciiExt;
 use data::{BotResult, Entity, RollType, as_io};
 use data::BotError::{InvalidInput, Propagated};
 use data::RollType::Basic;
+use data::damage::DamageType;
+use data::dice::{Dice, Visibility, set_roller};
+use data::journal::{EventKind, log_event};
+use data::modifier::{Expiry, Modifier, Operation};
+use data::rules::{RuleSystem, StatDef};
+use data::skill::{Proficiency, Skill};
 use data::stats::Stats;
-use data::utils::{Position, str_to_u8};
+use data::utils::{Position, str_to_stat};
 use data::world::World;
 use func::Functionality;
-use func::utils::{get_target, incorrect_format, permissions_test, validate_from};
+use func::utils::{find_target, get_target, incorrect_format, permissions_test, validate_from};
 use irc::client::prelude::*;
 
 pub struct Roll<'a, T: IrcRead, U: IrcWrite> {
     bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
     chan: &'a str,
     target: &'a (Entity + 'a),
     stat_str: Option<&'a str>,
-    stat: Option<RollType>,
+    skill: Option<Skill>,
+    options: String,
+    visibility: Visibility,
+    // The DMs of the game, who see rolls that aren't shown in the channel.
+    dms: Vec<String>,
+    rules: RuleSystem,
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Roll<'a, T, U> {
     pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
-        if args.len() > 3 { return Err(incorrect_format(chan, ".roll", "[@monster] [stat]")); }
-        let (stat_str, stat) = if args.len() == 3 && args[1].starts_with("@") {
-            (Some(args[2]), RollType::to_roll_type(args[2]))
+        Roll::create(bot, user, chan, args, world, ".roll", Visibility::Public)
+    }
+
+    // Rolls like .roll, but only the roller and the DMs see the result.
+    pub fn whispered(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        Roll::create(bot, user, chan, args, world, ".groll", Visibility::Whispered)
+    }
+
+    // Rolls for any player or monster in the game, with only the DMs seeing the result.
+    pub fn secret(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() < 2 || args.len() > 3 {
+            return Err(incorrect_format(user, ".sroll", "target [stat|skill]"));
+        }
+        try!(permissions_test(user, chan, world));
+        let dms = try!(world.get_game(chan)).dms.clone();
+        let options = match world.get_user(args[1]) {
+            Ok(ref p) if !args[1].starts_with("@") && p.rules.is_some() => p.rules().stat_list(),
+            _ => "str dex con wis int cha (or their full names)".to_string(),
+        };
+        let stat_str = args.get(2).cloned();
+        let rules = world.rules(chan);
+        Ok(Box::new(Roll {
+            bot: bot,
+            user: user,
+            chan: chan,
+            target: try!(find_target(args[1], user, chan, world)),
+            stat_str: stat_str,
+            skill: stat_str.and_then(|s| Skill::to_skill(s)),
+            options: options,
+            visibility: Visibility::Secret,
+            dms: dms,
+            rules: rules,
+        }))
+    }
+
+    fn create(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World,
+              cmd: &str, visibility: Visibility) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 3 { return Err(incorrect_format(chan, cmd, "[@monster] [stat|skill]")); }
+        let stat_str = if args.len() == 3 && args[1].starts_with("@") {
+            Some(args[2])
         } else if args.len() == 2 && !args[1].starts_with("@") {
-            (Some(args[1]), RollType::to_roll_type(args[1]))
+            Some(args[1])
+        } else {
+            None
+        };
+        let dms = if visibility == Visibility::Public {
+            Vec::new()
         } else {
-            (None, Some(Basic))
+            match world.get_game(chan) {
+                Ok(game) => game.dms.clone(),
+                Err(_) => return Err(Propagated(format!("{}", user), format!("There is no game in {}.", chan))),
+            }
         };
+        // Players list the stats of their own rule system when they ask for one that doesn't exist.
+        let options = match world.get_user(user) {
+            Ok(ref p) if !args.get(1).map_or(false, |a| a.starts_with("@")) && p.rules.is_some() => p.rules().stat_list(),
+            _ => "str dex con wis int cha (or their full names)".to_string(),
+        };
+        let rules = world.rules(chan);
         Ok(Box::new(Roll {
             bot: bot,
+            user: user,
             chan: chan,
             target: try!(get_target(if args.len() > 1 { args[1] } else { "" }, user, chan, chan, world)),
             stat_str: stat_str,
-            stat: stat,
+            skill: stat_str.and_then(|s| Skill::to_skill(s)),
+            options: options,
+            visibility: visibility,
+            dms: dms,
+            rules: rules,
         }))
     }
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for Roll<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
-        if self.stat.is_none() {
-            return Err(Propagated(
-                format!("{}", self.chan),
-                format!("{} is not a valid stat.\r\nOptions: str dex con wis int cha (or their full names).", self.stat_str.unwrap())
-            )); // We do not check if self.stat_str is none because it cannot be based on new(...).
+        set_roller(self.user, self.visibility);
+        // Mistakes in rolls that are kept out of the channel are kept out of it too.
+        let resp = if self.visibility == Visibility::Public { self.chan } else { self.user };
+        let (roll, success) = match roll_with_rules(&self.rules, self.target, self.stat_str, self.skill) {
+            Some(res) => res,
+            None => return Err(Propagated(
+                format!("{}", resp),
+                format!("{} is not a valid stat.\r\nOptions: {}.", self.stat_str.unwrap_or(""), self.options)
+            )),
+        };
+        let s = match success {
+            None => format!("{} rolled {}.", self.target.identifier(), roll),
+            Some(true) => format!("{} rolled {}, a success.", self.target.identifier(), roll),
+            Some(false) => format!("{} rolled {}, a failure.", self.target.identifier(), roll),
+        };
+        match self.visibility {
+            Visibility::Secret => {
+                let s = format!("Secret roll in {}: {}", self.chan, s);
+                for dm in self.dms.iter() {
+                    try!(as_io(self.bot.send_privmsg(dm, &s)));
+                }
+                Ok(())
+            },
+            Visibility::Whispered => {
+                let s = format!("Whispered roll in {}: {}", self.chan, s);
+                try!(as_io(self.bot.send_privmsg(self.user, &s)));
+                for dm in self.dms.iter().filter(|dm| *dm != self.user) {
+                    try!(as_io(self.bot.send_privmsg(dm, &s)));
+                }
+                Ok(())
+            },
+            _ => as_io(self.bot.send_privmsg(self.chan, &s)),
+        }
+    }
+}
+
+pub struct Check<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    target_str: &'a str,
+    target: &'a (Entity + 'a),
+    check_str: &'a str,
+    dc: i32,
+    rules: RuleSystem,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Check<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 3 && !(args.len() == 4 && args[1].starts_with("@")) {
+            return Err(incorrect_format(chan, ".check", "[@monster] stat|skill dc"));
+        }
+        let check_str = args[args.len() - 2];
+        try!(validate_check(check_str, chan));
+        let dc = match args[args.len() - 1].parse() {
+            Ok(n) => n,
+            Err(_) => return Err(Propagated(
+                format!("{}", chan), format!("{} is not a valid DC.", args[args.len() - 1])
+            )),
+        };
+        let rules = world.rules(chan);
+        Ok(Box::new(Check {
+            bot: bot,
+            chan: chan,
+            target_str: if args.len() == 4 { args[1] } else { user },
+            target: try!(get_target(args[1], user, chan, chan, world)),
+            check_str: check_str,
+            dc: dc,
+            rules: rules,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Check<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let (name, bonus, value) = check_bonus(self.target, self.check_str).unwrap(); // Validated in new(...).
+        let outcome = self.rules.test(self.target.identifier(), &format!("{} check", name), value, bonus, self.dc);
+        // Roll-under systems check against the stat rather than the DC.
+        let dc = if outcome.roll_under { String::new() } else { format!(" vs DC {}", self.dc) };
+        let s = format!("{} ({}) rolled {} on a {} check{}: {}.", self.target.identifier(), self.target_str,
+                        outcome, name, dc, if outcome.success { "success" } else { "failure" });
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Contest<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    attacker: (&'a str, &'a str),
+    defender: (&'a str, &'a str),
+    rules: RuleSystem,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Contest<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 5 {
+            return Err(incorrect_format(chan, ".contest", "attacker stat|skill defender stat|skill"));
         }
-        let s = format!("{} rolled {}.",
-                        self.target.identifier(), self.target.roll(self.stat.unwrap()));
+        try!(validate_check(args[2], chan));
+        try!(validate_check(args[4], chan));
+        // Only the DM rolls for monsters.
+        if args[1].starts_with("@") || args[3].starts_with("@") {
+            try!(permissions_test(user, chan, world));
+        }
+        let rules = world.rules(chan);
+        Ok(Box::new(Contest {
+            bot: bot,
+            chan: chan,
+            world: world,
+            attacker: (args[1], args[2]),
+            defender: (args[3], args[4]),
+            rules: rules,
+        }))
+    }
+
+    // Rolls one side of the contest, returning the announcement for it and the score it got.
+    fn roll_side(&mut self, (target_str, check_str): (&str, &str)) -> BotResult<(String, (bool, i32))> {
+        let target = try!(find_target(target_str, self.chan, self.chan, self.world));
+        let (name, bonus, value) = check_bonus(target, check_str).unwrap(); // Validated in new(...).
+        let outcome = self.rules.test(target.identifier(), &format!("{} contest", name), value, bonus, 0);
+        Ok((format!("{} ({}) rolled {} on {}", target.identifier(), target_str, outcome, name), outcome.score()))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Contest<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let (attacker, defender) = (self.attacker, self.defender);
+        let (a_str, a_score) = try!(self.roll_side(attacker));
+        let (d_str, d_score) = try!(self.roll_side(defender));
+        // On a tie, the situation stays as it was before the contest, so the defender holds.
+        let result = if a_score > d_score {
+            format!("{} wins.", attacker.0)
+        } else if a_score < d_score {
+            format!("{} wins.", defender.0)
+        } else {
+            format!("It's a tie, so {} holds.", defender.0)
+        };
+        let s = format!("{} and {}. {}", a_str, d_str, result);
         as_io(self.bot.send_privmsg(self.chan, &s))
     }
 }
 
 pub struct Damage<'a, T: IrcRead, U: IrcWrite> {
     bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
     chan: &'a str,
+    world: &'a mut World,
     target_str: &'a str,
-    target: &'a mut (Entity + 'a),
-    value: u8,
+    value: i32,
+    damage_type: Option<DamageType>,
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Damage<'a, T, U> {
     pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
-        if args.len() != 3 { return Err(incorrect_format(chan, ".damage", "target value")); }
+        if args.len() != 3 && args.len() != 4 { return Err(incorrect_format(chan, ".damage", "target value [type]")); }
+        let damage_type = if args.len() == 4 {
+            match DamageType::to_damage_type(args[3]) {
+                Some(t) => Some(t),
+                None => return Err(Propagated(
+                    format!("{}", chan), format!("{} is not a valid damage type.", args[3])
+                )),
+            }
+        } else {
+            None
+        };
+        try!(get_target(args[1], user, chan, chan, world));
         Ok(Box::new(Damage {
             bot: bot,
+            user: user,
             chan: chan,
+            world: world,
             target_str: args[1],
-            target: try!(get_target(args[1], user, chan, chan, world)),
-            value: if let Ok(n) = args[2].parse() {
-                n
-            } else {
-                return Err(Propagated(
+            value: match args[2].parse() {
+                Ok(n) if n >= 0 => n,
+                _ => return Err(Propagated(
                         format!("{}", chan),
                         format!("{} is not a valid positive integer.", args[2])
-                ));
+                )),
             },
+            damage_type: damage_type,
         }))
     }
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for Damage<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
-        let m = if self.target.damage(self.value) {
-            format!("{} ({}) took {} damage and has {} health remaining.", self.target.identifier(),
-                    self.target_str, self.value, self.target.stats().health)
-        } else {
-            format!("{} ({}) has fallen unconscious.", self.target.identifier(), self.target_str)
+        let (m, lost) = {
+            let target = try!(get_target(self.target_str, self.user, self.chan, self.chan, self.world));
+            let res = target.damage(self.value, self.damage_type);
+            log_event(EventKind::Damage, &res.describe(target.identifier(), self.damage_type,
+                                                       target.stats().health));
+            let kind = self.damage_type.map_or(String::new(), |t| format!(" {}", t));
+            let m = if res.conscious {
+                format!("{} ({}) took {}{} damage{} and has {} health remaining.", target.identifier(),
+                        self.target_str, res.dealt, kind, res.describe_adjustment(),
+                        target.stats().health)
+            } else if self.damage_type.is_some() {
+                format!("{} ({}) has fallen unconscious after taking {}{} damage{}.",
+                        target.identifier(), self.target_str, res.dealt, kind,
+                        res.describe_adjustment())
+            } else {
+                format!("{} ({}) has fallen unconscious.", target.identifier(), self.target_str)
+            };
+            (format!("{}{}", m, res.describe_concentration(target.identifier())), res.lost_concentration())
         };
+        if let Some(ref effect) = lost {
+            self.world.end_concentration(effect, self.chan);
+        }
         as_io(self.bot.send_privmsg(self.chan, &m))
     }
 }
 
+pub struct SavingThrow<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    stat: RollType,
+    dc: i32,
+    targets: Vec<&'a str>,
+    damage: Option<(Dice, Option<DamageType>)>,
+    rules: RuleSystem,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> SavingThrow<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "stat dc [target ...] [damage expr [type]]";
+        if args.len() < 3 { return Err(incorrect_format(chan, ".save", format)); }
+        let stat = match RollType::to_roll_type(args[1]) {
+            Some(stat) => stat,
+            None => return Err(Propagated(
+                format!("{}", chan),
+                format!("{} is not a valid stat.\r\nOptions: str dex con wis int cha (or their full names).", args[1])
+            )),
+        };
+        let dc = if let Ok(n) = args[2].parse() {
+            n
+        } else {
+            return Err(Propagated(format!("{}", chan), format!("{} is not a valid DC.", args[2])));
+        };
+        let (mut targets, damage) = match args.iter().position(|a| a.eq_ignore_ascii_case("damage")) {
+            Some(i) if i + 2 == args.len() || i + 3 == args.len() => {
+                let dice = match Dice::parse(args[i + 1]) {
+                    Some(dice) => dice,
+                    None => return Err(incorrect_format(chan, ".save", format)),
+                };
+                let damage_type = if i + 3 == args.len() {
+                    match DamageType::to_damage_type(args[i + 2]) {
+                        Some(t) => Some(t),
+                        None => return Err(Propagated(
+                            format!("{}", chan), format!("{} is not a valid damage type.", args[i + 2])
+                        )),
+                    }
+                } else {
+                    None
+                };
+                (args[3..i].to_vec(), Some((dice, damage_type)))
+            },
+            Some(_) => return Err(incorrect_format(chan, ".save", format)),
+            None => (args[3..].to_vec(), None),
+        };
+        if targets.is_empty() {
+            targets.push(user);
+        } else if targets.iter().any(|t| *t != user) {
+            try!(permissions_test(user, chan, world));
+        }
+        // Everyone is checked before anyone rolls, so a mistyped target doesn't leave the save half done.
+        for target in targets.iter() {
+            try!(find_target(target, chan, chan, world));
+        }
+        let rules = world.rules(chan);
+        Ok(Box::new(SavingThrow {
+            bot: bot,
+            chan: chan,
+            world: world,
+            stat: stat,
+            dc: dc,
+            targets: targets,
+            damage: damage,
+            rules: rules,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for SavingThrow<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let amount = self.damage.map_or(0, |(dice, _)| dice.roll());
+        let mut header = format!("{:?} save vs DC {}", self.stat, self.dc);
+        if let Some((_, damage_type)) = self.damage {
+            let kind = damage_type.map_or(String::new(), |t| format!(" {}", t));
+            header.push_str(&format!(" for {}{} damage (half on success)", amount, kind));
+        }
+        try!(as_io(self.bot.send_privmsg(self.chan, &format!("{}.", header))));
+        for target_str in self.targets.clone().into_iter() {
+            let mut lost = None;
+            let s = {
+                let target = try!(find_target(target_str, self.chan, self.chan, self.world));
+                let (bonus, value) = (target.save_bonus(self.stat), target.stats().get_for(self.stat));
+                let outcome = self.rules.test(target.identifier(), &format!("{:?} save", self.stat), value, bonus, self.dc);
+                let passed = outcome.success;
+                let mut s = format!("{} ({}) rolled {}: {}", target.identifier(), target_str, outcome,
+                                    if passed { "success" } else { "failure" });
+                let mut concentration = String::new();
+                if let Some((_, damage_type)) = self.damage {
+                    let res = target.damage(if passed { amount / 2 } else { amount }, damage_type);
+                    log_event(EventKind::Damage, &res.describe(target.identifier(), damage_type, target.stats().health));
+                    concentration = res.describe_concentration(target.identifier());
+                    lost = res.lost_concentration();
+                    let kind = damage_type.map_or(String::new(), |t| format!(" {}", t));
+                    if res.conscious {
+                        s.push_str(&format!(", took {}{} damage{} and has {} health remaining", res.dealt,
+                                            kind, res.describe_adjustment(), target.stats().health));
+                    } else {
+                        s.push_str(&format!(", took {}{} damage{} and has fallen unconscious", res.dealt,
+                                            kind, res.describe_adjustment()));
+                    }
+                }
+                s.push_str(".");
+                s.push_str(&concentration);
+                s
+            };
+            if let Some(ref effect) = lost {
+                self.world.end_concentration(effect, self.chan);
+            }
+            try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+        }
+        Ok(())
+    }
+}
+
+pub struct SetProficiency<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+    save: Option<RollType>,
+    skill: Option<Skill>,
+    proficiency: Option<Proficiency>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> SetProficiency<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "target save|skill name [expertise|none]";
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() != 4 && args.len() != 5 {
+            return Err(incorrect_format(chan, ".proficient", format));
+        }
+        let proficiency = if args.len() == 4 {
+            Some(Proficiency::Proficient)
+        } else if args[4].eq_ignore_ascii_case("expertise") {
+            Some(Proficiency::Expertise)
+        } else if args[4].eq_ignore_ascii_case("none") {
+            None
+        } else {
+            return Err(incorrect_format(chan, ".proficient", format));
+        };
+        let (save, skill) = match &args[2].to_ascii_lowercase()[..] {
+            "save" if proficiency != Some(Proficiency::Expertise) => match RollType::to_roll_type(args[3]) {
+                Some(stat) => (Some(stat), None),
+                None => return Err(Propagated(
+                    format!("{}", chan),
+                    format!("{} is not a valid stat.\r\nOptions: str dex con wis int cha (or their full names).", args[3])
+                )),
+            },
+            "skill" if !args[1].starts_with("@") => match Skill::to_skill(args[3]) {
+                Some(skill) => (None, Some(skill)),
+                None => return Err(Propagated(
+                    format!("{}", chan), format!("{} is not a valid skill.", args[3])
+                )),
+            },
+            "skill" => return Err(Propagated(
+                format!("{}", chan), format!("Monsters declare skill bonuses when they are added.")
+            )),
+            _ => return Err(incorrect_format(chan, ".proficient", format)),
+        };
+        Ok(Box::new(SetProficiency {
+            bot: bot,
+            chan: chan,
+            world: world,
+            target_str: args[1],
+            save: save,
+            skill: skill,
+            proficiency: proficiency,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for SetProficiency<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let (name, what) = if let Some(stat) = self.save {
+            let target = try!(find_target(self.target_str, self.chan, self.chan, self.world));
+            target.saving_throws_mut().retain(|s| *s != stat);
+            if self.proficiency.is_some() {
+                target.saving_throws_mut().push(stat);
+            }
+            (target.identifier().to_string(), format!("{:?} saves", stat))
+        } else {
+            let skill = self.skill.unwrap(); // new(...) always sets either a save or a skill.
+            let res = self.world.get_user(self.target_str);
+            if res.is_err() {
+                return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+            }
+            let player = try!(res);
+            player.set_skill_proficiency(skill, self.proficiency);
+            (player.identifier().to_string(), format!("{}", skill))
+        };
+        let s = match self.proficiency {
+            Some(Proficiency::Proficient) => format!("{} ({}) is now proficient in {}.", name, self.target_str, what),
+            Some(Proficiency::Expertise) => format!("{} ({}) now has expertise in {}.", name, self.target_str, what),
+            None => format!("{} ({}) is no longer proficient in {}.", name, self.target_str, what),
+        };
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct SetDefense<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    target_str: &'a str,
+    target: &'a mut (Entity + 'a),
+    kind: &'a str,
+    damage_type: DamageType,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> SetDefense<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() != 4 {
+            return Err(incorrect_format(chan, ".defense", "target resist|immune|vulnerable|none type"));
+        }
+        let kind = match &args[2].to_ascii_lowercase()[..] {
+            "resist" | "resistance" => "resistant",
+            "immune" | "immunity" => "immune",
+            "vulnerable" | "vulnerability" => "vulnerable",
+            "none" => "normal",
+            _ => return Err(incorrect_format(chan, ".defense", "target resist|immune|vulnerable|none type")),
+        };
+        Ok(Box::new(SetDefense {
+            bot: bot,
+            chan: chan,
+            target_str: args[1],
+            damage_type: match DamageType::to_damage_type(args[3]) {
+                Some(t) => t,
+                None => return Err(Propagated(
+                    format!("{}", chan), format!("{} is not a valid damage type.", args[3])
+                )),
+            },
+            target: try!(find_target(args[1], chan, chan, world)),
+            kind: kind,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for SetDefense<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        match self.kind {
+            "resistant" => self.target.defenses_mut().add_resistance(self.damage_type),
+            "immune" => self.target.defenses_mut().add_immunity(self.damage_type),
+            "vulnerable" => self.target.defenses_mut().add_vulnerability(self.damage_type),
+            _ => self.target.defenses_mut().clear(self.damage_type),
+        }
+        let s = if self.kind == "normal" {
+            format!("{} ({}) now takes normal {} damage.", self.target.identifier(), self.target_str,
+                    self.damage_type)
+        } else {
+            format!("{} ({}) is now {} to {} damage.", self.target.identifier(), self.target_str,
+                    self.kind, self.damage_type)
+        };
+        log_event(EventKind::Dm, &s);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct AddModifier<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    target_str: &'a str,
+    target: &'a mut (Entity + 'a),
+    modifier: Modifier,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> AddModifier<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "target name stat +n|-n|=n|xn [source] [until=short|long|never]";
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() < 5 || args.len() > 7 {
+            return Err(incorrect_format(chan, ".modifier", format));
+        }
+        let operation = match Operation::parse(args[4]) {
+            Some(operation) => operation,
+            None => return Err(Propagated(format!("{}", chan), format!("{} is not a valid change.", args[4]))),
+        };
+        let mut source = None;
+        let mut expiry = Expiry::Permanent;
+        for arg in args[5..].iter() {
+            if arg.to_ascii_lowercase().starts_with("until=") {
+                expiry = match Expiry::parse(&arg[6..]) {
+                    Some(expiry) => expiry,
+                    None => return Err(incorrect_format(chan, ".modifier", format)),
+                };
+            } else if source.is_none() {
+                source = Some(*arg);
+            } else {
+                return Err(incorrect_format(chan, ".modifier", format));
+            }
+        }
+        let modifier = match Modifier::new(args[2], source, args[3], operation, expiry) {
+            Some(modifier) => modifier,
+            None => return Err(Propagated(format!("{}", chan), format!("{} can't be modified.", args[3]))),
+        };
+        Ok(Box::new(AddModifier {
+            bot: bot,
+            chan: chan,
+            target_str: args[1],
+            target: try!(find_target(args[1], chan, chan, world)),
+            modifier: modifier,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddModifier<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        self.target.modifiers_mut().push(self.modifier.clone());
+        let stat = &self.modifier.stat[..];
+        let s = format!("{} ({}) gained {}. Effective {}: {}.", self.target.identifier(), self.target_str,
+                        self.modifier, stat, self.target.explain_stat(stat).unwrap());
+        log_event(EventKind::Dm, &s);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct RemoveModifier<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    target_str: &'a str,
+    target: &'a mut (Entity + 'a),
+    name: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> RemoveModifier<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() != 3 {
+            return Err(incorrect_format(chan, ".unmodify", "target name"));
+        }
+        Ok(Box::new(RemoveModifier {
+            bot: bot,
+            chan: chan,
+            target_str: args[1],
+            target: try!(find_target(args[1], chan, chan, world)),
+            name: args[2],
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for RemoveModifier<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let before = self.target.modifiers().len();
+        let name = self.name;
+        self.target.modifiers_mut().retain(|m| !m.is_named(name));
+        if self.target.modifiers().len() == before {
+            return Err(Propagated(format!("{}", self.chan), format!("{} ({}) has no modifier named {}.",
+                                  self.target.identifier(), self.target_str, self.name)));
+        }
+        let s = format!("{} ({}) is no longer affected by {}.", self.target.identifier(), self.target_str, self.name);
+        log_event(EventKind::Dm, &s);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
 pub struct SetTempStats<'a, T: IrcRead, U: IrcWrite> {
     bot: &'a ServerExt<'a, T, U>,
     chan: &'a str,
     target_str: &'a str,
     target: &'a mut (Entity + 'a),
-    health: u8, movement: u8,
-    st: u8, dx: u8, cn: u8,
-    ws: u8, it: u8, ch: u8,
+    health: i32, movement: i32,
+    st: i32, dx: i32, cn: i32,
+    ws: i32, it: i32, ch: i32,
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> SetTempStats<'a, T, U> {
@@ -115,9 +705,9 @@ impl<'a, T: IrcRead, U: IrcWrite> SetTempStats<'a, T, U> {
             chan: chan,
             target_str: args[1],
             target: try!(get_target(args[1], user, chan, chan, world)),
-            health: str_to_u8(args[2]), movement: str_to_u8(args[3]),
-            st: str_to_u8(args[4]), dx: str_to_u8(args[5]), cn: str_to_u8(args[6]),
-            ws: str_to_u8(args[7]), it: str_to_u8(args[8]), ch: str_to_u8(args[9]),
+            health: str_to_stat(args[2]), movement: str_to_stat(args[3]),
+            st: str_to_stat(args[4]), dx: str_to_stat(args[5]), cn: str_to_stat(args[6]),
+            ws: str_to_stat(args[7]), it: str_to_stat(args[8]), ch: str_to_stat(args[9]),
         }))
     }
 }
@@ -128,6 +718,7 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for SetTempStats<'a, T, U> {
                                               self.ws, self.it, self.ch));
         let s = format!("{} ({}) now has temporary {:?}.",
                         self.target.identifier(), self.target_str, self.target.stats());
+        log_event(EventKind::Dm, &s);
         as_io(self.bot.send_privmsg(self.chan, &s))
     }
 }
@@ -160,6 +751,7 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for ClearTempStats<'a, T, U> {
         self.target.clear_temp_stats();
         let s = format!("{} ({}) has reverted to {:?}.",
                         self.target.identifier(), self.target_str, self.target.stats());
+        log_event(EventKind::Dm, &s);
         as_io(self.bot.send_privmsg(self.chan, &s))
     }
 }
@@ -215,6 +807,8 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for Move<'a, T, U> {
         let s = if let Err(InvalidInput(msg)) = res {
             msg
         } else {
+            let Position(x, y) = self.position;
+            log_event(EventKind::Move, &format!("{} moved to ({}, {}).", self.target.identifier(), x, y));
             format!("{} ({}) moved to {:?}.",
                     self.target.identifier(), self.target_str, self.position)
         };
@@ -222,14 +816,65 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for Move<'a, T, U> {
     }
 }
 
+fn validate_check(check: &str, chan: &str) -> BotResult<()> {
+    if RollType::to_roll_type(check).is_none() && Skill::to_skill(check).is_none() {
+        return Err(Propagated(format!("{}", chan), format!("{} is not a valid stat or skill.", check)));
+    }
+    Ok(())
+}
+
+// Finds the name and modifier for a check made with either a stat or a skill, along with the value
+// of the stat it's made with for roll-under systems.
+fn check_bonus(target: &Entity, check: &str) -> Option<(String, i32, i32)> {
+    if let Some(stat) = RollType::to_roll_type(check) {
+        Some((format!("{:?}", stat), target.ability_bonus(stat), target.stats().get_for(stat)))
+    } else if let Some(skill) = Skill::to_skill(check) {
+        Some((format!("{}", skill), target.skill_bonus(skill), target.stats().get_for(skill.ability())))
+    } else {
+        None
+    }
+}
+
+// Rolls a stat or skill for the target with the game's rules, or just the die if neither is given.
+// Players roll stats that aren't built in themselves, since only they know their values.
+fn roll_with_rules(rules: &RuleSystem, target: &Entity, stat: Option<&str>, skill: Option<Skill>)
+                   -> Option<(i32, Option<bool>)> {
+    if rules.is_standard() {
+        return match (skill, stat) {
+            (Some(skill), _) => Some((target.roll_skill(skill), None)),
+            (None, Some(stat)) => target.roll_named(stat),
+            (None, None) => Some((target.roll(Basic), None)),
+        };
+    }
+    match (skill, stat) {
+        (Some(skill), _) if rules.roll_under => {
+            let value = target.stats().get_for(skill.ability());
+            Some(rules.roll_for(target.identifier(), &format!("{}", skill), Some(value)))
+        },
+        (Some(skill), _) => Some((target.roll_skill(skill), None)),
+        (None, Some(stat)) => match rules.stat(stat) {
+            Some(&StatDef { ref name, builtin: Some(builtin), .. }) => {
+                Some(rules.roll_for(target.identifier(), name, Some(target.stats().get_for(builtin))))
+            },
+            Some(_) => target.roll_named(stat),
+            None => None,
+        },
+        (None, None) => Some(rules.roll_for(target.identifier(), "", None)),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::borrow::ToOwned;
     use data::Entity;
+    use data::damage::DamageType::Fire;
+    use data::modifier::{Expiry, Modifier};
+    use data::modifier::Operation::Add;
     use data::monster::Monster;
     use data::player::Player;
+    use data::rules::RuleSystem;
     use data::stats::Stats;
-    use func::test::test_helper;
+    use func::test::{test_helper, test_helper_scripted};
 
     #[test]
     fn roll_success() {
@@ -258,8 +903,8 @@ mod test {
     }
 
     #[test]
-    fn roll_failed_invalid_stat() {
-        let data = test_helper(":test!test@test PRIVMSG #test :.roll @0 test\r\n",
+    fn roll_success_skill() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.roll @0 stealth\r\n",
             |world| {
                 world.add_game("Test", "test", "#test");
                 let m = Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12);
@@ -267,60 +912,344 @@ mod test {
                 Ok(())
             }
         ).unwrap();
-        let mut exp = "PRIVMSG #test :test is not a valid stat.\r\n".to_string();
-        exp.push_str("PRIVMSG #test :Options: str dex con wis int cha (or their full names).\r\n");
-        assert_eq!(data, exp);
+        assert_eq!(data[..27].to_owned(), format!("PRIVMSG #test :Test rolled "));
     }
 
     #[test]
-    fn roll_failed_monster_does_not_exist() {
-        let data = test_helper(":test!test@test PRIVMSG #test :.roll @0\r\n",
+    fn check_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.check @0 stealth 1\r\n",
             |world| {
                 world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
                 Ok(())
             }
         ).unwrap();
-        assert_eq!(data, format!("PRIVMSG #test :@0 is not a valid monster.\r\n"));
+        assert_eq!(data[..32].to_owned(), format!("PRIVMSG #test :Test (@0) rolled "));
+        assert!(data.ends_with("+0) on a Stealth check vs DC 1: success.\r\n"));
     }
 
     #[test]
-    fn roll_failed_user_is_not_logged_in() {
-        let data = test_helper(":test!test@test PRIVMSG #test :.roll\r\n",
+    fn check_transcript() {
+        let mut input = ":test!test@test PRIVMSG #test :.check @0 stealth 12\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.check @0 stealth 12\r\n");
+        let data = test_helper_scripted(&input, vec![12, 11],
             |world| {
                 world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
                 Ok(())
             }
         ).unwrap();
-        assert_eq!(data, format!("PRIVMSG #test :test is not logged in.\r\n"));
-    }
-
-    #[test]
-    fn roll_failed_invalid_format() {
-        let data = test_helper(":test!test@test PRIVMSG #test :.roll a b c\r\n", |_| { Ok(()) }).unwrap();
-        let mut exp = "PRIVMSG #test :Incorrect format for .roll. Format is:\r\n".to_string();
-        exp.push_str("PRIVMSG #test :.roll [@monster] [stat]\r\n");
+        let mut exp = "PRIVMSG #test :Test (@0) rolled 12 (12+0) on a Stealth check vs DC 12: success.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Test (@0) rolled 11 (11+0) on a Stealth check vs DC 12: failure.\r\n");
         assert_eq!(data, exp);
     }
 
     #[test]
-    fn damage_success() {
-        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 5\r\n",
+    fn check_roll_under() {
+        let mut input = ":test!test@test PRIVMSG #test :.check str 10\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.contest test str @0 dex\r\n");
+        let data = test_helper_scripted(&input, vec![70, 30, 25],
             |world| {
                 world.add_game("Test", "test", "#test");
-                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
-                world.add_monster(m, "#test");
+                try!(world.get_game("#test")).rules = RuleSystem::call_of_cthulhu();
+                let mut p = Player::create_test("test", "test", 20, 30, 50, 60, 55, 70, 45, 65);
+                p.rules = Some(RuleSystem::call_of_cthulhu());
+                world.add_user("test", "#test", p);
+                world.add_monster(Monster::create("Test", 14, 30, 40, 30, 40, 40, 40, 40), "#test");
                 Ok(())
             }
         ).unwrap();
-        assert_eq!(data, format!("PRIVMSG #test :Test (@0) took 5 damage and has 15 health remaining.\r\n"));
+        let mut exp = "PRIVMSG #test :test (test) rolled 70 (needing 50 or less) on a Strength check: failure.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test (test) rolled 30 (needing 50 or less) on Strength and Test (@0) rolled 25 (needing 30 or less) on Dexterity. test wins.\r\n");
+        assert_eq!(data, exp);
     }
 
     #[test]
-    fn damage_success_unconscious() {
-        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 20\r\n",
+    fn check_player_failure() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.check str 255\r\n",
             |world| {
-                world.add_game("Test", "test", "#test");
-                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data[..33].to_owned(), format!("PRIVMSG #test :test (test) rolled "));
+        assert!(data.ends_with("+1) on a Strength check vs DC 255: failure.\r\n"));
+    }
+
+    #[test]
+    fn check_failed_invalid_skill() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.check juggling 10\r\n",
+            |world| {
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :juggling is not a valid stat or skill.\r\n"));
+    }
+
+    #[test]
+    fn roll_failed_invalid_stat() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.roll @0 test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test is not a valid stat.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Options: str dex con wis int cha (or their full names).\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn roll_success_custom_rules() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.roll pow\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.rules = Some(RuleSystem::call_of_cthulhu());
+                try!(p.set_stat_value("POW", 50));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert!(data.starts_with("PRIVMSG #test :test rolled "));
+        assert!(data.ends_with(", a success.\r\n") || data.ends_with(", a failure.\r\n"));
+    }
+
+    #[test]
+    fn roll_failed_invalid_stat_custom_rules() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.roll wis\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.rules = Some(RuleSystem::call_of_cthulhu());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :wis is not a valid stat.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Options: str con siz dex app int pow edu.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn sroll_success_monster() {
+        let data = test_helper_scripted(":test!test@test PRIVMSG #test :.sroll @0 stealth\r\n", vec![12],
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Secret roll in #test: Test rolled 12.\r\n"));
+    }
+
+    #[test]
+    fn sroll_success_player() {
+        let mut input = ":test!test@test PRIVMSG #test :.sroll test2 wis\r\n".to_string();
+        input.push_str(":test2!test@test PRIVMSG #test :.rolls\r\n");
+        let data = test_helper_scripted(&input, vec![9],
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG test :Secret roll in #test: test2 rolled 10.");
+        assert!(lines[1].starts_with("PRIVMSG #test :["));
+        assert!(lines[1].ends_with("] test rolled d20 (Wisdom) for test2 (secret)"));
+    }
+
+    #[test]
+    fn sroll_failed_other_game() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.sroll test2 wis\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_game("Other Test", "test3", "#test2");
+                world.add_user("test2", "#test2", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :test2 is not playing in #test.\r\n"));
+    }
+
+    #[test]
+    fn sroll_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.sroll @0 stealth\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn sroll_failed_invalid_stat() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.sroll @0 test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG test :test is not a valid stat.\r\n".to_string();
+        exp.push_str("PRIVMSG test :Options: str dex con wis int cha (or their full names).\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn groll_success() {
+        let data = test_helper_scripted(":test2!test@test PRIVMSG #test :.groll str\r\n", vec![15],
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG test2 :Whispered roll in #test: test2 rolled 16.\r\n".to_string();
+        exp.push_str("PRIVMSG test :Whispered roll in #test: test2 rolled 16.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn groll_failed_no_game() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.groll str\r\n",
+            |world| {
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :There is no game in #test.\r\n"));
+    }
+
+    #[test]
+    fn roll_failed_monster_does_not_exist() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.roll @0\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :@0 is not a valid monster.\r\n"));
+    }
+
+    #[test]
+    fn roll_failed_user_is_not_logged_in() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.roll\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test is not logged in.\r\n"));
+    }
+
+    #[test]
+    fn roll_failed_invalid_format() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.roll a b c\r\n", |_| { Ok(()) }).unwrap();
+        let mut exp = "PRIVMSG #test :Incorrect format for .roll. Format is:\r\n".to_string();
+        exp.push_str("PRIVMSG #test :.roll [@monster] [stat|skill]\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn contest_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.contest test athletics @0 acrobatics\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                world.add_monster(Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data[..33].to_owned(), format!("PRIVMSG #test :test (test) rolled "));
+        assert!(data.contains("+1) on Athletics and Test (@0) rolled "));
+        assert!(data.ends_with("test wins.\r\n") || data.ends_with("@0 wins.\r\n") ||
+                data.ends_with("It's a tie, so @0 holds.\r\n"));
+    }
+
+    #[test]
+    fn contest_failed_invalid_skill() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.contest test juggling @0 str\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :juggling is not a valid stat or skill.\r\n"));
+    }
+
+    #[test]
+    fn contest_failed_monster_does_not_exist() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.contest @3 str @0 str\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :@3 is not a valid monster.\r\n"));
+    }
+
+    #[test]
+    fn contest_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.contest test2 athletics @0 athletics\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                world.add_monster(Monster::create("Test", 14, 30, 12, 10, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn damage_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 5\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) took 5 damage and has 15 health remaining.\r\n"));
+    }
+
+    #[test]
+    fn damage_success_above_255() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 300\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 500, 30, 12, 12, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) took 300 damage and has 200 health remaining.\r\n"));
+    }
+
+    #[test]
+    fn damage_success_unconscious() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 20\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
                 world.add_monster(m, "#test");
                 Ok(())
             }
@@ -363,6 +1292,340 @@ mod test {
         assert_eq!(data, format!("PRIVMSG #test :test is not logged in.\r\n"));
     }
 
+    #[test]
+    fn damage_success_typed() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 5 fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) took 5 fire damage and has 15 health remaining.\r\n"));
+    }
+
+    #[test]
+    fn damage_success_resisted() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 11 fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.defenses.add_resistance(Fire);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) took 5 fire damage (halved by resistance from 11) and has 15 health remaining.\r\n"));
+    }
+
+    #[test]
+    fn damage_success_vulnerable_unconscious() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 10 fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.defenses.add_vulnerability(Fire);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) has fallen unconscious after taking 20 fire damage (doubled by vulnerability from 10).\r\n"));
+    }
+
+    #[test]
+    fn damage_failed_invalid_type() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.damage @0 5 test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test is not a valid damage type.\r\n"));
+    }
+
+    #[test]
+    fn save_self_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.save dex 1\r\n",
+            |world| {
+                world.add_game("Test", "test2", "#test");
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG #test :Dexterity save vs DC 1.");
+        assert!(lines[1].starts_with("PRIVMSG #test :test (test) rolled "));
+        assert!(lines[1].ends_with("+1): success."));
+    }
+
+    #[test]
+    fn save_group_with_damage() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.save dex 30 @0 @1 damage 10 fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                let mut m = Monster::create("Test2", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.defenses.add_resistance(Fire);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "PRIVMSG #test :Dexterity save vs DC 30 for 10 fire damage (half on success).");
+        assert!(lines[1].starts_with("PRIVMSG #test :Test (@0) rolled "));
+        assert!(lines[1].ends_with(": failure, took 10 fire damage and has 10 health remaining."));
+        assert!(lines[2].starts_with("PRIVMSG #test :Test2 (@1) rolled "));
+        assert!(lines[2].ends_with(": failure, took 5 fire damage (halved by resistance from 10) and has 15 health remaining."));
+    }
+
+    #[test]
+    fn save_group_transcript() {
+        let data = test_helper_scripted(":test!test@test PRIVMSG #test :.save dex 12 @0 @1 damage 2d6 fire\r\n",
+                                        vec![4, 5, 11, 9],
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                let mut m = Monster::create("Test2", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.defenses.add_resistance(Fire);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :Dexterity save vs DC 12 for 9 fire damage (half on success).\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Test (@0) rolled 12 (11+1): success, took 4 fire damage and has 16 health remaining.\r\n");
+        exp.push_str("PRIVMSG #test :Test2 (@1) rolled 10 (9+1): failure, took 4 fire damage ");
+        exp.push_str("(halved by resistance from 9) and has 16 health remaining.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn save_success_half_damage() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.save con 1 @0 damage 9\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG #test :Constitution save vs DC 1 for 9 damage (half on success).");
+        assert!(lines[1].ends_with(": success, took 4 damage and has 16 health remaining."));
+    }
+
+    #[test]
+    fn save_failed_invalid_stat() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.save test 15\r\n", |_| { Ok(()) }).unwrap();
+        let mut exp = "PRIVMSG #test :test is not a valid stat.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Options: str dex con wis int cha (or their full names).\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn save_failed_others_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.save dex 15 test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn save_failed_invalid_target() {
+        let mut input = ":test!test@test PRIVMSG #test :.save dex 30 @0 @5 damage 10\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.damage @0 1\r\n");
+        let data = test_helper(&input,
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :@5 is not a valid monster.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Test (@0) took 1 damage and has 19 health remaining.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn proficient_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.proficient @0 save dex\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) is now proficient in Dexterity saves.\r\n"));
+    }
+
+    #[test]
+    fn proficient_remove_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.proficient test2 save wis none\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) is no longer proficient in Wisdom saves.\r\n"));
+    }
+
+    #[test]
+    fn proficient_skill_expertise_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.proficient test2 skill sleight_of_hand expertise\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) now has expertise in Sleight of Hand.\r\n"));
+    }
+
+    #[test]
+    fn proficient_skill_failed_monster() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.proficient @0 skill stealth\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Monsters declare skill bonuses when they are added.\r\n"));
+    }
+
+    #[test]
+    fn set_defense_monster_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.defense @0 resist fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) is now resistant to fire damage.\r\n"));
+    }
+
+    #[test]
+    fn set_defense_player_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.defense test2 none fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.defenses.add_immunity(Fire);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) now takes normal fire damage.\r\n"));
+    }
+
+    #[test]
+    fn set_defense_failed_user_is_not_logged_in() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.defense test2 immune fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 is not logged in.\r\n"));
+    }
+
+    #[test]
+    fn add_modifier_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.modifier test2 Bless str +2 spell until=long\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) gained Bless (spell): +2 strength until a long rest. Effective strength: 14 (base 12, +2 from Bless).\r\n"));
+    }
+
+    #[test]
+    fn add_modifier_monster_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.modifier @0 Haste move x2\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) gained Haste: movement x2. Effective movement: 60 (base 30, x2 from Haste).\r\n"));
+    }
+
+    #[test]
+    fn add_modifier_failed_invalid_stat() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.modifier @0 Aid health +5\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :health can't be modified.\r\n"));
+    }
+
+    #[test]
+    fn add_modifier_failed_invalid_change() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.modifier @0 Bless str 2\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :2 is not a valid change.\r\n"));
+    }
+
+    #[test]
+    fn remove_modifier_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.unmodify test2 bless\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.modifiers.push(Modifier::new("Bless", None, "str", Add(1), Expiry::Permanent).unwrap());
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) is no longer affected by bless.\r\n"));
+    }
+
+    #[test]
+    fn remove_modifier_failed_missing() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.unmodify test2 Bless\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) has no modifier named Bless.\r\n"));
+    }
+
+    #[test]
+    fn set_defense_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.defense @0 immune fire\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
     #[test]
     fn set_temp_stats_success() {
         let data = test_helper(":test!test@test PRIVMSG #test :.temp @0 20 30 12 12 12 12 12 12\r\n",
@@ -0,0 +1,422 @@
+use data::{BotResult, Entity, as_io};
+use data::BotError::Propagated;
+use data::item::{Item, ItemKind};
+use data::utils::join_from;
+use data::world::World;
+use func::Functionality;
+use func::utils::{incorrect_format, resolve_attack};
+use irc::client::prelude::*;
+
+pub struct AddItem<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    item: Item,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> AddItem<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "name quantity weight value [armor=ac[/maxdex]|shield=n|weapon=dice/type[/finesse][/reach]] [notes]";
+        if args.len() < 5 {
+            return Err(incorrect_format(chan, ".additem", format));
+        }
+        let (quantity, weight, value) = match (args[2].parse::<u16>(), args[3].parse::<f32>(), args[4].parse::<u32>()) {
+            (Ok(q), Ok(w), Ok(v)) if q > 0 && w >= 0.0 => (q, w, v),
+            _ => return Err(Propagated(
+                format!("{}", chan), format!("Quantity, weight and value must be positive numbers.")
+            )),
+        };
+        let kind = args.get(5).and_then(|s| ItemKind::parse(s));
+        let notes_from = if kind.is_some() { 6 } else { 5 };
+        let mut item = Item::new(args[1], quantity, weight, value, kind.unwrap_or(ItemKind::Gear));
+        if args.len() > notes_from {
+            item.notes = join_from(args, notes_from);
+        }
+        Ok(Box::new(AddItem { bot: bot, user: user, chan: chan, world: world, item: item }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddItem<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_user(self.user);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        }
+        let p = try!(res);
+        let weight = p.inventory.weight() + self.item.weight * self.item.quantity as f32;
+        if weight > p.carrying_capacity() {
+            return Err(Propagated(format!("{}", self.chan), format!(
+                "That would bring {} to {} lb., over their capacity of {} lb.", p.username, weight, p.carrying_capacity()
+            )));
+        }
+        p.inventory.add(self.item.clone());
+        let s = format!("{} ({}) now carries {} {} ({} / {} lb.).", p.username, self.user, self.item.quantity,
+                        self.item.name, p.inventory.weight(), p.carrying_capacity());
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct DropItem<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    name: &'a str,
+    quantity: u16,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> DropItem<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 2 && args.len() != 3 {
+            return Err(incorrect_format(chan, ".drop", "name [quantity]"));
+        }
+        Ok(Box::new(DropItem {
+            bot: bot, user: user, chan: chan, world: world, name: args[1],
+            quantity: try!(parse_quantity(args.get(2).map(|s| *s), chan)),
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for DropItem<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_user(self.user);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        }
+        let p = try!(res);
+        let chan = self.chan;
+        let item = try!(p.inventory.remove(self.name, self.quantity).map_err(|e| {
+            Propagated(format!("{}", chan), format!("{}", e))
+        }));
+        let s = format!("{} ({}) dropped {} {}.", p.username, self.user, item.quantity, item.name);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct GiveItem<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+    name: &'a str,
+    quantity: u16,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> GiveItem<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 3 && args.len() != 4 {
+            return Err(incorrect_format(chan, ".give", "player name [quantity]"));
+        }
+        Ok(Box::new(GiveItem {
+            bot: bot, user: user, chan: chan, world: world, target_str: args[1], name: args[2],
+            quantity: try!(parse_quantity(args.get(3).map(|s| *s), chan)),
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for GiveItem<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        if !self.world.is_user_logged_in(self.user) {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        } else if !self.world.is_user_logged_in(self.target_str) {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+        }
+        let weight = match try!(self.world.get_user(self.user)).inventory.get(self.name) {
+            Some(item) => item.weight * self.quantity as f32,
+            None => return Err(Propagated(
+                format!("{}", self.chan), format!("There is no {} in the inventory.", self.name)
+            )),
+        };
+        let receiver = {
+            let p = try!(self.world.get_user(self.target_str));
+            if p.inventory.weight() + weight > p.carrying_capacity() {
+                return Err(Propagated(format!("{}", self.chan), format!(
+                    "{} can't carry that much, their capacity is {} lb.", p.username, p.carrying_capacity()
+                )));
+            }
+            p.username.clone()
+        };
+        let (giver, item) = {
+            let chan = self.chan;
+            let p = try!(self.world.get_user(self.user));
+            let item = try!(p.inventory.remove(self.name, self.quantity).map_err(|e| {
+                Propagated(format!("{}", chan), format!("{}", e))
+            }));
+            (p.username.clone(), item)
+        };
+        let s = format!("{} ({}) gave {} {} to {} ({}).", giver, self.user, item.quantity, item.name,
+                        receiver, self.target_str);
+        try!(self.world.get_user(self.target_str)).inventory.add(item);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct ListInventory<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ListInventory<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 2 {
+            return Err(incorrect_format(chan, ".inventory", "[player]"));
+        }
+        Ok(Box::new(ListInventory {
+            bot: bot, chan: chan, world: world,
+            target_str: if args.len() == 2 { args[1] } else { user },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ListInventory<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_user(self.target_str);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+        }
+        let p = try!(res);
+        let items: Vec<_> = p.inventory.items.iter().map(|i| format!("{}", i)).collect();
+        let s = format!("{} ({}): {}. Carrying {} / {} lb.", p.username, self.target_str,
+                        if items.is_empty() { "nothing".to_string() } else { items.join(", ") },
+                        p.inventory.weight(), p.carrying_capacity());
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Equip<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    name: &'a str,
+    equip: bool,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Equip<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World, equip: bool) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 2 {
+            return Err(incorrect_format(chan, if equip { ".equip" } else { ".unequip" }, "name"));
+        }
+        Ok(Box::new(Equip { bot: bot, user: user, chan: chan, world: world, name: args[1], equip: equip }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Equip<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_user(self.user);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        }
+        let p = try!(res);
+        let res = if self.equip { p.inventory.equip(self.name) } else { p.inventory.unequip(self.name) };
+        if let Err(e) = res {
+            return Err(Propagated(format!("{}", self.chan), format!("{}", e)));
+        }
+        let s = format!("{} ({}) {} {} (AC {}).", p.username, self.user,
+                        if self.equip { "equipped" } else { "unequipped" }, self.name, p.armor_class());
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Attack<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    weapon: &'a str,
+    target_str: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Attack<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 3 {
+            return Err(incorrect_format(chan, ".attack", "weapon target"));
+        }
+        Ok(Box::new(Attack {
+            bot: bot, user: user, chan: chan, world: world, weapon: args[1], target_str: args[2],
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Attack<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let (name, position, action) = {
+            let res = self.world.get_user(self.user);
+            if res.is_err() {
+                return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+            }
+            let p = try!(res);
+            match p.weapon_attack(self.weapon) {
+                Some(action) => (p.username.clone(), p.position, action),
+                None => return Err(Propagated(
+                    format!("{}", self.chan), format!("{} has no {} equipped.", p.username, self.weapon)
+                )),
+            }
+        };
+        let s = try!(resolve_attack(self.world, self.chan, &name, self.user, position, &action,
+                                    self.target_str));
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+fn parse_quantity(arg: Option<&str>, chan: &str) -> BotResult<u16> {
+    match arg.map(|s| s.parse::<u16>()) {
+        None => Ok(1),
+        Some(Ok(n)) if n > 0 => Ok(n),
+        Some(_) => Err(Propagated(format!("{}", chan), format!("{} is not a valid quantity.", arg.unwrap()))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use data::damage::DamageType::Slashing;
+    use data::dice::Dice;
+    use data::item::{Item, ItemKind};
+    use data::monster::Monster;
+    use data::player::Player;
+    use func::test::{test_helper, test_helper_scripted};
+
+    fn longsword() -> Item {
+        Item::new("Longsword", 1, 3.0, 15, ItemKind::Weapon {
+            damage: Dice::new(1, 8, 0), damage_type: Slashing, finesse: false, reach: 5
+        })
+    }
+
+    #[test]
+    fn add_item_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.additem Torch 5 1 0 Smells of pitch\r\n",
+            |world| {
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test) now carries 5 Torch (5 / 180 lb.).\r\n"));
+    }
+
+    #[test]
+    fn add_item_failed_over_capacity() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.additem Anvil 1 200 10\r\n",
+            |world| {
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :That would bring test to 200 lb., over their capacity of 180 lb.\r\n"));
+    }
+
+    #[test]
+    fn drop_item_failed_not_enough() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.drop torch 6\r\n",
+            |world| {
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.inventory.add(Item::new("Torch", 5, 1.0, 0, ItemKind::Gear));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :There are only 5 Torch in the inventory.\r\n"));
+    }
+
+    #[test]
+    fn give_item_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.give test2 torch 2\r\n",
+            |world| {
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.inventory.add(Item::new("Torch", 5, 1.0, 0, ItemKind::Gear));
+                world.add_user("test", "#test", p);
+                let q = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", q);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test) gave 2 Torch to test2 (test2).\r\n"));
+    }
+
+    #[test]
+    fn list_inventory_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.inventory\r\n",
+            |world| {
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.inventory.add(Item::new("Torch", 5, 1.0, 0, ItemKind::Gear));
+                p.inventory.add(longsword());
+                p.inventory.equip("longsword").unwrap();
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let exp = "PRIVMSG #test :test (test): Torch x5 (1 lb., 0 gp), Longsword x1 (3 lb., 15 gp, equipped). Carrying 8 / 180 lb.\r\n";
+        assert_eq!(data, format!("{}", exp));
+    }
+
+    #[test]
+    fn equip_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.equip shield\r\n",
+            |world| {
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.inventory.add(Item::new("Shield", 1, 6.0, 10, ItemKind::Shield { bonus: 2 }));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test) equipped shield (AC 13).\r\n"));
+    }
+
+    #[test]
+    fn attack_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.attack longsword @0\r\n",
+            |world| {
+                world.add_game("Test", "test2", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.inventory.add(longsword());
+                p.inventory.equip("longsword").unwrap();
+                world.add_user("test", "#test", p);
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data[..61].to_owned(), format!("PRIVMSG #test :test (test) attacks Test (@0) with Longsword: "));
+    }
+
+    #[test]
+    fn attack_transcript() {
+        let mut input = ":test!test@test PRIVMSG #test :.attack longsword @0\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.attack longsword @0\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.attack longsword @0\r\n");
+        let data = test_helper_scripted(&input, vec![15, 6, 20, 3, 5, 1],
+            |world| {
+                world.add_game("Test", "test2", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.inventory.add(longsword());
+                p.inventory.equip("longsword").unwrap();
+                world.add_user("test", "#test", p);
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test (test) attacks Test (@0) with Longsword: 18 to hit vs AC 11, ".to_string();
+        exp.push_str("hit for 7 slashing damage (13 health remaining).\r\n");
+        exp.push_str("PRIVMSG #test :test (test) attacks Test (@0) with Longsword: 23 to hit vs AC 11, ");
+        exp.push_str("critical hit for 9 slashing damage (4 health remaining).\r\n");
+        exp.push_str("PRIVMSG #test :test (test) attacks Test (@0) with Longsword: 4 to hit vs AC 11, miss.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn attack_failed_not_equipped() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.attack longsword @0\r\n",
+            |world| {
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.inventory.add(longsword());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test has no longsword equipped.\r\n"));
+    }
+}
@@ -1,19 +1,47 @@
-use self::entity::{ClearTempStats, Damage, Move, Roll, SetTempStats};
-use self::monster::{AddMonster, LookUpMonster};
-use self::player::{AddFeat, AddUpdate, Login, Logout, LookUpPlayer, Register, Save};
-use self::world::{Create, PrivateRoll, SaveAll};
+use self::coins::{Convert, Ledger, Loot, Pay, ShowPurse, Split};
+use self::entity::{AddModifier, Check, ClearTempStats, Contest, Damage, Move, RemoveModifier, Roll,
+                   SavingThrow, SetDefense, SetProficiency, SetTempStats};
+use self::inventory::{AddItem, Attack, DropItem, Equip, GiveItem, ListInventory};
+use self::monster::{Act, AddAction, AddMonster, AddMultiattack, LookUpMonster};
+use self::player::{AddFeat, AddUpdate, AwardXp, Build, EditMacro, Login, Logout, LookUpFeat, LookUpPlayer,
+                   Register, RemoveFeat, Rest, ReviewFeat, Save, SetClass, UseMacro};
+use self::spell::{Cast, Concentrate, Learn, ListSpells, Prepare};
+use self::world::{Create, EndCampaign, Export, ListRolls, ManageDms, ManageSession, ManageSnapshot, PrivateRoll,
+                  SaveAll, SetRules, SetSeed, Undo};
 use std::borrow::ToOwned;
 use std::io::Result;
 use data::{BotResult, as_io};
+use data::dice::{Visibility, has_source, record_remaining, reset, set_roller, use_source};
+use data::journal::use_journal;
 use data::BotError::{InvalidInput, NotFound, Propagated};
 use data::world::World;
 use irc::client::prelude::{Server, ServerExt, IrcRead, IrcWrite};
 
+pub mod coins;
 pub mod entity;
+pub mod inventory;
 pub mod monster;
 pub mod player;
+pub mod spell;
 pub mod world;
 
+// Every channel command, in the order help lists them. Macros can't be named after any of them.
+pub const CHANNEL_COMMANDS: &'static [&'static str] = &[
+    "roll", "lookup", "update", "increase", "temp", "cleartemp", "damage", "move", "act", "defense", "save",
+    "proficient", "check", "contest", "class", "xp", "additem", "drop", "give", "inventory", "equip",
+    "unequip", "attack", "loot", "split", "pay", "convert", "purse", "ledger", "learn", "prepare",
+    "unprepare", "cast", "spells", "concentration", "rest", "approve", "deny", "modifier", "unmodify",
+    "rules", "seed", "session", "rolls", "sroll", "groll", "m", "export", "undo", "redo", "snapshot", "dm",
+    "endcampaign"
+];
+
+// Channel commands that never change a game in a way worth undoing, either because they only look
+// things up or because they go back in time themselves.
+const UNWATCHED_COMMANDS: &'static [&'static str] = &[
+    "roll", "lookup", "check", "contest", "inventory", "purse", "ledger", "spells", "rolls", "sroll", "groll",
+    "export", "help", "undo", "redo", "snapshot", "endcampaign"
+];
+
 pub trait Functionality {
     fn do_func(&mut self) -> BotResult<()>;
 }
@@ -39,37 +67,87 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for Help<'a, T, U> {
             // FIXME: There has to be some way of improving this.
             let format: &str = if cmd.starts_with(".") {
                 match &cmd[1..] {
-                    "roll" => "[@monster] [stat]",
+                    "roll" => "[@monster] [stat|skill]",
+                    "sroll" => "target [stat|skill]",
+                    "groll" => "[@monster] [stat|skill]",
+                    "m" => "name",
                     "lookup" => "target [stat]",
                     "update" => "stat value",
                     "increase" => "stat value",
                     "temp" => "target health str dex con wis int cha",
                     "cleartemp" => "target",
-                    "damage" => "target value",
+                    "damage" => "target value [type]",
+                    "defense" => "target resist|immune|vulnerable|none type",
                     "move" => "[@monster] x y",
+                    "act" => "@monster action target",
+                    "save" => "stat dc [target ...] [damage expr [type]]",
+                    "proficient" => "target save|skill name [expertise|none]",
+                    "check" => "[@monster] stat|skill dc",
+                    "contest" => "attacker stat|skill defender stat|skill",
+                    "class" => "target class",
+                    "xp" => "target|party amount [roll]",
+                    "additem" => "name quantity weight value [armor=ac[/maxdex]|shield=n|weapon=dice/type[/finesse][/reach]] [notes]",
+                    "drop" => "name [quantity]",
+                    "give" => "player name [quantity]",
+                    "inventory" => "[player]",
+                    "equip" => "name",
+                    "unequip" => "name",
+                    "attack" => "weapon target",
+                    "loot" => "amount denomination [amount denomination ...]",
+                    "split" => "",
+                    "pay" => "player amount denomination [amount denomination ...]",
+                    "convert" => "amount from to",
+                    "purse" => "[player|party]",
+                    "ledger" => "[count]",
+                    "learn" => "name level [attack=dice/type|save=stat/dice/type[/half]] [upcast=dice] [modify=stat/+n|-n|=n|xn] [concentration]",
+                    "prepare" => "spell",
+                    "unprepare" => "spell",
+                    "cast" => "spell [level] [target ...]",
+                    "spells" => "[player]",
+                    "concentration" => "[end]",
+                    "rest" => "short [max hit dice]|long",
+                    "approve" | "deny" => "nickname name of feat or spell",
+                    "modifier" => "target name stat +n|-n|=n|xn [source] [until=short|long|never]",
+                    "unmodify" => "target name",
+                    "rules" => "[system]",
+                    "seed" => "[seed|random]",
+                    "session" => "[start|end]",
+                    "rolls" => "[name] [count]",
+                    "export" => "[markdown|html]",
+                    "undo" | "redo" => "[count]",
+                    "snapshot" => "create name | restore name | list",
+                    "dm" => "add nick | remove nick | transfer nick | list",
+                    "endcampaign" => "",
                     _ => return Err(Propagated(format!("{}", self.resp), format!("{} is not a valid command.", self.cmd.unwrap())))
                 }
             } else {
                 match cmd {
-                    "register" => "username password health str dex con wis int cha",
+                    "register" => "username password health str dex con wis int cha [race [subrace]] [rules=system]",
+                    "build" => "channel, then follow the prompts (or cancel)",
                     "login" => "username password channel",
                     "create" => "channel campaign name",
                     "logout" => "",
                     "addfeat" => "name of feat",
+                    "removefeat" => "name of feat",
+                    "featinfo" => "name of feat",
                     "roll" => "",
                     "saveall" => "",
                     "save" => "",
                     "lookup" => "target [stat]",
                     "mlookup" => "channel target [stat]",
-                    "addmonster" => "chan name health str dex con wis int cha",
+                    "addmonster" => "chan name health str dex con wis int cha [skill=bonus ...]",
+                    "addaction" => "channel @monster name bonus reach damage type [recharge=n] [uses=n]",
+                    "addmulti" => "channel @monster name action [action ...]",
+                    "macro" => "set name roll [; roll ...] | remove name | list",
                     _ => return Err(Propagated(format!("{}", self.resp), format!("{} is not a valid command.", self.cmd.unwrap())))
                 }
             };
             as_io(self.bot.send_privmsg(self.resp, &format!("Format: {} {}", self.cmd.unwrap(), format)))
         } else {
             let mut s = "List of Commands:\r\n".to_string();
-            s.push_str("Channel commands: .roll .lookup .update .increase .temp .cleartemp .damage .move\r\n");
-            s.push_str("Query commands: register login create logout addfeat roll saveall save lookup mlookup addmonster\r\n");
+            let commands: Vec<_> = CHANNEL_COMMANDS.iter().map(|c| format!(".{}", c)).collect();
+            s.push_str(&format!("Channel commands: {}\r\n", commands.join(" ")));
+            s.push_str("Query commands: register login create logout addfeat roll saveall save lookup mlookup addmonster addaction addmulti build removefeat featinfo macro\r\n");
             s.push_str(&format!("If you need additional help, use {}help [command].", if self.resp.starts_with("#") { "." } else { "" }));
             as_io(self.bot.send_privmsg(self.resp, &s))
         }
@@ -105,6 +183,20 @@ fn tokenize<'a>(line: &'a str, vec: &'a mut Vec<String>) -> BotResult<Vec<&'a st
     }
 }
 
+// The channel whose game a command might change in a way that can be undone. Commands that can
+// change a game are watched, and only those that turn out to change something are recorded. Anything
+// else starting with a dot is a macro, which can run any command.
+fn undoable_in(chan: &str, user: &str, tokens: &[&str], world: &World) -> Option<String> {
+    match tokens[0] {
+        cmd if chan.starts_with("#") && cmd.starts_with(".") => {
+            if UNWATCHED_COMMANDS.contains(&&cmd[1..]) { None } else { Some(chan.to_owned()) }
+        },
+        "addmonster" | "addaction" | "addmulti" if tokens.len() > 1 => Some(tokens[1].to_owned()),
+        "addfeat" | "removefeat" => world.user_channels.get(user).cloned(),
+        _ => None,
+    }
+}
+
 pub fn process_world<'a, T: IrcRead, U: IrcWrite>(bot: &'a ServerExt<'a, T, U>, source: &'a str, 
     command: &str, args: &[&'a str], token_store: &'a mut Vec<String>, world: &'a mut World) 
     -> Result<()> {
@@ -118,19 +210,33 @@ pub fn process_world<'a, T: IrcRead, U: IrcWrite>(bot: &'a ServerExt<'a, T, U>,
                 Err(_) => return bot.send_privmsg(user, "Something went seriously wrong."),
                 Ok(tokens) => tokens,
             };
+            debug_assert!(!has_source(), "Dice were left set from the last message.");
+            use_source(Some(world.dice_for(chan, user)));
+            use_journal(world.journal_for(chan, user), user);
+            set_roller(user, if chan.starts_with("#") { Visibility::Public } else { Visibility::Private });
+            world.settle_changes();
+            if let Some(game_chan) = undoable_in(chan, user, &tokens, world) {
+                world.begin_change(&game_chan, user, msg);
+            }
             let func = if !chan.starts_with("#") {
                 match tokens[0] {
                     "register" => Register::new(bot, user, tokens),
+                    "build" => Build::new(bot, user, tokens, world),
                     "login" => Login::new(bot, user, tokens, world),
                     "create" => Create::new(bot, user, tokens, world),
                     "logout" => Logout::new(bot, user, world),
                     "addfeat" => AddFeat::new(bot, user, tokens, world),
+                    "removefeat" => RemoveFeat::new(bot, user, tokens, world),
+                    "featinfo" => LookUpFeat::new(bot, user, tokens),
                     "roll" => PrivateRoll::new(bot, user),
                     "saveall" => SaveAll::new(bot, user, world),
                     "save" => Save::new(bot, user, world),
                     "lookup" => LookUpPlayer::new(bot, user, tokens, world),
                     "mlookup" => LookUpMonster::new(bot, user, tokens, world),
                     "addmonster" => AddMonster::new(bot, user, tokens, world),
+                    "addaction" => AddAction::new(bot, user, tokens, world),
+                    "addmulti" => AddMultiattack::new(bot, user, tokens, world),
+                    "macro" => EditMacro::new(bot, user, tokens, world),
                     "help" => Help::new(bot, user, tokens),
                     _ => Err(Propagated(format!("{}", user), format!("{} is not a valid command.", tokens[0])))
                 }
@@ -138,15 +244,60 @@ pub fn process_world<'a, T: IrcRead, U: IrcWrite>(bot: &'a ServerExt<'a, T, U>,
                 if tokens[0].starts_with(".") {
                     match &tokens[0][1..] {
                         "roll" => Roll::new(bot, user, chan, tokens, world),
+                        "sroll" => Roll::secret(bot, user, chan, tokens, world),
+                        "groll" => Roll::whispered(bot, user, chan, tokens, world),
                         "lookup" => LookUpPlayer::new(bot, chan, tokens, world),
                         "update" => AddUpdate::new(bot, user, chan, tokens, world, true),
                         "increase" => AddUpdate::new(bot, user, chan, tokens, world, false),
                         "temp" => SetTempStats::new(bot, user, chan, tokens, world),
                         "cleartemp" => ClearTempStats::new(bot, user, chan, tokens, world),
                         "damage" => Damage::new(bot, user, chan, tokens, world),
+                        "defense" => SetDefense::new(bot, user, chan, tokens, world),
                         "move" => Move::new(bot, user, chan, tokens, world),
+                        "act" => Act::new(bot, user, chan, tokens, world),
+                        "save" => SavingThrow::new(bot, user, chan, tokens, world),
+                        "proficient" => SetProficiency::new(bot, user, chan, tokens, world),
+                        "check" => Check::new(bot, user, chan, tokens, world),
+                        "contest" => Contest::new(bot, user, chan, tokens, world),
+                        "class" => SetClass::new(bot, user, chan, tokens, world),
+                        "xp" => AwardXp::new(bot, user, chan, tokens, world),
+                        "additem" => AddItem::new(bot, user, chan, tokens, world),
+                        "drop" => DropItem::new(bot, user, chan, tokens, world),
+                        "give" => GiveItem::new(bot, user, chan, tokens, world),
+                        "inventory" => ListInventory::new(bot, user, chan, tokens, world),
+                        "equip" => Equip::new(bot, user, chan, tokens, world, true),
+                        "unequip" => Equip::new(bot, user, chan, tokens, world, false),
+                        "attack" => Attack::new(bot, user, chan, tokens, world),
+                        "loot" => Loot::new(bot, user, chan, tokens, world),
+                        "split" => Split::new(bot, user, chan, tokens, world),
+                        "pay" => Pay::new(bot, user, chan, tokens, world),
+                        "convert" => Convert::new(bot, user, chan, tokens, world),
+                        "purse" => ShowPurse::new(bot, user, chan, tokens, world),
+                        "ledger" => Ledger::new(bot, user, chan, tokens, world),
+                        "learn" => Learn::new(bot, user, chan, tokens, world),
+                        "prepare" => Prepare::new(bot, user, chan, tokens, world, true),
+                        "unprepare" => Prepare::new(bot, user, chan, tokens, world, false),
+                        "cast" => Cast::new(bot, user, chan, tokens, world),
+                        "spells" => ListSpells::new(bot, user, chan, tokens, world),
+                        "concentration" => Concentrate::new(bot, user, chan, tokens, world),
+                        "rest" => Rest::new(bot, user, chan, tokens, world),
+                        "approve" => ReviewFeat::new(bot, user, chan, tokens, world, true),
+                        "deny" => ReviewFeat::new(bot, user, chan, tokens, world, false),
+                        "modifier" => AddModifier::new(bot, user, chan, tokens, world),
+                        "unmodify" => RemoveModifier::new(bot, user, chan, tokens, world),
+                        "rules" => SetRules::new(bot, user, chan, tokens, world),
+                        "seed" => SetSeed::new(bot, user, chan, tokens, world),
+                        "session" => ManageSession::new(bot, user, chan, tokens, world),
+                        "rolls" => ListRolls::new(bot, chan, tokens, world),
+                        "export" => Export::new(bot, user, chan, tokens, world),
+                        "snapshot" => ManageSnapshot::new(bot, user, chan, tokens, world),
+                        "undo" => Undo::new(bot, user, chan, tokens, world, false),
+                        "redo" => Undo::new(bot, user, chan, tokens, world, true),
+                        "dm" => ManageDms::new(bot, user, chan, tokens, world),
+                        "endcampaign" => EndCampaign::new(bot, user, chan, tokens, world),
+                        "m" => UseMacro::new(bot, user, chan, tokens, world),
                         "help" => Help::new(bot, chan, tokens),
-                        _ => Err(NotFound(tokens[0].to_owned()))
+                        _ => UseMacro::fallback(bot, user, chan, tokens, world),
                     }
                 } else {
                     Err(NotFound(tokens[0].to_owned()))
@@ -157,6 +308,9 @@ pub fn process_world<'a, T: IrcRead, U: IrcWrite>(bot: &'a ServerExt<'a, T, U>,
             } else if let Err(Propagated(resp, msg)) = func.and_then(|mut f| f.do_func()) {
                 try!(bot.send_privmsg(&resp, &msg));
             }
+            record_remaining();
+            reset();
+            use_journal(None, "");
         },
         ("NOTICE", 2) => { // TODO: replace this with slice patterns when stabilized
             if args[1].starts_with("***") {
@@ -169,9 +323,13 @@ pub fn process_world<'a, T: IrcRead, U: IrcWrite>(bot: &'a ServerExt<'a, T, U>,
 }
 
 mod utils {
+    use std::cmp::max;
     use data::{BotError, BotResult, Entity};
     use data::BotError::Propagated;
-    use data::utils::str_to_u8;
+    use data::action::{Action, ActionKind};
+    use data::journal::{EventKind, log_event};
+    use data::stats::MAX_STAT;
+    use data::utils::Position;
     use data::world::World;
 
     pub fn get_target<'a>(maybe: &str, fallback: &str, resp: &str, chan: &str, world: &'a mut World) -> BotResult<&'a mut (Entity + 'a)> {
@@ -184,13 +342,83 @@ mod utils {
         if res.is_ok() { res } else { Err(Propagated(format!("{}", resp), err)) }
     }
 
+    pub fn find_target<'a>(target: &str, resp: &str, chan: &str, world: &'a mut World) -> BotResult<&'a mut (Entity + 'a)> {
+        let err = if target.starts_with("@") {
+            format!("{} is not a valid monster.", target)
+        } else if world.is_user_logged_in(target) {
+            format!("{} is not playing in {}.", target, chan)
+        } else {
+            format!("{} is not logged in.", target)
+        };
+        world.get_entity(target, Some(chan)).map_err(|_| Propagated(format!("{}", resp), err))
+    }
+
+    // Rolls an attack against the target with the game's rules and applies the damage, returning the
+    // announcement. Roll-under systems have no armor class, so attackers roll under the better of
+    // their Strength and Dexterity instead.
+    pub fn resolve_attack(world: &mut World, chan: &str, name: &str, attacker_str: &str, position: Position,
+                          action: &Action, target_str: &str) -> BotResult<String> {
+        let (bonus, reach, damage, damage_type) = match action.kind {
+            ActionKind::Attack { bonus, reach, damage, damage_type } => (bonus, reach, damage, damage_type),
+            _ => unreachable!(),
+        };
+        let rules = world.rules(chan);
+        let value = match world.get_entity(attacker_str, Some(chan)) {
+            Ok(attacker) => max(attacker.stats().strength, attacker.stats().dexterity),
+            Err(_) => 0,
+        };
+        let (s, lost) = {
+            let res = world.get_entity(target_str, Some(chan));
+            if res.is_err() {
+                return Err(Propagated(format!("{}", chan), format!("{} is not a valid target.", target_str)));
+            }
+            let target = try!(res);
+            let prefix = format!("{} ({}) attacks {} ({}) with {}:", name, attacker_str,
+                                 target.identifier(), target_str, action.name);
+            if position.distance(target.position()) > reach as i32 / 5 {
+                return Ok(format!("{} out of reach.", prefix));
+            }
+            let ac = target.armor_class();
+            let outcome = rules.test(name, &format!("{} attack", action.name), value, bonus, ac);
+            let roll = if outcome.roll_under {
+                format!("rolled {}", outcome)
+            } else {
+                format!("{} to hit vs AC {}", outcome.total, ac)
+            };
+            let critical = rules.is_critical(outcome.natural);
+            if rules.is_fumble(outcome.natural) || (!critical && !outcome.success) {
+                return Ok(format!("{} {}, miss.", prefix, roll));
+            }
+            let hit = if critical { "critical hit" } else { "hit" };
+            let res = target.damage(damage.roll_with_crit(critical), Some(damage_type));
+            log_event(EventKind::Damage, &res.describe(target.identifier(), Some(damage_type), target.stats().health));
+            let s = if res.conscious {
+                format!("{} {}, {} for {} {} damage{} ({} health remaining).", prefix, roll, hit, res.dealt,
+                        damage_type, res.describe_adjustment(), target.stats().health)
+            } else {
+                format!("{} {}, {} for {} {} damage{}. {} ({}) has fallen unconscious.", prefix, roll, hit,
+                        res.dealt, damage_type, res.describe_adjustment(), target.identifier(), target_str)
+            };
+            (format!("{}{}", s, res.describe_concentration(target.identifier())), res.lost_concentration())
+        };
+        if let Some(ref effect) = lost {
+            world.end_concentration(effect, chan);
+        }
+        Ok(s)
+    }
+
     pub fn validate_from(args: Vec<&str>, from: usize, resp: &str, cmd: &str, format: &str) -> BotResult<()> {
         for s in args[from..].iter() {
-            if str_to_u8(*s) == 0 {
-                return Err(Propagated(
+            match s.parse::<i32>() {
+                Ok(n) if n > MAX_STAT => return Err(Propagated(
+                    format!("{}", resp),
+                    format!("Stats can be at most {}. Format is:\r\n{} {}", MAX_STAT, cmd, format)
+                )),
+                Ok(n) if n > 0 => (),
+                _ => return Err(Propagated(
                     format!("{}", resp),
                     format!("Stats must be non-zero positive integers. Format is:\r\n{} {}", cmd, format)
-                ));
+                )),
             }
         }
         Ok(())
@@ -251,6 +479,17 @@ mod test {
         Ok(String::from_utf8(vec).unwrap())
     }
 
+    // Like test_helper(...), but every die rolled comes out as given, in order, so that tests can
+    // check exact transcripts.
+    pub fn test_helper_scripted<F>(input: &str, rolls: Vec<i32>, world_hook: F) -> BotResult<String>
+        where F: FnOnce(&mut World) -> BotResult<()> {
+        test_helper(input, |world| {
+            try!(world_hook(world));
+            world.script_dice(rolls);
+            Ok(())
+        })
+    }
+
     #[test]
     fn tokenize() {
         let mut store = Vec::new();
@@ -267,6 +506,21 @@ mod test {
         assert!(super::tokenize("a \"b \"c d", &mut store).is_err());
     }
 
+    #[test]
+    fn undoable_in() {
+        let mut w = World::new();
+        w.add_game("Dungeons and Tests", "test", "#test");
+        assert_eq!(super::undoable_in("#test", "test", &[".damage", "test2", "5"], &w), Some("#test".to_owned()));
+        assert_eq!(super::undoable_in("#test", "test", &[".attack"], &w), Some("#test".to_owned()));
+        assert_eq!(super::undoable_in("#test", "test", &[".fireball"], &w), Some("#test".to_owned()));
+        assert_eq!(super::undoable_in("#test", "test", &[".lookup", "test2"], &w), None);
+        assert_eq!(super::undoable_in("#test", "test", &[".rolls"], &w), None);
+        assert_eq!(super::undoable_in("#test", "test", &[".undo"], &w), None);
+        assert_eq!(super::undoable_in("#test", "test", &["hello"], &w), None);
+        assert_eq!(super::undoable_in("test", "test", &["addmonster", "#test", "Zombie"], &w), Some("#test".to_owned()));
+        assert_eq!(super::undoable_in("test", "test", &["lookup"], &w), None);
+    }
+
     #[test]
     fn permissions_test_no_game() {
         let res = super::utils::permissions_test("test", "#test", &mut World::new());
@@ -298,6 +552,8 @@ mod test {
         let mut world = World::new();
         world.add_game("Test", "test", "#test");
         assert!(super::utils::permissions_test("test", "#test", &mut world).is_ok());
+        world.get_game("#test").unwrap().add_dm("test2").unwrap();
+        assert!(super::utils::permissions_test("test2", "#test", &mut world).is_ok());
     }
 
     #[test]
@@ -332,7 +588,7 @@ mod test {
     #[test]
     fn specific_help_query_command() {
         let data = test_helper(":test!test@test PRIVMSG test :help register\r\n", |_| { Ok(()) }).unwrap();
-        assert_eq!(data, format!("PRIVMSG test :Format: register username password health str dex con wis int cha\r\n"));
+        assert_eq!(data, format!("PRIVMSG test :Format: register username password health str dex con wis int cha [race [subrace]] [rules=system]\r\n"));
     }
 
     #[test]
@@ -351,8 +607,8 @@ mod test {
     fn general_help_in_channel() {
         let data = test_helper(":test!test@test PRIVMSG #test :.help\r\n", |_| { Ok(()) }).unwrap();
         let mut exp = "PRIVMSG #test :List of Commands:\r\n".to_string();
-        exp.push_str("PRIVMSG #test :Channel commands: .roll .lookup .update .increase .temp .cleartemp .damage .move\r\n");
-        exp.push_str("PRIVMSG #test :Query commands: register login create logout addfeat roll saveall save lookup mlookup addmonster\r\n");
+        exp.push_str("PRIVMSG #test :Channel commands: .roll .lookup .update .increase .temp .cleartemp .damage .move .act .defense .save .proficient .check .contest .class .xp .additem .drop .give .inventory .equip .unequip .attack .loot .split .pay .convert .purse .ledger .learn .prepare .unprepare .cast .spells .concentration .rest .approve .deny .modifier .unmodify .rules .seed .session .rolls .sroll .groll .m .export .undo .redo .snapshot .dm .endcampaign\r\n");
+        exp.push_str("PRIVMSG #test :Query commands: register login create logout addfeat roll saveall save lookup mlookup addmonster addaction addmulti build removefeat featinfo macro\r\n");
         exp.push_str("PRIVMSG #test :If you need additional help, use .help [command].\r\n");
         assert_eq!(data, exp)
     }
@@ -361,8 +617,8 @@ mod test {
     fn general_help_in_query() {
         let data = test_helper(":test!test@test PRIVMSG test :help\r\n", |_| { Ok(()) }).unwrap();
         let mut exp = "PRIVMSG test :List of Commands:\r\n".to_string();
-        exp.push_str("PRIVMSG test :Channel commands: .roll .lookup .update .increase .temp .cleartemp .damage .move\r\n");
-        exp.push_str("PRIVMSG test :Query commands: register login create logout addfeat roll saveall save lookup mlookup addmonster\r\n");
+        exp.push_str("PRIVMSG test :Channel commands: .roll .lookup .update .increase .temp .cleartemp .damage .move .act .defense .save .proficient .check .contest .class .xp .additem .drop .give .inventory .equip .unequip .attack .loot .split .pay .convert .purse .ledger .learn .prepare .unprepare .cast .spells .concentration .rest .approve .deny .modifier .unmodify .rules .seed .session .rolls .sroll .groll .m .export .undo .redo .snapshot .dm .endcampaign\r\n");
+        exp.push_str("PRIVMSG test :Query commands: register login create logout addfeat roll saveall save lookup mlookup addmonster addaction addmulti build removefeat featinfo macro\r\n");
         exp.push_str("PRIVMSG test :If you need additional help, use help [command].\r\n");
         assert_eq!(data, exp)
     }
@@ -1,11 +1,16 @@
 use std::ascii::AsciiExt;
 use data::{BotResult, Entity, as_io};
 use data::BotError::Propagated;
+use data::action::Action;
+use data::damage::DamageType;
+use data::dice::Dice;
+use data::journal::EventKind;
 use data::monster::Monster;
-use data::utils::str_to_u8;
+use data::skill::Skill;
+use data::utils::str_to_stat;
 use data::world::World;
 use func::Functionality;
-use func::utils::{get_target, incorrect_format, permissions_test, validate_from};
+use func::utils::{get_target, incorrect_format, permissions_test, resolve_attack, validate_from};
 use irc::client::prelude::*;
 
 pub struct AddMonster<'a, T: IrcRead, U: IrcWrite> {
@@ -13,39 +18,56 @@ pub struct AddMonster<'a, T: IrcRead, U: IrcWrite> {
     user: &'a str,
     world: &'a mut World,
     chan: &'a str, name: &'a str,
-    health: u8, movement: u8,
-    st: u8, dx: u8, cn: u8,
-    ws: u8, it: u8, ch: u8,
+    health: i32, movement: i32,
+    st: i32, dx: i32, cn: i32,
+    ws: i32, it: i32, ch: i32,
+    skills: Vec<(Skill, i32)>,
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> AddMonster<'a, T, U> {
     pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
         if let Err(perm) = permissions_test(user, args[1], world) {
             return Err(perm);
-        } else if args.len() != 11 {
+        } else if args.len() < 11 {
             return Err(incorrect_format(user, "addmonster",
-                                        "chan name health movement str dex con wis int cha"));
+                                        "chan name health movement str dex con wis int cha [skill=bonus ...]"));
+        }
+        try!(validate_from(args[..11].to_vec(), 3, user, "addmonster",
+                           "chan name health movement str dex con wis int cha [skill=bonus ...]"));
+        let mut skills = Vec::new();
+        for opt in args[11..].iter() {
+            let split: Vec<_> = opt.splitn(2, '=').collect();
+            match (Skill::to_skill(split[0]), split.get(1).and_then(|b| b.parse().ok())) {
+                (Some(skill), Some(bonus)) => skills.push((skill, bonus)),
+                _ => return Err(Propagated(
+                    format!("{}", user), format!("{} is not a valid skill bonus.", opt)
+                )),
+            }
         }
-        try!(validate_from(args.clone(), 3, user, "addmonster",
-                           "chan name health movement str dex con wis int cha"));
         Ok(Box::new(AddMonster {
             bot: bot,
             user: user,
             world: world,
             chan: args[1], name: args[2],
-            health: str_to_u8(args[3]), movement: str_to_u8(args[4]),
-            st: str_to_u8(args[5]), dx: str_to_u8(args[6]), cn: str_to_u8(args[7]),
-            ws: str_to_u8(args[8]), it: str_to_u8(args[9]), ch: str_to_u8(args[10]),
+            health: str_to_stat(args[3]), movement: str_to_stat(args[4]),
+            st: str_to_stat(args[5]), dx: str_to_stat(args[6]), cn: str_to_stat(args[7]),
+            ws: str_to_stat(args[8]), it: str_to_stat(args[9]), ch: str_to_stat(args[10]),
+            skills: skills,
         }))
     }
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddMonster<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
-        let m = Monster::create(self.name, self.health, self.movement, self.st, self.dx, self.cn,
-                                self.ws, self.it, self.ch);
+        let mut m = Monster::create(self.name, self.health, self.movement, self.st, self.dx,
+                                    self.cn, self.ws, self.it, self.ch);
+        m.skills = self.skills.clone();
         let s = format!("Monster ({}) has been created as @{}.",
                         self.name, self.world.add_monster(m, self.chan));
+        // This is sent privately, so it goes in the journal of the game it was added to.
+        if let Some(game) = self.world.games.get(self.chan) {
+            game.journal.borrow_mut().log(EventKind::Dm, self.user, &s);
+        }
         as_io(self.bot.send_privmsg(self.user, &s))
     }
 }
@@ -85,6 +107,16 @@ impl<'a, T: IrcRead, U: IrcWrite> LookUpMonster<'a, T, U> {
 
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for LookUpMonster<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
+        if self.stat_str.map_or(false, |s| s.eq_ignore_ascii_case("actions")) {
+            let res = self.world.get_monster(self.target_str, self.chan);
+            if res.is_err() {
+                return Err(Propagated(format!("{}", self.user), format!("{} is not a valid monster.", self.target_str)));
+            }
+            let monster = try!(res);
+            let actions: Vec<_> = monster.actions.iter().map(|a| format!("{}", a)).collect();
+            let s = format!("{} ({}): {}", monster.name, self.target_str, actions.join(", "));
+            return as_io(self.bot.send_privmsg(self.user, &s));
+        }
         let target = try!(get_target(self.target_str, self.user, self.user, self.chan, self.world));
         let temp = if target.has_temp_stats() {
             "Temp. "
@@ -97,21 +129,207 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for LookUpMonster<'a, T, U> {
         } else if self.stat_str.unwrap().eq_ignore_ascii_case("pos") || self.stat_str.unwrap().eq_ignore_ascii_case("position") {
             let s = format!("{} ({}): {:?}", target.identifier(), self.target_str, target.position());
             as_io(self.bot.send_privmsg(self.user, &s))
+        } else if self.stat_str.unwrap().eq_ignore_ascii_case("defenses") {
+            let s = format!("{} ({}): {}", target.identifier(), self.target_str, target.defenses());
+            as_io(self.bot.send_privmsg(self.user, &s))
+        } else if self.stat_str.unwrap().eq_ignore_ascii_case("modifiers") {
+            let modifiers: Vec<_> = target.modifiers().iter().map(|m| format!("{}", m)).collect();
+            let list = if modifiers.is_empty() { "none".to_string() } else { modifiers.join("; ") };
+            let s = format!("{} ({}): {}", target.identifier(), self.target_str, list);
+            as_io(self.bot.send_privmsg(self.user, &s))
         } else if let Some(x) = target.stats().get_stat(self.stat_str.unwrap()) {
+            let x = target.explain_stat(self.stat_str.unwrap()).unwrap_or(format!("{}", x));
             let s = format!("{} ({}): {}{} {}", target.identifier(), self.target_str, temp, x, self.stat_str.unwrap());
             as_io(self.bot.send_privmsg(self.user, &s))
+        } else if let Some(skill) = Skill::to_skill(self.stat_str.unwrap()) {
+            let s = format!("{} ({}): {:+} {}", target.identifier(), self.target_str, target.skill_bonus(skill), self.stat_str.unwrap());
+            as_io(self.bot.send_privmsg(self.user, &s))
         } else {
             Err(Propagated(format!("{}", self.user), format!("{} is not a valid stat.", self.stat_str.unwrap())))
         }
     }
 }
 
+pub struct AddAction<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    world: &'a mut World,
+    chan: &'a str,
+    target_str: &'a str,
+    action: Action,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> AddAction<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "channel @monster name bonus reach damage type [recharge=n] [uses=n]";
+        if args.len() < 8 || args.len() > 10 {
+            return Err(incorrect_format(user, "addaction", format));
+        } else if let Err(perm) = permissions_test(user, args[1], world) {
+            return Err(perm);
+        }
+        let (bonus, reach, damage) = match (args[4].trim_left_matches('+').parse(), args[5].parse(), Dice::parse(args[6])) {
+            (Ok(b), Ok(r), Some(d)) => (b, r, d),
+            _ => return Err(incorrect_format(user, "addaction", format)),
+        };
+        let damage_type = match DamageType::to_damage_type(args[7]) {
+            Some(t) => t,
+            None => return Err(Propagated(format!("{}", user), format!("{} is not a valid damage type.", args[7]))),
+        };
+        let mut action = Action::attack(args[3], bonus, reach, damage, damage_type);
+        for opt in args[8..].iter() {
+            let value = opt.find('=').and_then(|i| opt[i + 1..].parse().ok());
+            if opt.starts_with("recharge=") && value.is_some() {
+                action.recharge = value;
+            } else if opt.starts_with("uses=") && value.is_some() {
+                action.uses = value;
+            } else {
+                return Err(incorrect_format(user, "addaction", format));
+            }
+        }
+        Ok(Box::new(AddAction {
+            bot: bot,
+            user: user,
+            world: world,
+            chan: args[1],
+            target_str: args[2],
+            action: action,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddAction<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_monster(self.target_str, self.chan);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.user), format!("{} is not a valid monster.", self.target_str)));
+        }
+        let monster = try!(res);
+        monster.add_action(self.action.clone());
+        let s = format!("{} ({}) can now use {}.", monster.name, self.target_str, self.action);
+        as_io(self.bot.send_privmsg(self.user, &s))
+    }
+}
+
+pub struct AddMultiattack<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    world: &'a mut World,
+    chan: &'a str,
+    target_str: &'a str,
+    name: &'a str,
+    actions: Vec<String>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> AddMultiattack<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() < 5 {
+            return Err(incorrect_format(user, "addmulti", "channel @monster name action [action ...]"));
+        } else if let Err(perm) = permissions_test(user, args[1], world) {
+            return Err(perm);
+        }
+        Ok(Box::new(AddMultiattack {
+            bot: bot,
+            user: user,
+            world: world,
+            chan: args[1],
+            target_str: args[2],
+            name: args[3],
+            actions: args[4..].iter().map(|s| s.to_string()).collect(),
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddMultiattack<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_monster(self.target_str, self.chan);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.user), format!("{} is not a valid monster.", self.target_str)));
+        }
+        let monster = try!(res);
+        for name in self.actions.iter() {
+            match monster.get_action(name) {
+                Some(action) if !action.is_multiattack() => (),
+                _ => return Err(Propagated(
+                    format!("{}", self.user), format!("{} has no attack named {}.", monster.name, name)
+                )),
+            }
+        }
+        let action = Action::multiattack(self.name, self.actions.clone());
+        let s = format!("{} ({}) can now use {}.", monster.name, self.target_str, action);
+        monster.add_action(action);
+        as_io(self.bot.send_privmsg(self.user, &s))
+    }
+}
+
+pub struct Act<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    monster_str: &'a str,
+    action: &'a str,
+    target_str: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Act<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 4 || !args[1].starts_with("@") {
+            return Err(incorrect_format(chan, ".act", "@monster action target"));
+        } else if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        }
+        Ok(Box::new(Act {
+            bot: bot,
+            chan: chan,
+            world: world,
+            monster_str: args[1],
+            action: args[2],
+            target_str: args[3],
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Act<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        if self.world.get_monster(self.monster_str, self.chan).is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not a valid monster.", self.monster_str)));
+        }
+        // Checked before the action is spent, so that a mistyped target doesn't waste it.
+        if self.world.get_entity(self.target_str, Some(self.chan)).is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not a valid target.", self.target_str)));
+        }
+        let (name, position, attacks) = {
+            let monster = try!(self.world.get_monster(self.monster_str, self.chan));
+            match monster.use_action(self.action) {
+                Ok(attacks) => (monster.name.clone(), monster.position, attacks),
+                Err(e) => return Err(Propagated(format!("{}", self.chan), format!("{}", e))),
+            }
+        };
+        if attacks.len() > 1 {
+            let s = format!("{} ({}) uses {}.", name, self.monster_str, self.action);
+            try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+        }
+        for action in attacks.iter() {
+            let s = try!(resolve_attack(self.world, self.chan, &name, self.monster_str, position,
+                                        action, self.target_str));
+            try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use std::borrow::ToOwned;
     use data::Entity;
+    use data::action::Action;
+    use data::damage::DamageType::{Bludgeoning, Piercing};
+    use data::dice::Dice;
     use data::monster::Monster;
+    use data::player::Player;
+    use data::skill::Skill::Stealth;
     use data::stats::Stats;
-    use func::test::test_helper;
+    use data::utils::Position;
+    use func::test::{test_helper, test_helper_scripted};
 
     #[test]
     fn add_success() {
@@ -124,6 +342,19 @@ mod test {
         assert_eq!(data, format!("PRIVMSG test :Monster (Test) has been created as @0.\r\n"));
     }
 
+    #[test]
+    fn add_failed_stat_too_large() {
+        let data = test_helper(":test!test@test PRIVMSG test :addmonster #test Test 10000 30 12 12 12 12 12 12\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG test :Stats can be at most 9999. Format is:\r\n".to_string();
+        exp.push_str("PRIVMSG test :addmonster chan name health movement str dex con wis int cha [skill=bonus ...]\r\n");
+        assert_eq!(data, exp);
+    }
+
     #[test]
     fn add_failed_non_integers() {
         let data = test_helper(":test!test@test PRIVMSG test :addmonster #test Test 20 30 -12 a 12 12 12 12\r\n",
@@ -133,10 +364,32 @@ mod test {
             }
         ).unwrap();
         let mut exp = "PRIVMSG test :Stats must be non-zero positive integers. Format is:\r\n".to_string();
-        exp.push_str("PRIVMSG test :addmonster chan name health movement str dex con wis int cha\r\n");
+        exp.push_str("PRIVMSG test :addmonster chan name health movement str dex con wis int cha [skill=bonus ...]\r\n");
         assert_eq!(data, exp);
     }
 
+    #[test]
+    fn add_success_with_skills() {
+        let data = test_helper(":test!test@test PRIVMSG test :addmonster #test Test 20 30 12 12 12 12 12 12 stealth=6 perception=4\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Monster (Test) has been created as @0.\r\n"));
+    }
+
+    #[test]
+    fn add_failed_invalid_skill() {
+        let data = test_helper(":test!test@test PRIVMSG test :addmonster #test Test 20 30 12 12 12 12 12 12 juggling=6\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :juggling=6 is not a valid skill bonus.\r\n"));
+    }
+
     #[test]
     fn look_up_success() {
         let data = test_helper(":test!test@test PRIVMSG test :mlookup #test @0\r\n",
@@ -174,6 +427,20 @@ mod test {
         assert_eq!(data, format!("PRIVMSG test :Test (@0): 20 health\r\n"));
     }
 
+    #[test]
+    fn look_up_success_by_skill() {
+        let data = test_helper(":test!test@test PRIVMSG test :mlookup #test @0 stealth\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.skills.push((Stealth, 6));
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Test (@0): +6 stealth\r\n"));
+    }
+
     #[test]
     fn look_up_failed_invalid_stat() {
         let data = test_helper(":test!test@test PRIVMSG test :mlookup #test @0 test\r\n",
@@ -227,4 +494,226 @@ mod test {
         let exp = "PRIVMSG test :Test (@0): Position(0, 0)\r\n".to_string();
         assert_eq!(data, exp);
     }
+
+    #[test]
+    fn add_action_success() {
+        let data = test_helper(":test!test@test PRIVMSG test :addaction #test @0 Bite +4 5 1d6+2 piercing uses=3\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Test (@0) can now use Bite (+4, 5 ft., 1d6+2 piercing, 3/3 per day).\r\n"));
+    }
+
+    #[test]
+    fn add_action_failed_invalid_damage() {
+        let data = test_helper(":test!test@test PRIVMSG test :addaction #test @0 Bite 4 5 lots piercing\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG test :Incorrect format for addaction. Format is:\r\n".to_string();
+        exp.push_str("PRIVMSG test :addaction channel @monster name bonus reach damage type [recharge=n] [uses=n]\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn add_action_failed_no_monster() {
+        let data = test_helper(":test!test@test PRIVMSG test :addaction #test @0 Bite 4 5 1d6 piercing\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :@0 is not a valid monster.\r\n"));
+    }
+
+    #[test]
+    fn add_multiattack_success() {
+        let data = test_helper(":test!test@test PRIVMSG test :addmulti #test @0 Multiattack Bite Bite\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Test (@0) can now use Multiattack (Bite, Bite).\r\n"));
+    }
+
+    #[test]
+    fn add_multiattack_failed_unknown_attack() {
+        let data = test_helper(":test!test@test PRIVMSG test :addmulti #test @0 Multiattack Bite Claw\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Test has no attack named Claw.\r\n"));
+    }
+
+    #[test]
+    fn look_up_actions() {
+        let data = test_helper(":test!test@test PRIVMSG test :mlookup #test @0 actions\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+                m.add_action(Action::multiattack("Multiattack", vec!["Bite".to_string(), "Bite".to_string()]));
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Test (@0): Bite (+4, 5 ft., 1d6+2 piercing), Multiattack (Bite, Bite)\r\n"));
+    }
+
+    #[test]
+    fn act_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.act @0 bite test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+                world.add_monster(m, "#test");
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data[..55].to_owned(), format!("PRIVMSG #test :Test (@0) attacks test (test) with Bite:"));
+    }
+
+    #[test]
+    fn act_multiattack() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.act @0 multiattack test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+                m.add_action(Action::multiattack("Multiattack", vec!["Bite".to_string(), "Bite".to_string()]));
+                world.add_monster(m, "#test");
+                let p = Player::create_test("test", "test", 100, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "PRIVMSG #test :Test (@0) uses multiattack.");
+        assert!(lines[1].starts_with("PRIVMSG #test :Test (@0) attacks test (test) with Bite:"));
+        assert!(lines[2].starts_with("PRIVMSG #test :Test (@0) attacks test (test) with Bite:"));
+    }
+
+    #[test]
+    fn act_out_of_reach() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.act @0 bite test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+                world.add_monster(m, "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                try!(p.do_move(Position(3, 0)));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test (@0) attacks test (test) with Bite: out of reach.\r\n"));
+    }
+
+    #[test]
+    fn act_failed_bad_target_keeps_action() {
+        let mut input = ":test!test@test PRIVMSG #test :.act @0 web tset\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.act @0 web test\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.act @0 breath tset\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.act @0 breath test\r\n");
+        let data = test_helper_scripted(&input, vec![15, 1, 15, 1],
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                let mut web = Action::attack("Web", 5, 30, Dice::new(1, 6, 0), Bludgeoning);
+                web.uses = Some(1);
+                m.add_action(web);
+                let mut breath = Action::attack("Breath", 5, 30, Dice::new(1, 6, 0), Bludgeoning);
+                breath.recharge = Some(5);
+                m.add_action(breath);
+                world.add_monster(m, "#test");
+                let p = Player::create_test("test", "test", 100, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG #test :tset is not a valid target.");
+        assert!(lines[1].starts_with("PRIVMSG #test :Test (@0) attacks test (test) with Web: 20 to hit"));
+        assert_eq!(lines[2], "PRIVMSG #test :tset is not a valid target.");
+        // Had the breath been spent, it would have needed a recharge roll first and then missed.
+        assert!(lines[3].starts_with("PRIVMSG #test :Test (@0) attacks test (test) with Breath: 20 to hit"));
+    }
+
+    #[test]
+    fn act_failed_other_game() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.act @0 bite test2\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_game("Other Test", "test3", "#test2");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Piercing));
+                world.add_monster(m, "#test");
+                world.add_user("test2", "#test2", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 is not a valid target.\r\n"));
+    }
+
+    #[test]
+    fn act_failed_unknown_action() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.act @0 claw test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                world.add_user("test", "#test", Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Test has no action named claw.\r\n"));
+    }
+
+    #[test]
+    fn act_failed_no_uses_left() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.act @0 web test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                let mut m = Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12);
+                let mut web = Action::attack("Web", 5, 30, Dice::new(0, 1, 0), Bludgeoning);
+                web.uses = Some(1);
+                web.used = 1;
+                m.add_action(web);
+                world.add_monster(m, "#test");
+                world.add_user("test", "#test", Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Web has no uses left today.\r\n"));
+    }
+
+    #[test]
+    fn act_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.act @0 bite test\r\n",
+            |world| {
+                world.add_game("Test", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
 }
@@ -1,51 +1,209 @@
 use std::ascii::AsciiExt;
 use data::{BotResult, Entity, as_io};
-use data::BotError::Propagated;
+use data::BotError::{InvalidInput, NotFound, Propagated};
+use data::builder::CharacterBuilder;
+use data::builder::Method::Rolled;
+use data::builder::Step::{ChooseMethod, ChooseScores, ChooseRace, ChooseClass, ChooseName};
+use data::class::CharacterClass;
+use data::feat::FeatInfo;
+use data::journal::{EventKind, log_event};
+use data::macros::Macro;
 use data::player::Player;
-use data::utils::{join_from, str_to_u8};
+use data::race::RaceInfo;
+use data::rules::RuleSystem;
+use data::skill::{Proficiency, Skill};
+use data::spell::Spell;
+use data::stats::Stats;
+use data::utils::{join_from, str_to_stat, str_to_u8};
 use data::world::World;
-use func::Functionality;
-use func::utils::{incorrect_format, validate_from};
+use func::{CHANNEL_COMMANDS, Functionality};
+use func::utils::{incorrect_format, permissions_test, validate_from};
 use irc::client::prelude::*;
 
 pub struct Register<'a, T: IrcRead, U: IrcWrite> {
     bot: &'a ServerExt<'a, T, U>,
     user: &'a str,
     username: &'a str, password: &'a str,
-    health: u8, movement: u8,
-    st: u8, dx: u8, cn: u8,
-    ws: u8, it: u8, ch: u8,
+    health: i32, movement: i32,
+    values: Vec<i32>,
+    race: Option<&'static RaceInfo>,
+    rules: RuleSystem,
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Register<'a, T, U> {
     pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>) -> BotResult<Box<Functionality + 'a>> {
-        if args.len() != 11 {
-            return Err(incorrect_format(user, "register",
-                                        "username password health movement str dex con wis int cha")
-            );
+        // The stats are given in the order the rule system lists them, which defaults to 5e.
+        let rules = match args.iter().find(|a| a.to_ascii_lowercase().starts_with("rules=")) {
+            Some(arg) => match RuleSystem::find(&arg[6..]) {
+                Ok(rules) => rules,
+                Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", user), msg)),
+                Err(e) => return Err(e),
+            },
+            None => RuleSystem::standard(),
+        };
+        let args: Vec<_> = args.into_iter().filter(|a| !a.to_ascii_lowercase().starts_with("rules=")).collect();
+        let format = format!("username password health movement {} [race [subrace]] [rules=system]", rules.stat_list());
+        let count = 5 + rules.stats.len();
+        if args.len() < count || args.len() > count + 2 {
+            return Err(incorrect_format(user, "register", &format));
+        }
+        try!(validate_from(args[..count].to_vec(), 3, user, "register", &format));
+        let values: Vec<_> = args[5..count].iter().map(|a| str_to_stat(a)).collect();
+        for (def, value) in rules.stats.iter().zip(values.iter()) {
+            if let Err(InvalidInput(msg)) = def.validate(*value) {
+                return Err(Propagated(format!("{}", user), msg));
+            }
         }
-        try!(validate_from(args.clone(), 3, user, "register",
-                           "username password health movement str dex con wis int cha"));
+        let race = if args.len() > count {
+            match RaceInfo::find(args[count], args.get(count + 1).map(|s| *s)) {
+                Ok(info) => Some(info),
+                Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", user), msg)),
+                Err(e) => return Err(e),
+            }
+        } else {
+            None
+        };
         Ok(Box::new(Register {
             bot: bot,
             user: user,
             username: args[1], password: args[2],
-            health: str_to_u8(args[3]), movement: str_to_u8(args[4]),
-            st: str_to_u8(args[5]), dx: str_to_u8(args[6]), cn: str_to_u8(args[7]),
-            ws: str_to_u8(args[8]), it: str_to_u8(args[9]), ch: str_to_u8(args[10]),
+            health: str_to_stat(args[3]), movement: str_to_stat(args[4]),
+            values: values,
+            race: race,
+            rules: rules,
         }))
     }
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for Register<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
-        let p = try!(Player::create(self.username, self.password, self.health, self.movement,
-                                    self.st, self.dx, self.cn, self.ws, self.it, self.ch));
+        // Built-in abilities the rule system doesn't use are left at an average 10, and characters
+        // with a race start with its speed.
+        let movement = self.race.map_or(self.movement, |info| info.speed as i32);
+        let mut p = try!(Player::create(self.username, self.password, self.health, movement,
+                                        10, 10, 10, 10, 10, 10));
+        if !self.rules.is_standard() {
+            p.rules = Some(self.rules.clone());
+        }
+        for (def, value) in self.rules.stats.iter().zip(self.values.iter()) {
+            try!(p.set_stat_value(&def.name, *value));
+        }
+        if let Some(info) = self.race {
+            p.apply_race(info);
+        }
         try!(as_io(p.save()));
         as_io(self.bot.send_privmsg(self.user, &format!("Your account ({}) has been created.", self.username)))
     }
 }
 
+pub struct Build<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    world: &'a mut World,
+    args: Vec<&'a str>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Build<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() == 1 && !world.builders.contains_key(user) {
+            return Err(incorrect_format(user, "build", "channel, then follow the prompts (or cancel)"));
+        }
+        Ok(Box::new(Build { bot: bot, user: user, world: world, args: args[1..].to_vec() }))
+    }
+
+    fn start(&mut self) -> BotResult<String> {
+        let chan = self.args[0];
+        if !self.world.game_exists(chan) {
+            return Err(Propagated(format!("{}", self.user), format!("There is no game in {}.", chan)));
+        }
+        let builder = CharacterBuilder::new(chan);
+        let prompt = builder.prompt();
+        self.world.builders.insert(self.user.to_string(), builder);
+        Ok(prompt)
+    }
+
+    // Fills in the current step of the builder from the message, returning the next prompt.
+    fn advance(&mut self) -> BotResult<String> {
+        let mut builder = self.world.builders[self.user].clone();
+        match builder.step() {
+            ChooseMethod => {
+                try!(builder.set_method(self.args[0]));
+                if builder.method == Some(Rolled) {
+                    try!(self.log_rolls(&mut builder));
+                }
+            },
+            ChooseScores => {
+                let scores: Vec<u8> = self.args.iter().map(|s| str_to_u8(*s)).collect();
+                try!(builder.set_scores(scores));
+            },
+            ChooseRace => builder.race = Some(try!(RaceInfo::find(self.args[0], self.args.get(1).map(|s| *s)))),
+            ChooseClass => builder.class = match CharacterClass::to_class(self.args[0]) {
+                Some(class) => Some(class),
+                None => return Err(InvalidInput(format!("{} is not a valid class.", self.args[0]))),
+            },
+            ChooseName => {
+                if self.args.len() != 2 {
+                    return Err(InvalidInput(format!("Give a character name and a password.")));
+                }
+                try!(self.finish(&builder));
+                self.world.builders.remove(self.user);
+                return Ok(format!("Your account ({}) has been created.", self.args[0]));
+            },
+        }
+        let prompt = builder.prompt();
+        self.world.builders.insert(self.user.to_string(), builder);
+        Ok(prompt)
+    }
+
+    // The DMs get every die so that rolled characters can be checked.
+    fn log_rolls(&mut self, builder: &mut CharacterBuilder) -> BotResult<()> {
+        let rolls: Vec<_> = builder.roll_scores().into_iter().map(|(total, dice)| {
+            let dice: Vec<_> = dice.iter().map(|d| format!("{}", d)).collect();
+            format!("{} ({})", total, dice.join(", "))
+        }).collect();
+        let dms = try!(self.world.get_game(&builder.chan)).dms.clone();
+        let s = format!("{} rolled ability scores (4d6 drop lowest): {}", self.user, rolls.join("; "));
+        for dm in dms.iter() {
+            try!(as_io(self.bot.send_privmsg(dm, &s)));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, builder: &CharacterBuilder) -> BotResult<()> {
+        let (scores, race, class) = (builder.scores.clone().unwrap(), builder.race.unwrap(), builder.class.unwrap());
+        let scores: Vec<_> = scores.iter().map(|s| *s as i32).collect();
+        let mut p = try!(Player::create(self.args[0], self.args[1], 1, race.speed as i32, scores[0], scores[1],
+                                        scores[2], scores[3], scores[4], scores[5]));
+        p.apply_race(race);
+        p.class = Some(class);
+        // First level characters start with the maximum of their hit die.
+        let health = class.hit_die() as i32 + Stats::calc_bonus(p.stats.constitution);
+        p.stats.health = if health < 1 { 1 } else { health };
+        p.max_health = p.stats.health;
+        as_io(p.save())
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Build<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = if !self.world.builders.contains_key(self.user) {
+            self.start()
+        } else if self.args.is_empty() {
+            Ok(self.world.builders[self.user].prompt())
+        } else if self.args[0].eq_ignore_ascii_case("cancel") {
+            self.world.builders.remove(self.user);
+            Ok(format!("Character creation has been cancelled."))
+        } else {
+            self.advance()
+        };
+        match res {
+            Ok(s) => as_io(self.bot.send_privmsg(self.user, &s)),
+            Err(InvalidInput(msg)) => Err(Propagated(format!("{}", self.user), msg)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
 pub struct Login<'a, T: IrcRead, U: IrcWrite> {
     bot: &'a ServerExt<'a, T, U>,
     user: &'a str,
@@ -85,9 +243,13 @@ impl<'a, T: IrcRead, U: IrcWrite> Login<'a, T, U> {
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for Login<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
         if let Some(game) = self.world.games.get_mut(&self.chan.to_string()) {
+            let journal = game.journal.clone();
             let res = game.login(self.player.clone(), self.user, self.password);
             if res.is_ok() {
                 try!(as_io(self.bot.send_privmsg(self.user, try!(res))));
+                // The user isn't in the game until now, so this goes straight to its journal.
+                let s = format!("{} logged in as {}.", self.user, self.player.username);
+                journal.borrow_mut().log(EventKind::Login, self.user, &s);
                 try!(as_io(self.bot.send_invite(self.user, self.chan)));
             } else {
                 return Err(Propagated(format!("{}", self.user), format!("{}", res.unwrap_err())))
@@ -115,6 +277,7 @@ impl<'a, T: IrcRead, U: IrcWrite> Logout<'a, T, U> {
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for Logout<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
         if self.world.is_user_logged_in(self.user) {
+            log_event(EventKind::Logout, &format!("{} logged out.", self.user));
             let chan = try!(self.world.remove_user(self.user));
             try!(as_io(self.bot.send_kick(chan, self.user, "Logged out.")));
             try!(as_io(self.bot.send_privmsg(self.user, "You've been logged out.")));
@@ -140,14 +303,168 @@ impl<'a, T: IrcRead, U: IrcWrite> AddFeat<'a, T, U> {
 }
 
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddFeat<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let chan = self.world.user_channels.get(self.user).cloned();
+        let (username, chan) = match (self.world.get_user(self.user), chan) {
+            (Ok(player), Some(chan)) => {
+                if let Err(InvalidInput(msg)) = player.check_feat(&self.feat_name) {
+                    return Err(Propagated(format!("{}", self.user), msg));
+                }
+                (player.username.clone(), chan)
+            },
+            _ => return Err(Propagated(format!("{}", self.user), format!("You must be logged in to add a feat."))),
+        };
+        let feat = FeatInfo::find(&self.feat_name).map_or(self.feat_name.clone(), |info| info.name.to_string());
+        let dms = {
+            let game = try!(self.world.get_game(&chan));
+            game.request_feat(self.user, &feat);
+            game.dms.clone()
+        };
+        let s = format!("Requested the {} feat. The DM must approve it first.", feat);
+        try!(as_io(self.bot.send_privmsg(self.user, &s)));
+        let s = format!("{} ({}) requested the {} feat. Use .approve {} {} or .deny {} {} in {}.", username,
+                        self.user, feat, self.user, feat, self.user, feat, chan);
+        for dm in dms.iter() {
+            try!(as_io(self.bot.send_privmsg(dm, &s)));
+        }
+        Ok(())
+    }
+}
+
+pub struct RemoveFeat<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    world: &'a mut World,
+    feat_name: String,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> RemoveFeat<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() < 2 { return Err(incorrect_format(user, "removefeat", "name of feat")); }
+        Ok(Box::new(RemoveFeat { bot: bot, user: user, world: world, feat_name: join_from(args, 1) }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for RemoveFeat<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
         if let Ok(player) = self.world.get_user(self.user) {
-            player.add_feat(&self.feat_name);
-            try!(as_io(self.bot.send_privmsg(self.user, &format!("Added {} feat.", self.feat_name))));
+            match player.remove_feat(&self.feat_name) {
+                Ok(feat) => try!(as_io(self.bot.send_privmsg(self.user, &format!("Removed {} feat.", feat)))),
+                Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", self.user), msg)),
+                Err(e) => return Err(e),
+            }
             Ok(())
         } else {
-            Err(Propagated(format!("{}", self.user), format!("You must be logged in to add a feat.")))
+            Err(Propagated(format!("{}", self.user), format!("You must be logged in to remove a feat.")))
+        }
+    }
+}
+
+pub struct LookUpFeat<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    feat_name: String,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> LookUpFeat<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() < 2 { return Err(incorrect_format(user, "featinfo", "name of feat")); }
+        Ok(Box::new(LookUpFeat { bot: bot, user: user, feat_name: join_from(args, 1) }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for LookUpFeat<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let info = match FeatInfo::find(&self.feat_name) {
+            Some(info) => info,
+            None => return Err(Propagated(
+                format!("{}", self.user), format!("{} is not a known feat.", self.feat_name)
+            )),
+        };
+        let s = format!("{} ({}): {}", info.name, info.describe_requirements(), info.description);
+        as_io(self.bot.send_privmsg(self.user, &s))
+    }
+}
+
+pub struct ReviewFeat<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    nick: &'a str,
+    feat_name: String,
+    approve: bool,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ReviewFeat<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World, approve: bool) -> BotResult<Box<Functionality + 'a>> {
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() < 3 {
+            let cmd = if approve { ".approve" } else { ".deny" };
+            return Err(incorrect_format(chan, cmd, "nickname name of feat or spell"));
+        }
+        Ok(Box::new(ReviewFeat {
+            bot: bot, chan: chan, world: world, nick: args[1], feat_name: join_from(args, 2), approve: approve
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ReviewFeat<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let spell = try!(self.world.get_game(self.chan)).spell_request(self.nick, &self.feat_name).cloned();
+        if let Some(spell) = spell {
+            return self.review_spell(spell);
         }
+        let feat = match try!(self.world.get_game(self.chan)).feat_request(self.nick, &self.feat_name) {
+            Some(feat) => feat.to_string(),
+            None => return Err(Propagated(
+                format!("{}", self.chan), format!("{} hasn't requested {}.", self.nick, self.feat_name)
+            )),
+        };
+        // The request is kept until the feat has been added, so a failed approval can be retried.
+        let s = {
+            let player = match self.world.get_user(self.nick) {
+                Ok(player) => player,
+                Err(_) => return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.nick))),
+            };
+            if !self.approve {
+                format!("The request from {} ({}) for the {} feat was denied.", player.username, self.nick, feat)
+            } else {
+                match player.add_feat(&feat) {
+                    Ok(feat) => format!("{} ({}) gained the {} feat.", player.username, self.nick, feat),
+                    Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", self.chan), msg)),
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+        try!(self.world.get_game(self.chan)).take_feat_request(self.nick, &feat);
+        log_event(EventKind::Dm, &s);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ReviewFeat<'a, T, U> {
+    // Spells requested with .learn are reviewed the same way as feats. The request is only
+    // dropped once the spell has been learned or denied.
+    fn review_spell(&mut self, spell: Spell) -> BotResult<()> {
+        let s = {
+            let player = match self.world.get_user(self.nick) {
+                Ok(player) => player,
+                Err(_) => return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.nick))),
+            };
+            if !self.approve {
+                format!("The request from {} ({}) to learn {} was denied.", player.username, self.nick, spell.name)
+            } else {
+                if let Err(InvalidInput(msg)) = player.check_spell(&spell) {
+                    return Err(Propagated(format!("{}", self.chan), msg));
+                }
+                player.learn_spell(spell.clone());
+                format!("{} ({}) learned {}.", player.username, self.nick, player.spell(&spell.name).unwrap())
+            }
+        };
+        try!(self.world.get_game(self.chan)).take_spell_request(self.nick, &spell.name);
+        log_event(EventKind::Dm, &s);
+        as_io(self.bot.send_privmsg(self.chan, &s))
     }
 }
 
@@ -221,7 +538,13 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for LookUpPlayer<'a, T, U> {
         }
         let p = try!(res);
         let temp = if p.has_temp_stats() { "Temp. " } else { "" };
-        if self.stat_str.is_none() {
+        if self.stat_str.is_none() && p.rules.is_some() {
+            let rules = p.rules();
+            let stats: Vec<_> = rules.stats.iter().map(|d| format!("{} {}", d.name, p.stat_value(&d.name).unwrap_or(0))).collect();
+            let s = format!("{} ({}): {}Health {}, Movement {}, {} Feats {:?}", p.username, self.target_str, temp,
+                            p.stats().health, p.stats().movement, stats.join(", "), p.feats);
+            as_io(self.bot.send_privmsg(self.resp, &s))
+        } else if self.stat_str.is_none() {
             let s = format!("{} ({}): {}{:?} Feats {:?}", p.username, self.target_str, temp, p.stats(), p.feats);
             as_io(self.bot.send_privmsg(self.resp, &s))
         } else if self.stat_str.unwrap().eq_ignore_ascii_case("feats") || self.stat_str.unwrap().eq_ignore_ascii_case("feat") {
@@ -230,9 +553,42 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for LookUpPlayer<'a, T, U> {
         } else if self.stat_str.unwrap().eq_ignore_ascii_case("pos") || self.stat_str.unwrap().eq_ignore_ascii_case("position") {
             let s = format!("{} ({}): {:?}", p.username, self.target_str, p.position());
             as_io(self.bot.send_privmsg(self.resp, &s))
-        } else if let Some(x) = p.stats().get_stat(self.stat_str.unwrap()) {
+        } else if self.stat_str.unwrap().eq_ignore_ascii_case("defenses") {
+            let s = format!("{} ({}): {}", p.username, self.target_str, p.defenses());
+            as_io(self.bot.send_privmsg(self.resp, &s))
+        } else if ["class", "level", "xp"].iter().any(|k| self.stat_str.unwrap().eq_ignore_ascii_case(k)) {
+            let class = p.class.map_or("classless".to_string(), |c| format!("{}", c));
+            let s = format!("{} ({}): level {} {}, {} XP", p.username, self.target_str, p.level, class, p.xp);
+            as_io(self.bot.send_privmsg(self.resp, &s))
+        } else if self.stat_str.unwrap().eq_ignore_ascii_case("race") {
+            let s = match p.race {
+                Some(ref race) => {
+                    let traits = if race.traits.is_empty() { "none".to_string() } else { race.traits.join(", ") };
+                    format!("{} ({}): {} ({:?}, speed {}, darkvision {} ft.) Traits: {}", p.username, self.target_str,
+                            race, race.size, race.speed, race.darkvision, traits)
+                },
+                None => format!("{} ({}): no race recorded", p.username, self.target_str),
+            };
+            as_io(self.bot.send_privmsg(self.resp, &s))
+        } else if self.stat_str.unwrap().eq_ignore_ascii_case("skills") {
+            let skills: Vec<_> = p.skills.iter().map(|&(skill, prof)| match prof {
+                Proficiency::Proficient => format!("{}", skill),
+                Proficiency::Expertise => format!("{} (expertise)", skill),
+            }).collect();
+            let s = format!("{} ({}): {}", p.username, self.target_str, skills.join(", "));
+            as_io(self.bot.send_privmsg(self.resp, &s))
+        } else if self.stat_str.unwrap().eq_ignore_ascii_case("modifiers") {
+            let modifiers: Vec<_> = p.modifiers.iter().map(|m| format!("{}", m)).collect();
+            let list = if modifiers.is_empty() { "none".to_string() } else { modifiers.join("; ") };
+            let s = format!("{} ({}): {}", p.username, self.target_str, list);
+            as_io(self.bot.send_privmsg(self.resp, &s))
+        } else if let Some(x) = p.stat_value(self.stat_str.unwrap()) {
+            let x = p.explain_stat(self.stat_str.unwrap()).unwrap_or(format!("{}", x));
             let s = format!("{} ({}): {}{} {}", p.identifier(), self.target_str, temp, x, self.stat_str.unwrap());
             as_io(self.bot.send_privmsg(self.resp, &s))
+        } else if let Some(skill) = Skill::to_skill(self.stat_str.unwrap()) {
+            let s = format!("{} ({}): {:+} {}", p.identifier(), self.target_str, p.skill_bonus(skill), self.stat_str.unwrap());
+            as_io(self.bot.send_privmsg(self.resp, &s))
         } else {
             Err(Propagated(format!("{}", self.resp), format!("{} is not a valid stat.", self.stat_str.unwrap())))
         }
@@ -245,7 +601,7 @@ pub struct AddUpdate<'a, T: IrcRead, U: IrcWrite> {
     chan: &'a str,
     world: &'a mut World,
     stat_str: &'a str,
-    value: u8,
+    value: i32,
     update: bool,
 }
 
@@ -260,10 +616,9 @@ impl<'a, T: IrcRead, U: IrcWrite> AddUpdate<'a, T, U> {
             chan: chan,
             world: world,
             stat_str: args[1],
-            value: if let Ok(n) = args[2].parse() {
-                n
-            } else {
-                return Err(Propagated(format!("{}", chan), format!("{} is not a valid positive integer.", args[2])));
+            value: match args[2].parse() {
+                Ok(n) if n >= 0 => n,
+                _ => return Err(Propagated(format!("{}", chan), format!("{} is not a valid positive integer.", args[2]))),
             },
             update: update,
         }))
@@ -273,18 +628,17 @@ impl<'a, T: IrcRead, U: IrcWrite> AddUpdate<'a, T, U> {
 impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddUpdate<'a, T, U> {
     fn do_func(&mut self) -> BotResult<()> {
         if let Ok(p) = self.world.get_user(self.user) {
-            if self.update {
-                p.stats.update_stat(self.stat_str, self.value);
-                try!(as_io(
-                    self.bot.send_privmsg(self.chan, &format!("{} ({}) now has {} {}.", p.username, self.user, self.value, self.stat_str))
-                ));
+            let value = if self.update {
+                self.value
             } else {
-                p.stats.increase_stat(self.stat_str, self.value);
-                let k = if let Some(i) = p.stats.get_stat(self.stat_str) { i } else { 0 };
-                try!(as_io(
-                    self.bot.send_privmsg(self.chan, &format!("{} ({}) now has {} {}.", p.username, self.user, k, self.stat_str))
-                ));
+                p.stat_value(self.stat_str).unwrap_or(0).saturating_add(self.value)
+            };
+            if let Err(InvalidInput(msg)) = p.set_stat_value(self.stat_str, value) {
+                return Err(Propagated(format!("{}", self.chan), msg));
             }
+            try!(as_io(
+                self.bot.send_privmsg(self.chan, &format!("{} ({}) now has {} {}.", p.username, self.user, value, self.stat_str))
+            ));
             Ok(())
         } else {
             Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")))
@@ -292,11 +646,307 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for AddUpdate<'a, T, U> {
     }
 }
 
+pub struct SetClass<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+    class: CharacterClass,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> SetClass<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() != 3 {
+            return Err(incorrect_format(chan, ".class", "target class"));
+        }
+        Ok(Box::new(SetClass {
+            bot: bot,
+            chan: chan,
+            world: world,
+            target_str: args[1],
+            class: match CharacterClass::to_class(args[2]) {
+                Some(class) => class,
+                None => return Err(Propagated(format!("{}", chan), format!("{} is not a valid class.", args[2]))),
+            },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for SetClass<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        // The DM can only change characters in their own game.
+        if !self.world.party(self.chan).iter().any(|nick| nick == self.target_str) {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+        }
+        let p = try!(self.world.get_user(self.target_str));
+        p.class = Some(self.class);
+        let s = format!("{} ({}) is now a level {} {}.", p.username, self.target_str, p.level, self.class);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct AwardXp<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+    amount: u32,
+    rolled: bool,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> AwardXp<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if (args.len() != 3 && args.len() != 4) || (args.len() == 4 && !args[3].eq_ignore_ascii_case("roll")) {
+            return Err(incorrect_format(chan, ".xp", "target|party amount [roll]"));
+        }
+        Ok(Box::new(AwardXp {
+            bot: bot,
+            chan: chan,
+            world: world,
+            target_str: args[1],
+            amount: if let Ok(n) = args[2].parse() {
+                n
+            } else {
+                return Err(Propagated(format!("{}", chan), format!("{} is not a valid positive integer.", args[2])));
+            },
+            rolled: args.len() == 4,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for AwardXp<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let targets = if self.target_str.eq_ignore_ascii_case("party") {
+            let party = self.world.party(self.chan);
+            if party.is_empty() {
+                return Err(Propagated(format!("{}", self.chan), format!("There are no players in {}.", self.chan)));
+            }
+            party
+        } else if self.world.party(self.chan).iter().any(|nick| nick == self.target_str) {
+            vec![self.target_str.to_string()]
+        } else {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+        };
+        for nick in targets.iter() {
+            let p = try!(self.world.get_user(nick));
+            let gains = p.award_xp(self.amount, self.rolled);
+            let s = format!("{} ({}) gained {} XP and now has {} XP.", p.username, nick, self.amount, p.xp);
+            log_event(EventKind::Dm, &s);
+            try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+            for &(level, hp) in gains.iter() {
+                let s = format!("{} ({}) has reached level {} and gained {} hit points ({} max).",
+                                p.username, nick, level, hp, p.max_health);
+                try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Rest<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    long: bool,
+    max_dice: u8,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Rest<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "short [max hit dice]|long";
+        if let Err(perm) = permissions_test(user, chan, world) {
+            return Err(perm);
+        } else if args.len() < 2 || args.len() > 3 {
+            return Err(incorrect_format(chan, ".rest", format));
+        }
+        let long = match &args[1].to_ascii_lowercase()[..] {
+            "short" => false,
+            "long" if args.len() == 2 => true,
+            _ => return Err(incorrect_format(chan, ".rest", format)),
+        };
+        Ok(Box::new(Rest {
+            bot: bot,
+            chan: chan,
+            world: world,
+            long: long,
+            max_dice: match args.get(2).map(|s| s.parse::<u8>()) {
+                None => 255,
+                Some(Ok(n)) => n,
+                Some(Err(_)) => return Err(Propagated(
+                    format!("{}", chan), format!("{} is not a valid positive integer.", args[2])
+                )),
+            },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Rest<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let party = self.world.party(self.chan);
+        if party.is_empty() {
+            return Err(Propagated(format!("{}", self.chan), format!("There are no players in {}.", self.chan)));
+        }
+        let s = format!("The party takes a {} rest.", if self.long { "long" } else { "short" });
+        log_event(EventKind::Dm, &s);
+        try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+        for nick in party.iter() {
+            let p = try!(self.world.get_user(nick));
+            let res = if self.long { p.long_rest() } else { p.short_rest(self.max_dice) };
+            let mut s = format!("{} ({}) ", p.username, nick);
+            if res.dice_spent > 0 {
+                s.push_str(&format!("spent {} hit dice and ", res.dice_spent));
+            }
+            s.push_str(&format!("regained {} health ({}/{})", res.healed, p.stats.health, p.max_health));
+            if res.dice_regained > 0 {
+                s.push_str(&format!(" and {} hit dice", res.dice_regained));
+            }
+            s.push_str(&format!(", with {}/{} hit dice left.", p.hit_dice_remaining(), p.level));
+            if res.slots_recovered {
+                s.push_str(" Spell slots restored.");
+            }
+            if let Some(effect) = res.ended {
+                s.push_str(&format!(" Concentration on {} ended{}.", effect.spell, effect.ending()));
+            }
+            if !res.expired.is_empty() {
+                let names: Vec<_> = res.expired.iter().map(|m| &m.name[..]).collect();
+                s.push_str(&format!(" {} wore off.", names.join(", ")));
+            }
+            try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+        }
+        if let Some(monsters) = self.world.monsters.get_mut(self.chan) {
+            for (i, m) in monsters.iter_mut().enumerate() {
+                let restored = m.rest(self.long);
+                if !restored.is_empty() {
+                    let s = format!("{} (@{}) can use {} again.", m.name, i, restored.join(", "));
+                    try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct EditMacro<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    world: &'a mut World,
+    args: Vec<&'a str>,
+    new_macro: Option<Macro>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> EditMacro<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let new_macro = match args.get(1).map(|s| s.to_ascii_lowercase()) {
+            Some(ref action) if action == "set" && args.len() > 3 => match Macro::new(args[2], &join_from(args.clone(), 3)) {
+                Ok(ref m) if m.name == "help" || CHANNEL_COMMANDS.contains(&&m.name[..]) => return Err(Propagated(
+                    format!("{}", user), format!(".{} is already a command, so it can't be a macro.", m.name)
+                )),
+                Ok(m) => Some(m),
+                Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", user), msg)),
+                Err(e) => return Err(e),
+            },
+            Some(ref action) if (action == "remove" && args.len() == 3) || (action == "list" && args.len() == 2) => None,
+            _ => return Err(incorrect_format(user, "macro", "set name roll [; roll ...] | remove name | list")),
+        };
+        Ok(Box::new(EditMacro { bot: bot, user: user, world: world, args: args, new_macro: new_macro }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for EditMacro<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let player = match self.world.get_user(self.user) {
+            Ok(player) => player,
+            Err(_) => return Err(Propagated(format!("{}", self.user), format!("You must be logged in to use macros."))),
+        };
+        let s = if let Some(m) = self.new_macro.take() {
+            format!("{} macro {}: {}. Use it with .{} or .m {} in your game.",
+                    if player.set_macro(m.clone()) { "Updated" } else { "Saved" },
+                    m.name, m.rolls.join("; "), m.name, m.name)
+        } else if self.args.len() == 3 {
+            match player.remove_macro(self.args[2]) {
+                Ok(m) => format!("Removed macro {}.", m.name),
+                Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", self.user), msg)),
+                Err(e) => return Err(e),
+            }
+        } else if player.macros.is_empty() {
+            format!("{} has no macros.", player.username)
+        } else {
+            let macros: Vec<_> = player.macros.iter().map(|m| format!("{} ({})", m.name, m.rolls.join("; "))).collect();
+            format!("Macros: {}.", macros.join(", "))
+        };
+        as_io(self.bot.send_privmsg(self.user, &s))
+    }
+}
+
+pub struct UseMacro<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    name: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> UseMacro<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 2 { return Err(incorrect_format(chan, ".m", "name")); }
+        Ok(Box::new(UseMacro { bot: bot, user: user, chan: chan, world: world, name: args[1] }))
+    }
+
+    // Runs a macro used as a command of its own, like .sneak, as long as the player has one by
+    // that name. Built in commands always come first.
+    pub fn fallback(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let cmd: &'a str = args[0];
+        let name = &cmd[1..];
+        if args.len() == 1 && world.users.get(user).map_or(false, |p| p.get_macro(name).is_some()) {
+            Ok(Box::new(UseMacro { bot: bot, user: user, chan: chan, world: world, name: name }))
+        } else {
+            Err(NotFound(cmd.to_owned()))
+        }
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for UseMacro<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let player = match self.world.get_user(self.user) {
+            Ok(player) => player,
+            Err(_) => return Err(Propagated(format!("{}", self.chan), format!("You're not logged in."))),
+        };
+        let m = match player.get_macro(self.name) {
+            Some(m) => m.clone(),
+            None => return Err(Propagated(
+                format!("{}", self.chan), format!("{} has no macro named {}.", player.username, self.name)
+            )),
+        };
+        let rolls: Vec<_> = try!(m.roll(&*player)).into_iter()
+            .map(|r| format!("{} = {} ({})", r.expression, r.total, r.breakdown)).collect();
+        let s = format!("{} ({}) used {}: {}.", player.username, self.user, m.name, rolls.join("; "));
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use data::as_io;
+    use data::action::Action;
+    use data::builder::CharacterBuilder;
+    use data::class::CharacterClass::{Fighter, Warlock};
+    use data::damage::DamageType::Fire;
+    use data::dice::Dice;
+    use data::monster::Monster;
+    use data::modifier::{Expiry, Modifier};
+    use data::modifier::Operation::{Add, Set};
     use data::player::Player;
-    use func::test::test_helper;
+    use data::race::RaceInfo;
+    use data::rules::RuleSystem;
+    use data::skill::Proficiency::{Expertise, Proficient};
+    use data::skill::Skill::{Perception, Stealth};
+    use data::stats::Stats;
+    use data::macros::Macro;
+    use func::test::{test_helper, test_helper_scripted};
 
     #[test]
     fn register_success() {
@@ -305,44 +955,168 @@ mod test {
         assert_eq!(data, format!("PRIVMSG test :Your account (test5) has been created.\r\n"));
     }
 
+    #[test]
+    fn register_success_with_race() {
+        let data = test_helper(":test!test@test PRIVMSG test :register test6 test 20 30 12 12 12 12 12 12 elf wood\r\n",
+                    |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Your account (test6) has been created.\r\n"));
+        let p = Player::load("test6").unwrap();
+        assert_eq!((p.stats.movement, p.stats.dexterity), (35, 14));
+    }
+
+    #[test]
+    fn register_failed_missing_subrace() {
+        let data = test_helper(":test!test@test PRIVMSG test :register test6 test 20 30 12 12 12 12 12 12 gnome\r\n",
+                    |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Gnome requires a subrace: Forest, Rock.\r\n"));
+    }
+
     #[test]
     fn register_failed_invalid_stats() {
         let data = test_helper(":test!test@test PRIVMSG test :register test5 test 20 30 12 -12 a 12 12 12\r\n",
                     |_| { Ok(()) }).unwrap();
         let mut exp = "PRIVMSG test :Stats must be non-zero positive integers. Format is:\r\n".to_string();
-        exp.push_str("PRIVMSG test :register username password health movement str dex con wis int cha\r\n");
+        exp.push_str("PRIVMSG test :register username password health movement str dex con wis int cha [race [subrace]] [rules=system]\r\n");
         assert_eq!(data, exp);
     }
 
     #[test]
-    fn login_success() {
-        let data = test_helper(":test!test@test PRIVMSG test :login login test #test\r\n",
+    fn register_success_with_rules() {
+        let data = test_helper(":test!test@test PRIVMSG test :register test7 test 20 30 50 60 55 70 45 65 80 40 rules=coc\r\n",
+                    |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Your account (test7) has been created.\r\n"));
+        let p = Player::load("test7").unwrap();
+        assert_eq!(p.stat_value("siz"), Some(55));
+        assert_eq!(p.stats.dexterity, 70);
+    }
+
+    #[test]
+    fn register_failed_out_of_range() {
+        let data = test_helper(":test!test@test PRIVMSG test :register test7 test 20 30 50 60 55 70 45 65 80 100 rules=coc\r\n",
+                    |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :EDU must be between 1 and 99.\r\n"));
+    }
+
+    #[test]
+    fn register_failed_wrong_stat_count() {
+        let data = test_helper(":test!test@test PRIVMSG test :register test7 test 20 30 12 12 12 12 12 12 rules=coc\r\n",
+                    |_| { Ok(()) }).unwrap();
+        let mut exp = "PRIVMSG test :Incorrect format for register. Format is:\r\n".to_string();
+        exp.push_str("PRIVMSG test :register username password health movement str con siz dex app int pow edu [race [subrace]] [rules=system]\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn build_start() {
+        let data = test_helper(":test!test@test PRIVMSG test :build #test\r\n",
             |world| {
                 world.add_game("Dungeons and Tests", "test", "#test");
                 Ok(())
             }
         ).unwrap();
-        let mut exp = "PRIVMSG test :Login successful.\r\n".to_string();
-        exp.push_str("INVITE test #test\r\n");
-        assert_eq!(data, exp);
+        assert_eq!(data, format!("PRIVMSG test :Choose how to generate ability scores: pointbuy, standard or roll.\r\n"));
     }
 
     #[test]
-    fn login_failed_password_incorrect() {
-        let data = test_helper(":test!test@test PRIVMSG test :login login ztest #test\r\n",
+    fn build_standard_array() {
+        let data = test_helper(":test!test@test PRIVMSG test :build standard\r\n",
             |world| {
                 world.add_game("Dungeons and Tests", "test", "#test");
+                world.builders.insert("test".to_string(), CharacterBuilder::new("#test"));
                 Ok(())
             }
         ).unwrap();
-        assert_eq!(data, format!("PRIVMSG test :Password incorrect.\r\n"));
+        assert_eq!(data, format!("PRIVMSG test :Assign 15 14 13 12 10 8 to str dex con wis int cha, in that order.\r\n"));
     }
 
     #[test]
-    fn login_failed_game_not_found() {
-        let data = test_helper(":test!test@test PRIVMSG test :login login test #test\r\n", |_| { Ok(()) }).unwrap();
-        assert_eq!(data, format!("PRIVMSG test :Game not found on #test.\r\n"));
-    }
+    fn build_roll_logged_for_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG test2 :build roll\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                world.builders.insert("test2".to_string(), CharacterBuilder::new("#test"));
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert!(lines[0].starts_with("PRIVMSG test :test2 rolled ability scores (4d6 drop lowest): "));
+        assert!(lines[1].starts_with("PRIVMSG test2 :Assign your rolls ("));
+    }
+
+    #[test]
+    fn build_failed_point_buy_over_budget() {
+        let data = test_helper(":test!test@test PRIVMSG test :build 15 15 15 15 8 8\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut b = CharacterBuilder::new("#test");
+                b.set_method("pointbuy").unwrap();
+                world.builders.insert("test".to_string(), b);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Those scores cost 36 points, but the budget is 27.\r\n"));
+    }
+
+    #[test]
+    fn build_finish() {
+        let data = test_helper(":test!test@test PRIVMSG test :build test7 test\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut b = CharacterBuilder::new("#test");
+                b.set_method("standard").unwrap();
+                b.set_scores(vec![15, 14, 13, 12, 10, 8]).unwrap();
+                b.race = Some(RaceInfo::find("human", None).unwrap());
+                b.class = Some(Fighter);
+                world.builders.insert("test".to_string(), b);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Your account (test7) has been created.\r\n"));
+        let p = Player::load("test7").unwrap();
+        assert_eq!(p.stats, Stats::new(12, 30, 16, 15, 14, 13, 11, 9));
+        assert_eq!(p.max_health, 12);
+    }
+
+    #[test]
+    fn build_cancel() {
+        let data = test_helper(":test!test@test PRIVMSG test :build cancel\r\n",
+            |world| {
+                world.builders.insert("test".to_string(), CharacterBuilder::new("#test"));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Character creation has been cancelled.\r\n"));
+    }
+
+    #[test]
+    fn login_success() {
+        let data = test_helper(":test!test@test PRIVMSG test :login login test #test\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG test :Login successful.\r\n".to_string();
+        exp.push_str("INVITE test #test\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn login_failed_password_incorrect() {
+        let data = test_helper(":test!test@test PRIVMSG test :login login ztest #test\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Password incorrect.\r\n"));
+    }
+
+    #[test]
+    fn login_failed_game_not_found() {
+        let data = test_helper(":test!test@test PRIVMSG test :login login test #test\r\n", |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Game not found on #test.\r\n"));
+    }
 
     #[test]
     fn login_failed_player_not_found() {
@@ -409,7 +1183,22 @@ mod test {
                 Ok(())
             }
         ).unwrap();
-        assert_eq!(data, format!("PRIVMSG test :Added Test Feat feat.\r\n"));
+        let mut exp = "PRIVMSG test :Requested the Test Feat feat. The DM must approve it first.\r\n".to_string();
+        exp.push_str("PRIVMSG test :login (test) requested the Test Feat feat. Use .approve test Test Feat or .deny test Test Feat in #test.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn add_feat_failed_prerequisite() {
+        let data = test_helper(":test!test@test PRIVMSG test :addfeat grappler\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Grappler requires Strength 13.\r\n"));
     }
 
     #[test]
@@ -426,6 +1215,115 @@ mod test {
         assert_eq!(data, format!("PRIVMSG test :You must be logged in to add a feat.\r\n"));
     }
 
+    #[test]
+    fn remove_feat_success() {
+        let data = test_helper(":test!test@test PRIVMSG test :removefeat mobile\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                try!(p.add_feat("Mobile"));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Removed Mobile feat.\r\n"));
+    }
+
+    #[test]
+    fn remove_feat_failed_missing() {
+        let data = test_helper(":test!test@test PRIVMSG test :removefeat Mobile\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :test doesn't have Mobile.\r\n"));
+    }
+
+    #[test]
+    fn feat_info_success() {
+        let data = test_helper(":test!test@test PRIVMSG test :featinfo fast movement\r\n", |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Fast Movement (class feature, requires a level 5 Barbarian): Speed increases by 10 feet while not wearing heavy armor.\r\n"));
+    }
+
+    #[test]
+    fn feat_info_failed_unknown() {
+        let data = test_helper(":test!test@test PRIVMSG test :featinfo Test Feat\r\n", |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Test Feat is not a known feat.\r\n"));
+    }
+
+    #[test]
+    fn approve_feat_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.approve test2 resilient\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                try!(world.get_game("#test")).request_feat("test2", "Resilient");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) gained the Resilient feat.\r\n"));
+    }
+
+    #[test]
+    fn deny_feat_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.deny test2 Alert\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                try!(world.get_game("#test")).request_feat("test2", "Alert");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :The request from test2 (test2) for the Alert feat was denied.\r\n"));
+    }
+
+    #[test]
+    fn approve_feat_failed_not_requested() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.approve test2 Alert\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 hasn't requested Alert.\r\n"));
+    }
+
+    #[test]
+    fn approve_feat_failed_keeps_request() {
+        let mut input = ":test!test@test PRIVMSG #test :.approve test2 Grappler\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.deny test2 Grappler\r\n");
+        let data = test_helper(&input,
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                try!(world.get_game("#test")).request_feat("test2", "Grappler");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :Grappler requires Strength 13.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :The request from test2 (test2) for the Grappler feat was denied.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn approve_feat_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.approve test2 Alert\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
     #[test]
     fn save_success() {
         let data = test_helper(":test!test@test PRIVMSG test :save\r\n",
@@ -485,6 +1383,49 @@ mod test {
         assert_eq!(data, format!("PRIVMSG test :test (test): Position(0, 0)\r\n"));
     }
 
+    #[test]
+    fn lookup_query_success_defenses() {
+        let data = test_helper(":test!test@test PRIVMSG test :lookup test defenses\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.defenses.add_resistance(Fire);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :test (test): Resistances: fire; Immunities: none; Vulnerabilities: none\r\n"));
+    }
+
+    #[test]
+    fn lookup_query_success_skills() {
+        let data = test_helper(":test!test@test PRIVMSG test :lookup test skills\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.set_skill_proficiency(Perception, Some(Proficient));
+                p.set_skill_proficiency(Stealth, Some(Expertise));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :test (test): Perception, Stealth (expertise)\r\n"));
+    }
+
+    #[test]
+    fn lookup_query_success_skill() {
+        let data = test_helper(":test!test@test PRIVMSG test :lookup test perception\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.set_skill_proficiency(Perception, Some(Proficient));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :test (test): +3 perception\r\n"));
+    }
+
     #[test]
     fn lookup_query_success_stat() {
         let data = test_helper(":test!test@test PRIVMSG test :lookup test health\r\n",
@@ -531,6 +1472,35 @@ mod test {
         assert_eq!(data, exp);
     }
 
+    #[test]
+    fn lookup_channel_success_modified_stat() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.lookup test str\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.modifiers.push(Modifier::new("Bless", None, "str", Add(1), Expiry::Permanent).unwrap());
+                p.modifiers.push(Modifier::new("Belt", Some("item"), "str", Set(21), Expiry::Permanent).unwrap());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test): 22 (base 12, set to 21 by Belt, +1 from Bless) str\r\n"));
+    }
+
+    #[test]
+    fn lookup_channel_success_modifiers() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.lookup test modifiers\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.modifiers.push(Modifier::new("Bless", None, "str", Add(1), Expiry::LongRest).unwrap());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test): Bless: +1 strength until a long rest\r\n"));
+    }
+
     #[test]
     fn lookup_channel_success_feats() {
         let data = test_helper(":test!test@test PRIVMSG #test :.lookup test feats\r\n",
@@ -615,6 +1585,63 @@ mod test {
         assert_eq!(data, format!("PRIVMSG #test :test (test) now has 16 str.\r\n"));
     }
 
+    #[test]
+    fn update_stat_custom_rules() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.update siz 60\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.rules = Some(RuleSystem::call_of_cthulhu());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test) now has 60 siz.\r\n"));
+    }
+
+    #[test]
+    fn update_stat_failed_out_of_range() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.increase str 20\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Strength must be between 1 and 30.\r\n"));
+    }
+
+    #[test]
+    fn update_stat_failed_invalid_stat() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.update wis 12\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.rules = Some(RuleSystem::call_of_cthulhu());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :wis is not a valid stat.\r\n"));
+    }
+
+    #[test]
+    fn lookup_channel_success_custom_rules() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.lookup test\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 50, 60, 12, 12, 70, 12);
+                p.rules = Some(RuleSystem::call_of_cthulhu());
+                try!(p.set_stat_value("siz", 55));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let exp = "PRIVMSG #test :test (test): Health 20, Movement 30, STR 50, CON 12, SIZ 55, DEX 60, APP 0, INT 70, POW 0, EDU 0 Feats []\r\n";
+        assert_eq!(data, exp);
+    }
+
     #[test]
     fn add_update_failed_invalid_stat_value() {
         let data = test_helper(":test!test@test PRIVMSG #test :.update str a\r\n",
@@ -633,4 +1660,260 @@ mod test {
         let data = test_helper(":test!test@test PRIVMSG #test :.update str 16\r\n", |_| { Ok(()) }).unwrap();
         assert_eq!(data, format!("PRIVMSG #test :You're not logged in.\r\n"));
     }
+
+    #[test]
+    fn lookup_query_success_race() {
+        let data = test_helper(":test!test@test PRIVMSG test :lookup test race\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.apply_race(RaceInfo::find("tiefling", None).unwrap());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :test (test): Tiefling (Medium, speed 30, darkvision 60 ft.) Traits: Hellish Resistance, Infernal Legacy\r\n"));
+    }
+
+    #[test]
+    fn lookup_query_success_level() {
+        let data = test_helper(":test!test@test PRIVMSG test :lookup test level\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.class = Some(Fighter);
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :test (test): level 1 Fighter, 0 XP\r\n"));
+    }
+
+    #[test]
+    fn set_class_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.class test2 wizard\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) is now a level 1 Wizard.\r\n"));
+    }
+
+    #[test]
+    fn set_class_failed_invalid_class() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.class test2 jester\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :jester is not a valid class.\r\n"));
+    }
+
+    #[test]
+    fn award_xp_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.xp test2 100\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 (test2) gained 100 XP and now has 100 XP.\r\n"));
+    }
+
+    #[test]
+    fn award_xp_party_level_up() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.xp party 300\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 14, 12, 12, 12);
+                p.class = Some(Fighter);
+                world.add_user("test2", "#test", p);
+                let q = Player::create_test("test3", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test3", "#test", q);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test2 (test2) gained 300 XP and now has 300 XP.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test2 (test2) has reached level 2 and gained 8 hit points (28 max).\r\n");
+        exp.push_str("PRIVMSG #test :test3 (test3) gained 300 XP and now has 300 XP.\r\n");
+        exp.push_str("PRIVMSG #test :test3 (test3) has reached level 2 and gained 6 hit points (26 max).\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn award_xp_failed_other_game() {
+        let mut input = ":test!test@test PRIVMSG #test :.xp test2 100\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.class test2 wizard\r\n");
+        let data = test_helper(&input,
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                world.add_game("Dungeons and Tests 2", "test3", "#test2");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test2", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test2 is not logged in.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test2 is not logged in.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn award_xp_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.xp party 300\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn rest_long_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rest long\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.class = Some(Warlock);
+                p.level = 2;
+                p.hit_dice_used = 2;
+                p.stats.health = 8;
+                p.spend_slot(1).unwrap();
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :The party takes a long rest.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test2 (test2) regained 12 health (20/20) and 1 hit dice, with 1/2 hit dice left. Spell slots restored.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn rest_long_restores_monsters() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rest long\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                world.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                let mut m = Monster::create("Dragon", 20, 30, 12, 12, 12, 12, 12, 12);
+                let mut breath = Action::attack("Breath", 4, 15, Dice::new(4, 6, 0), Fire);
+                breath.recharge = Some(5);
+                breath.charged = false;
+                m.add_action(breath);
+                m.add_action(Action::attack("Bite", 4, 5, Dice::new(1, 6, 2), Fire));
+                world.add_monster(m, "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :The party takes a long rest.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test2 (test2) regained 0 health (20/20), with 1/1 hit dice left.\r\n");
+        exp.push_str("PRIVMSG #test :Dragon (@0) can use Breath again.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn rest_short_full_health() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rest short 2\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                p.class = Some(Warlock);
+                p.spend_slot(1).unwrap();
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :The party takes a short rest.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test2 (test2) regained 0 health (20/20), with 1/1 hit dice left. Spell slots restored.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn macro_set_success() {
+        let data = test_helper(":test!test@test PRIVMSG test :macro set Sneak 1d20+dex+prof ; 3d6\r\n",
+            |world| {
+                world.add_user("test", "#test", Player::create_test("test", "test", 20, 30, 12, 14, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        let exp = "PRIVMSG test :Saved macro sneak: 1d20+dex+prof; 3d6. Use it with .sneak or .m sneak in your game.\r\n";
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn macro_set_failed_invalid_roll() {
+        let data = test_helper(":test!test@test PRIVMSG test :macro set sneak 1d20+luck\r\n",
+            |world| {
+                world.add_user("test", "#test", Player::create_test("test", "test", 20, 30, 12, 14, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :luck is not a die, number, stat, skill or prof.\r\n"));
+    }
+
+    #[test]
+    fn macro_set_failed_command_name() {
+        let data = test_helper(":test!test@test PRIVMSG test :macro set Damage 2d6\r\n",
+            |world| {
+                world.add_user("test", "#test", Player::create_test("test", "test", 20, 30, 12, 14, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :.damage is already a command, so it can't be a macro.\r\n"));
+    }
+
+    #[test]
+    fn macro_set_failed_not_logged_in() {
+        let data = test_helper(":test!test@test PRIVMSG test :macro set sneak 1d20+dex\r\n", |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :You must be logged in to use macros.\r\n"));
+    }
+
+    #[test]
+    fn macro_list_and_remove() {
+        let mut input = ":test!test@test PRIVMSG test :macro list\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG test :macro remove sneak\r\n");
+        input.push_str(":test!test@test PRIVMSG test :macro remove sneak\r\n");
+        input.push_str(":test!test@test PRIVMSG test :macro list\r\n");
+        let data = test_helper(&input,
+            |world| {
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 14, 12, 12, 12, 12);
+                p.set_macro(Macro::new("sneak", "1d20+dex+prof;3d6").unwrap());
+                p.set_macro(Macro::new("stab", "d20+str").unwrap());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG test :Macros: sneak (1d20+dex+prof; 3d6), stab (d20+str).\r\n".to_string();
+        exp.push_str("PRIVMSG test :Removed macro sneak.\r\n");
+        exp.push_str("PRIVMSG test :test has no macro named sneak.\r\n");
+        exp.push_str("PRIVMSG test :Macros: stab (d20+str).\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn use_macro_success() {
+        let mut input = ":test!test@test PRIVMSG #test :.sneak\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.m SNEAK\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.stab\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.m stab\r\n");
+        let data = test_helper_scripted(&input, vec![14, 2, 4, 6, 1, 1, 1, 1],
+            |world| {
+                world.add_game("Test", "test2", "#test");
+                let mut p = Player::create_test("test", "test", 20, 30, 12, 14, 12, 12, 12, 12);
+                p.set_macro(Macro::new("sneak", "1d20+dex+prof;3d6").unwrap());
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test (test) used sneak: 1d20+dex+prof = 18 ([14]+2+2); 3d6 = 12 ([2, 4, 6]).\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test (test) used sneak: 1d20+dex+prof = 5 ([1]+2+2); 3d6 = 3 ([1, 1, 1]).\r\n");
+        exp.push_str("PRIVMSG #test :test has no macro named stab.\r\n");
+        assert_eq!(data, exp);
+    }
 }
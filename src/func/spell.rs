@@ -0,0 +1,631 @@
+use std::ascii::AsciiExt;
+use data::{BotResult, Entity, as_io};
+use data::BotError::{InvalidInput, Propagated};
+use data::dice::Dice;
+use data::journal::{EventKind, log_event};
+use data::modifier::{Expiry, Modifier, Operation};
+use data::spell::{Concentration, Spell, SpellEffect};
+use data::world::World;
+use func::Functionality;
+use func::utils::{find_target, incorrect_format};
+use irc::client::prelude::*;
+
+pub struct Learn<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    spell: Spell,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Learn<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "name level [attack=dice/type|save=stat/dice/type[/half]] [upcast=dice] [modify=stat/+n|-n|=n|xn] [concentration]";
+        if args.len() < 3 || args.len() > 7 {
+            return Err(incorrect_format(chan, ".learn", format));
+        }
+        let level = match args[2].parse::<u8>() {
+            Ok(n) if n <= 9 => n,
+            _ => return Err(Propagated(format!("{}", chan), format!("{} is not a valid spell level.", args[2]))),
+        };
+        let mut spell = Spell::new(args[1], level, SpellEffect::Utility);
+        // Whether it starts out prepared is settled once the DM approves it.
+        spell.prepared = true;
+        for opt in args[3..].iter() {
+            if opt.eq_ignore_ascii_case("concentration") {
+                spell.concentration = true;
+            } else if opt.to_ascii_lowercase().starts_with("upcast=") {
+                match Dice::parse(&opt[7..]) {
+                    Some(dice) => spell.upcast = Some(dice),
+                    None => return Err(incorrect_format(chan, ".learn", format)),
+                }
+            } else if opt.to_ascii_lowercase().starts_with("modify=") {
+                let parts: Vec<_> = opt[7..].splitn(2, '/').collect();
+                let modifier = parts.get(1).and_then(|change| Operation::parse(change)).and_then(|op| {
+                    Modifier::new(args[1], Some(args[1]), parts[0], op, Expiry::LongRest)
+                });
+                match modifier {
+                    Some(modifier) => spell.modifier = Some(modifier),
+                    None => return Err(incorrect_format(chan, ".learn", format)),
+                }
+            } else {
+                match SpellEffect::parse(opt) {
+                    Some(effect) => spell.effect = effect,
+                    None => return Err(incorrect_format(chan, ".learn", format)),
+                }
+            }
+        }
+        if let (Some(upcast), Some(damage)) = (spell.upcast, spell.effect.damage()) {
+            if upcast.sides != damage.sides {
+                return Err(Propagated(
+                    format!("{}", chan), format!("Upcast dice must be d{}s like the spell's damage.", damage.sides)
+                ));
+            }
+        }
+        Ok(Box::new(Learn { bot: bot, user: user, chan: chan, world: world, spell: spell }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Learn<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_user(self.user);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        }
+        let username = {
+            let p = try!(res);
+            if let Err(InvalidInput(msg)) = p.check_spell(&self.spell) {
+                return Err(Propagated(format!("{}", self.chan), msg));
+            }
+            p.username.clone()
+        };
+        try!(self.world.get_game(self.chan)).request_spell(self.user, self.spell.clone());
+        let s = format!("{} ({}) asked to learn {}. The DM must approve it with .approve {} {} or .deny {} {}.",
+                        username, self.user, self.spell, self.user, self.spell.name, self.user, self.spell.name);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Prepare<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    name: &'a str,
+    prepare: bool,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Prepare<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World, prepare: bool) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() != 2 {
+            return Err(incorrect_format(chan, if prepare { ".prepare" } else { ".unprepare" }, "spell"));
+        }
+        Ok(Box::new(Prepare { bot: bot, user: user, chan: chan, world: world, name: args[1], prepare: prepare }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Prepare<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_user(self.user);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+        }
+        let p = try!(res);
+        if let Err(e) = p.prepare_spell(self.name, self.prepare) {
+            return Err(Propagated(format!("{}", self.chan), format!("{}", e)));
+        }
+        let s = format!("{} ({}) {} {}.", p.username, self.user,
+                        if self.prepare { "prepared" } else { "unprepared" }, p.spell(self.name).unwrap().name);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Cast<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    name: &'a str,
+    level: Option<u8>,
+    targets: Vec<&'a str>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Cast<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() < 2 {
+            return Err(incorrect_format(chan, ".cast", "spell [level] [target ...]"));
+        }
+        let level = args.get(2).and_then(|s| s.parse::<u8>().ok());
+        let targets = args[if level.is_some() { 3 } else { 2 }..].to_vec();
+        Ok(Box::new(Cast {
+            bot: bot, user: user, chan: chan, world: world, name: args[1], level: level, targets: targets,
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Cast<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        // Every target is checked up front so that a typo doesn't cost a spell slot.
+        for target_str in self.targets.iter() {
+            try!(find_target(target_str, self.chan, self.chan, self.world));
+        }
+        let rules = self.world.rules(self.chan);
+        let (caster, header, replaced, spell, slot_level, bonus, dc, value) = {
+            let res = self.world.get_user(self.user);
+            if res.is_err() {
+                return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+            }
+            let p = try!(res);
+            let spell = match p.spell(self.name) {
+                Some(spell) => spell.clone(),
+                None => return Err(Propagated(
+                    format!("{}", self.chan), format!("{} doesn't know {}.", p.username, self.name)
+                )),
+            };
+            if !spell.prepared {
+                return Err(Propagated(format!("{}", self.chan), format!("{} is not prepared.", spell.name)));
+            } else if spell.effect != SpellEffect::Utility && self.targets.is_empty() {
+                return Err(Propagated(format!("{}", self.chan), format!("{} needs a target.", spell.name)));
+            }
+            let mut header = format!("{} ({}) cast {}", p.username, self.user, spell.name);
+            let slot_level = if spell.is_cantrip() {
+                if self.level.is_some() {
+                    return Err(Propagated(format!("{}", self.chan), format!("Cantrips don't use spell slots.")));
+                }
+                0
+            } else {
+                let slot_level = self.level.unwrap_or(spell.level);
+                if slot_level < spell.level {
+                    return Err(Propagated(format!("{}", self.chan), format!(
+                        "{} needs a slot of level {} or higher.", spell.name, spell.level
+                    )));
+                }
+                let chan = self.chan;
+                try!(p.spend_slot(slot_level).map_err(|e| Propagated(format!("{}", chan), format!("{}", e))));
+                header.push_str(&format!(" with a level {} slot ({} left)", slot_level, p.slots_remaining(slot_level)));
+                slot_level
+            };
+            // Starting to concentrate on a new spell ends whatever was being concentrated on before.
+            let replaced = if spell.concentration {
+                p.set_concentration(Some(Concentration::new(&spell.name, &self.targets)))
+            } else {
+                None
+            };
+            // Roll-under systems have casters roll under their spellcasting stat to hit.
+            let value = p.class.and_then(|c| c.spellcasting_ability()).map_or(0, |a| p.stats().get_for(a));
+            (p.username.clone(), header, replaced, spell, slot_level, p.spell_attack_bonus(), p.spell_save_dc(), value)
+        };
+        let ended = match replaced {
+            Some(ref old) => {
+                self.world.end_concentration(old, self.chan);
+                format!(" {} stopped concentrating on {}{}.", caster, old.spell, old.ending())
+            },
+            None => String::new(),
+        };
+        // Casting again replaces the modifier an earlier casting left rather than stacking on it.
+        if let Some(ref modifier) = spell.modifier {
+            for target_str in self.targets.iter() {
+                let target = try!(find_target(target_str, self.chan, self.chan, self.world));
+                target.modifiers_mut().retain(|m| !m.is_from(&spell.name));
+                target.modifiers_mut().push(modifier.clone());
+            }
+        }
+        let damage = spell.damage_at(slot_level);
+        match spell.effect {
+            SpellEffect::Utility => {
+                let on = if self.targets.is_empty() { String::new() } else { format!(" on {}", self.targets.join(", ")) };
+                as_io(self.bot.send_privmsg(self.chan, &format!("{}{}.{}", header, on, ended)))
+            },
+            SpellEffect::Attack { damage_type, .. } => {
+                try!(as_io(self.bot.send_privmsg(self.chan, &format!("{}.{}", header, ended))));
+                for target_str in self.targets.clone().into_iter() {
+                    let (s, lost) = {
+                        let target = try!(find_target(target_str, self.chan, self.chan, self.world));
+                        let ac = target.armor_class();
+                        let outcome = rules.test(&caster, &format!("{} attack", spell.name), value, bonus, ac);
+                        let prefix = if outcome.roll_under {
+                            format!("{} ({}): rolled {}", target.identifier(), target_str, outcome)
+                        } else {
+                            format!("{} ({}): {} to hit vs AC {}", target.identifier(), target_str, outcome.total, ac)
+                    };
+                    let critical = rules.is_critical(outcome.natural);
+                    if rules.is_fumble(outcome.natural) || (!critical && !outcome.success) {
+                        (format!("{}, miss.", prefix), None)
+                    } else {
+                        let hit = if critical { "critical hit" } else { "hit" };
+                        let res = target.damage(damage.unwrap().roll_with_crit(critical), Some(damage_type));
+                        log_event(EventKind::Damage, &res.describe(target.identifier(), Some(damage_type),
+                                                                   target.stats().health));
+                        let s = if res.conscious {
+                            format!("{}, {} for {} {} damage{} ({} health remaining).", prefix, hit, res.dealt,
+                                    damage_type, res.describe_adjustment(), target.stats().health)
+                        } else {
+                            format!("{}, {} for {} {} damage{} and has fallen unconscious.", prefix, hit,
+                                    res.dealt, damage_type, res.describe_adjustment())
+                        };
+                        (format!("{}{}", s, res.describe_concentration(target.identifier())), res.lost_concentration())
+                    }
+                    };
+                    if let Some(ref effect) = lost {
+                        self.world.end_concentration(effect, self.chan);
+                    }
+                    try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+                }
+                Ok(())
+            },
+            SpellEffect::Save { stat, damage_type, half, .. } => {
+                let amount = damage.unwrap().roll();
+                try!(as_io(self.bot.send_privmsg(self.chan, &format!(
+                    "{}: {:?} save vs DC {} for {} {} damage{}.{}", header, stat, dc, amount, damage_type,
+                    if half { " (half on success)" } else { "" }, ended
+                ))));
+                for target_str in self.targets.clone().into_iter() {
+                    let mut lost = None;
+                    let s = {
+                        let target = try!(find_target(target_str, self.chan, self.chan, self.world));
+                        let (save, value) = (target.save_bonus(stat), target.stats().get_for(stat));
+                        let outcome = rules.test(target.identifier(), &format!("{:?} save", stat), value, save, dc);
+                        let passed = outcome.success;
+                        let mut s = format!("{} ({}) rolled {}: {}", target.identifier(), target_str, outcome,
+                                            if passed { "success" } else { "failure" });
+                        let dealt = if !passed { amount } else if half { amount / 2 } else { 0 };
+                        let mut concentration = String::new();
+                        if dealt > 0 {
+                            let res = target.damage(dealt, Some(damage_type));
+                            log_event(EventKind::Damage, &res.describe(target.identifier(), Some(damage_type),
+                                                                       target.stats().health));
+                            concentration = res.describe_concentration(target.identifier());
+                            lost = res.lost_concentration();
+                            if res.conscious {
+                                s.push_str(&format!(", took {} {} damage{} and has {} health remaining", res.dealt,
+                                                    damage_type, res.describe_adjustment(), target.stats().health));
+                            } else {
+                                s.push_str(&format!(", took {} {} damage{} and has fallen unconscious", res.dealt,
+                                                    damage_type, res.describe_adjustment()));
+                            }
+                        }
+                        s.push_str(".");
+                        s.push_str(&concentration);
+                        s
+                    };
+                    if let Some(ref effect) = lost {
+                        self.world.end_concentration(effect, self.chan);
+                    }
+                    try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+pub struct Concentrate<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    end: bool,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Concentrate<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let end = match args.get(1) {
+            None => false,
+            Some(arg) if args.len() == 2 && arg.eq_ignore_ascii_case("end") => true,
+            Some(_) => return Err(incorrect_format(chan, ".concentration", "[end]")),
+        };
+        Ok(Box::new(Concentrate { bot: bot, user: user, chan: chan, world: world, end: end }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Concentrate<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let (s, effect) = {
+            let res = self.world.get_user(self.user);
+            if res.is_err() {
+                return Err(Propagated(format!("{}", self.chan), format!("You're not logged in.")));
+            }
+            let p = try!(res);
+            let effect = if self.end { p.set_concentration(None) } else { p.concentration().cloned() };
+            let s = match effect {
+                None => format!("{} ({}) is not concentrating on anything.", p.username, self.user),
+                Some(ref effect) if self.end => format!("{} ({}) stopped concentrating on {}{}.", p.username,
+                                                        self.user, effect.spell, effect.ending()),
+                Some(ref effect) if effect.targets.is_empty() => {
+                    format!("{} ({}) is concentrating on {}.", p.username, self.user, effect.spell)
+                },
+                Some(ref effect) => format!("{} ({}) is concentrating on {} for {}.", p.username, self.user,
+                                            effect.spell, effect.targets.join(", ")),
+            };
+            (s, effect)
+        };
+        if let (true, Some(ref effect)) = (self.end, effect) {
+            self.world.end_concentration(effect, self.chan);
+        }
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct ListSpells<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    target_str: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ListSpells<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 2 {
+            return Err(incorrect_format(chan, ".spells", "[player]"));
+        }
+        Ok(Box::new(ListSpells {
+            bot: bot, chan: chan, world: world,
+            target_str: if args.len() == 2 { args[1] } else { user },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ListSpells<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let res = self.world.get_user(self.target_str);
+        if res.is_err() {
+            return Err(Propagated(format!("{}", self.chan), format!("{} is not logged in.", self.target_str)));
+        }
+        let p = try!(res);
+        let spells: Vec<_> = p.spells.iter().map(|s| format!("{}", s)).collect();
+        let slots: Vec<_> = p.spell_slots().iter().enumerate().filter(|&(_, max)| *max > 0)
+            .map(|(i, max)| format!("level {} {}/{}", i + 1, p.slots_remaining(i as u8 + 1), max)).collect();
+        let s = format!("{} ({}): {}. Slots: {}.", p.username, self.target_str,
+                        if spells.is_empty() { "no spells".to_string() } else { spells.join(", ") },
+                        if slots.is_empty() { "none".to_string() } else { slots.join(", ") });
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use data::class::CharacterClass::{Fighter, Wizard};
+    use data::dice::Dice;
+    use data::monster::Monster;
+    use data::player::Player;
+    use data::modifier::{Expiry, Modifier};
+    use data::modifier::Operation::Add;
+    use data::spell::{Concentration, Spell, SpellEffect};
+    use func::test::{test_helper, test_helper_scripted};
+
+    fn wizard() -> Player {
+        let mut p = Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 16, 12);
+        p.class = Some(Wizard);
+        p
+    }
+
+    #[test]
+    fn learn_success() {
+        let mut input = ":test2!test@test PRIVMSG #test :.learn Fireball 3 save=dex/8d6/fire/half upcast=1d6\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.approve test2 fireball\r\n");
+        let data = test_helper(&input,
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let mut p = wizard();
+                p.level = 5;
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test (test2) asked to learn Fireball (level 3, Dexterity save for 8d6 fire, half on success, +1d6 per slot level). The DM must approve it with .approve test2 Fireball or .deny test2 Fireball.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test (test2) learned Fireball (level 3, Dexterity save for 8d6 fire, half on success, +1d6 per slot level, unprepared).\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn learn_denied() {
+        let mut input = ":test2!test@test PRIVMSG #test :.learn Sleep 1\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.deny test2 Sleep\r\n");
+        input.push_str(":test2!test@test PRIVMSG #test :.prepare sleep\r\n");
+        let data = test_helper(&input,
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                world.add_user("test2", "#test", wizard());
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test (test2) asked to learn Sleep (level 1). The DM must approve it with .approve test2 Sleep or .deny test2 Sleep.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :The request from test (test2) to learn Sleep was denied.\r\n");
+        exp.push_str("PRIVMSG #test :test doesn't know sleep.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn learn_failed_not_caster() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.learn Light 0\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                world.add_user("test", "#test", Player::create_test("test", "test", 20, 30, 12, 12, 12, 12, 16, 12));
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test can't cast spells.\r\n"));
+    }
+
+    #[test]
+    fn learn_failed_spell_level() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.learn Fireball 3\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                world.add_user("test", "#test", wizard());
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test has no level 3 spell slots.\r\n"));
+    }
+
+    #[test]
+    fn learn_failed_upcast_sides() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.learn Fireball 3 save=dex/8d6/fire/half upcast=1d8\r\n",
+            |world| {
+                world.add_user("test", "#test", wizard());
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Upcast dice must be d6s like the spell's damage.\r\n"));
+    }
+
+    #[test]
+    fn learn_modifier() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.learn Bless 1 modify=str/+1 concentration\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test2", "#test");
+                world.add_user("test", "#test", wizard());
+                Ok(())
+            }
+        ).unwrap();
+        let exp = "PRIVMSG #test :test (test) asked to learn Bless (level 1, +1 strength, concentration). The DM must approve it with .approve test Bless or .deny test Bless.\r\n";
+        assert_eq!(data, format!("{}", exp));
+    }
+
+    #[test]
+    fn prepare_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.prepare sleep\r\n",
+            |world| {
+                let mut p = wizard();
+                p.learn_spell(Spell::new("Sleep", 1, SpellEffect::Utility));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test) prepared Sleep.\r\n"));
+    }
+
+    #[test]
+    fn cast_utility_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.cast shield\r\n",
+            |world| {
+                let mut p = wizard();
+                p.spells.push(Spell::new("Shield", 1, SpellEffect::Utility));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test) cast Shield with a level 1 slot (1 left).\r\n"));
+    }
+
+    #[test]
+    fn cast_failed_no_slots() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.cast shield\r\n",
+            |world| {
+                let mut p = wizard();
+                p.class = Some(Fighter);
+                p.spells.push(Spell::new("Shield", 1, SpellEffect::Utility));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test has no level 1 spell slots left.\r\n"));
+    }
+
+    #[test]
+    fn cast_failed_unprepared() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.cast sleep\r\n",
+            |world| {
+                let mut p = wizard();
+                p.learn_spell(Spell::new("Sleep", 1, SpellEffect::Utility));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :Sleep is not prepared.\r\n"));
+    }
+
+    #[test]
+    fn cast_save_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.cast \"Burning Hands\" 2 @0\r\n",
+            |world| {
+                world.add_game("Test", "test2", "#test");
+                let mut p = wizard();
+                p.level = 3;
+                let mut spell = Spell::new("Burning Hands", 1, SpellEffect::parse("save=dex/3d6/fire/half").unwrap());
+                spell.upcast = Some(Dice::new(1, 6, 0));
+                p.spells.push(spell);
+                world.add_user("test", "#test", p);
+                world.add_monster(Monster::create("Test", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let exp = "PRIVMSG #test :test (test) cast Burning Hands with a level 2 slot (1 left): Dexterity save vs DC 13 for ";
+        assert_eq!(data[..exp.len()].to_owned(), format!("{}", exp));
+        assert!(data.contains("PRIVMSG #test :Test (@0) rolled "));
+    }
+
+    #[test]
+    fn cast_concentration_replaces() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.cast bless test2\r\n",
+            |world| {
+                let mut p = wizard();
+                let mut bless = Spell::new("Bless", 1, SpellEffect::Utility);
+                bless.concentration = true;
+                p.spells.push(bless);
+                p.concentration = Some(Concentration::new("Shield of Faith", &["test3"]));
+                world.add_user("test", "#test", p);
+                world.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        let exp = "PRIVMSG #test :test (test) cast Bless with a level 1 slot (1 left) on test2. test stopped concentrating on Shield of Faith, ending it for test3.\r\n";
+        assert_eq!(data, format!("{}", exp));
+    }
+
+    #[test]
+    fn cast_modifier_lost_with_concentration() {
+        let mut input = ":test!test@test PRIVMSG #test :.cast bless test2\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.lookup test2 modifiers\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.damage test 5\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.lookup test2 modifiers\r\n");
+        let data = test_helper_scripted(&input, vec![1],
+            |world| {
+                world.add_game("Test", "test3", "#test");
+                let mut p = wizard();
+                let mut bless = Spell::new("Bless", 1, SpellEffect::Utility);
+                bless.concentration = true;
+                bless.modifier = Modifier::new("Bless", Some("Bless"), "str", Add(1), Expiry::LongRest);
+                p.spells.push(bless);
+                world.add_user("test", "#test", p);
+                world.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+                Ok(())
+            }
+        ).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG #test :test (test) cast Bless with a level 1 slot (1 left) on test2.");
+        assert_eq!(lines[1], "PRIVMSG #test :test2 (test2): Bless (Bless): +1 strength until a long rest");
+        assert!(lines[2].ends_with("test lost concentration on Bless (2 vs DC 10), ending it for test2."));
+        assert_eq!(lines[3], "PRIVMSG #test :test2 (test2): none");
+    }
+
+    #[test]
+    fn concentration_end() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.concentration end\r\n:test!test@test PRIVMSG #test :.concentration\r\n",
+            |world| {
+                let mut p = wizard();
+                p.concentration = Some(Concentration::new("Bless", &["test2"]));
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :test (test) stopped concentrating on Bless, ending it for test2.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test (test) is not concentrating on anything.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn list_spells_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.spells\r\n",
+            |world| {
+                let mut p = wizard();
+                p.spells.push(Spell::new("Shield", 1, SpellEffect::Utility));
+                p.spend_slot(1).unwrap();
+                world.add_user("test", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test (test): Shield (level 1). Slots: level 1 1/2.\r\n"));
+    }
+}
@@ -1,12 +1,20 @@
+use std::ascii::AsciiExt;
+use std::io::ErrorKind;
 use data::{BotResult, as_io};
-use data::BotError::Propagated;
+use data::BotError::{InvalidInput, Io, Propagated};
 use data::game::Game;
-use data::utils::join_from;
+use data::history::MAX_HISTORY;
+use data::journal::{EventKind, Format, log_event, use_journal};
+use data::rules::RuleSystem;
+use data::utils::{clock_time, date, join_from, now};
 use data::world::World;
 use func::Functionality;
-use func::utils::incorrect_format;
+use func::utils::{incorrect_format, permissions_test};
 use irc::client::prelude::*;
 
+const MAX_ROLLS_LISTED: usize = 20;
+const MAX_SNAPSHOT_NAME: usize = 20;
+
 pub struct Create<'a, T: IrcRead, U: IrcWrite> {
     bot: &'a ServerExt<'a, T, U>,
     user: &'a str,
@@ -29,16 +37,513 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for Create<'a, T, U> {
                 format!("{}", self.user), format!("A campaign already exists on {}.", self.chan)
             ));
         }
+        // A campaign that was saved before is picked back up, but only by someone who ran it. Titles
+        // that differ only in punctuation would share its files, so they're turned away.
+        let (game, verb) = match Game::load(&self.title) {
+            Ok(ref game) if !game.name.eq_ignore_ascii_case(&self.title) => return Err(Propagated(
+                format!("{}", self.user),
+                format!("{} is too close to the name of the campaign {}. Please choose another.", self.title, game.name)
+            )),
+            Ok(ref game) if !game.is_dm(self.user) => return Err(Propagated(
+                format!("{}", self.user),
+                format!("A campaign named {} already exists, and only its DMs can resume it.", game.name)
+            )),
+            Ok(game) => (game, "resumed"),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => (Game::new(&self.title, self.user), "created"),
+            Err(e) => return Err(Io(e)),
+        };
+        try!(as_io(game.save()));
         try!(as_io(self.bot.send_join(self.chan)));
-        try!(as_io(self.bot.send_topic(self.chan, &self.title)));
+        try!(as_io(self.bot.send_topic(self.chan, &game.name)));
         try!(as_io(self.bot.send_mode(self.chan, "+i", "")));
-        self.world.add_game(&self.title, self.user, self.chan);
-        let s = format!("Campaign created named {}.", self.title);
+        let s = format!("Campaign {} named {}.", verb, game.name);
+        self.world.insert_game(game, self.chan);
         try!(as_io(self.bot.send_privmsg(self.user, &s)));
         as_io(self.bot.send_invite(self.user, self.chan))
     }
 }
 
+pub struct SetRules<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    rules: Option<RuleSystem>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> SetRules<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 2 {
+            return Err(incorrect_format(chan, ".rules", "[system]"));
+        } else if args.len() == 2 {
+            try!(permissions_test(user, chan, world));
+        } else if !world.game_exists(chan) {
+            return Err(Propagated(format!("{}", chan), format!("There is no game in {}.", chan)));
+        }
+        Ok(Box::new(SetRules {
+            bot: bot,
+            chan: chan,
+            world: world,
+            rules: match args.get(1).map(|name| RuleSystem::find(name)) {
+                None => None,
+                Some(Ok(rules)) => Some(rules),
+                Some(Err(InvalidInput(msg))) => return Err(Propagated(format!("{}", chan), msg)),
+                Some(Err(e)) => return Err(e),
+            },
+        }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for SetRules<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        // Characters are made for a particular system, so it can't change under logged in players.
+        if self.rules.is_some() && !self.world.party(self.chan).is_empty() {
+            return Err(Propagated(
+                format!("{}", self.chan), format!("The rules can't be changed while players are logged in.")
+            ));
+        }
+        let game = try!(self.world.get_game(self.chan));
+        if let Some(rules) = self.rules.take() {
+            log_event(EventKind::Dm, &format!("The rules were changed to {}.", rules.name));
+            game.rules = rules;
+            try!(as_io(game.save()));
+        }
+        let s = format!("{} uses {}", game.name, game.rules);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct SetSeed<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    seed: Option<Option<usize>>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> SetSeed<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 2 {
+            return Err(incorrect_format(chan, ".seed", "[seed|random]"));
+        } else if args.len() == 2 {
+            try!(permissions_test(user, chan, world));
+        } else if !world.game_exists(chan) {
+            return Err(Propagated(format!("{}", chan), format!("There is no game in {}.", chan)));
+        }
+        let seed = match args.get(1) {
+            None => None,
+            Some(&"random") => Some(None),
+            Some(s) => match s.parse::<usize>() {
+                Ok(seed) => Some(Some(seed)),
+                Err(_) => return Err(Propagated(
+                    format!("{}", chan), format!("{} is not a valid seed. Seeds must be non-negative integers.", s)
+                )),
+            },
+        };
+        Ok(Box::new(SetSeed { bot: bot, chan: chan, world: world, seed: seed }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for SetSeed<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let game = try!(self.world.get_game(self.chan));
+        // The seed stays secret until the session it was committed to ends.
+        if let Some(ref session) = game.session {
+            if self.seed.is_some() {
+                return Err(Propagated(format!("{}", self.chan), format!("The seed can't be changed during a session.")));
+            }
+            let s = format!("{} rolls are committed to {} until the session ends.", game.name, session.commitment);
+            return as_io(self.bot.send_privmsg(self.chan, &s));
+        }
+        if let Some(seed) = self.seed {
+            log_event(EventKind::Dm, &match seed {
+                Some(seed) => format!("The seed was set to {}.", seed),
+                None => format!("The dice were made random."),
+            });
+            game.set_seed(seed);
+        }
+        let s = match game.seed() {
+            Some(seed) => format!("{} rolls with seed {}.", game.name, seed),
+            None => format!("{} rolls randomly.", game.name),
+        };
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct ManageSession<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    action: Option<&'a str>,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ManageSession<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let action = match args.get(1).map(|s| s.to_ascii_lowercase()) {
+            _ if args.len() > 2 => return Err(incorrect_format(chan, ".session", "[start|end]")),
+            Some(ref s) if s == "start" => Some("start"),
+            Some(ref s) if s == "end" => Some("end"),
+            Some(_) => return Err(incorrect_format(chan, ".session", "[start|end]")),
+            None => None,
+        };
+        if action.is_some() {
+            try!(permissions_test(user, chan, world));
+        } else if !world.game_exists(chan) {
+            return Err(Propagated(format!("{}", chan), format!("There is no game in {}.", chan)));
+        }
+        Ok(Box::new(ManageSession { bot: bot, user: user, chan: chan, world: world, action: action }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ManageSession<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let (chan, user) = (self.chan, self.user);
+        let s = {
+            let game = try!(self.world.get_game(chan));
+            match self.action {
+                Some("start") => {
+                    let s = {
+                        let commitment = try!(game.start_session()
+                            .map_err(|e| Propagated(format!("{}", chan), format!("{}", e))));
+                        format!("Session started. The dice are committed to SHA-512 {}.", commitment)
+                    };
+                    // Everything from here on goes in the new session's journal.
+                    use_journal(Some(game.journal.clone()), user);
+                    log_event(EventKind::Dm, &s);
+                    s
+                },
+                Some(_) => {
+                    if let Some(ref session) = game.session {
+                        log_event(EventKind::Dm, &format!("Session ended. The seed was {}.", session.seed));
+                    }
+                    let session = try!(game.end_session()
+                        .map_err(|e| Propagated(format!("{}", chan), format!("{}", e))));
+                    use_journal(Some(game.journal.clone()), user);
+                    let rolls = game.dice.borrow().recorded - session.first_roll;
+                    format!("Session ended after {} roll{}. The seed was {}, committed as SHA-512 {}.", rolls,
+                            if rolls == 1 { "" } else { "s" }, session.seed, session.commitment)
+                },
+                None => match game.session {
+                    Some(ref session) => format!(
+                        "A session is running in {}. The dice are committed to SHA-512 {}.", game.name, session.commitment
+                    ),
+                    None => format!("There is no session running in {}.", game.name),
+                },
+            }
+        };
+        try!(as_io(self.bot.send_privmsg(chan, &s)));
+        // Sessions are a natural point to go back to if things go wrong. The session has started
+        // either way, so failing to save one doesn't stop the commitment being announced.
+        if self.action == Some("start") {
+            let time = now();
+            let name = format!("session-{}-{}", date(time), clock_time(time).replace(":", ""));
+            if let Err(e) = self.world.take_snapshot(chan, &name, true) {
+                let s = format!("The session's snapshot couldn't be saved: {}", e);
+                try!(as_io(self.bot.send_privmsg(chan, &s)));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct ManageSnapshot<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    action: &'a str,
+    name: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ManageSnapshot<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "create name | restore name | list";
+        let action = match args.get(1).map(|s| s.to_ascii_lowercase()) {
+            Some(ref s) if s == "create" && args.len() == 3 => "create",
+            Some(ref s) if s == "restore" && args.len() == 3 => "restore",
+            Some(ref s) if s == "list" && args.len() == 2 => "list",
+            _ => return Err(incorrect_format(chan, ".snapshot", format)),
+        };
+        if action != "list" {
+            try!(permissions_test(user, chan, world));
+        } else if !world.game_exists(chan) {
+            return Err(Propagated(format!("{}", chan), format!("There is no game in {}.", chan)));
+        }
+        let name = args.get(2).cloned().unwrap_or("");
+        if action == "create" && (name.len() > MAX_SNAPSHOT_NAME
+                                  || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')) {
+            return Err(Propagated(format!("{}", chan), format!(
+                "Snapshot names must be up to {} letters, numbers, dashes or underscores.", MAX_SNAPSHOT_NAME
+            )));
+        }
+        Ok(Box::new(ManageSnapshot { bot: bot, chan: chan, world: world, action: action, name: name }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ManageSnapshot<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let s = match self.action {
+            "create" => {
+                let replaced = try!(self.world.take_snapshot(self.chan, self.name, false));
+                format!("{} snapshot {}.", if replaced { "Updated" } else { "Saved" }, self.name)
+            },
+            "restore" => {
+                let missing = match self.world.restore_snapshot(self.chan, self.name) {
+                    Ok(missing) => missing,
+                    Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", self.chan), msg)),
+                    Err(e) => return Err(e),
+                };
+                let mut s = format!("Restored snapshot {}.", self.name);
+                if !missing.is_empty() {
+                    s.push_str(&format!(" Left as they are since they're no longer logged in: {}.", missing.join(", ")));
+                }
+                s
+            },
+            _ => {
+                let game = try!(self.world.get_game(self.chan));
+                let names: Vec<_> = game.snapshots.iter()
+                    .map(|s| format!("{} ({} {})", s.name, date(s.time), clock_time(s.time))).collect();
+                let s = if names.is_empty() {
+                    format!("{} has no snapshots.", game.name)
+                } else {
+                    format!("Snapshots of {}: {}.", game.name, names.join(", "))
+                };
+                return as_io(self.bot.send_privmsg(self.chan, &s));
+            },
+        };
+        log_event(EventKind::Dm, &s);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct ManageDms<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    user: &'a str,
+    chan: &'a str,
+    world: &'a mut World,
+    action: &'a str,
+    nick: &'a str,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ManageDms<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        let format = "add nick | remove nick | transfer nick | list";
+        let action = match args.get(1).map(|s| s.to_ascii_lowercase()) {
+            Some(ref s) if s == "add" && args.len() == 3 => "add",
+            Some(ref s) if s == "remove" && args.len() == 3 => "remove",
+            Some(ref s) if s == "transfer" && args.len() == 3 => "transfer",
+            Some(ref s) if s == "list" && args.len() == 2 => "list",
+            _ => return Err(incorrect_format(chan, ".dm", format)),
+        };
+        if action != "list" {
+            try!(permissions_test(user, chan, world));
+        } else if !world.game_exists(chan) {
+            return Err(Propagated(format!("{}", chan), format!("There is no game in {}.", chan)));
+        }
+        let nick = args.get(2).cloned().unwrap_or("");
+        Ok(Box::new(ManageDms { bot: bot, user: user, chan: chan, world: world, action: action, nick: nick }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ManageDms<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let game = try!(self.world.get_game(self.chan));
+        let name = game.name.clone();
+        let res = match self.action {
+            "add" => game.add_dm(self.nick).map(|_| format!("{} is now a DM of {}.", self.nick, name)),
+            "remove" => game.remove_dm(self.user, self.nick).map(|_| format!("{} is no longer a DM of {}.", self.nick, name)),
+            "transfer" => game.transfer_dm(self.user, self.nick)
+                .map(|_| format!("{} handed {} over to {}.", self.user, name, self.nick)),
+            _ => {
+                let s = format!("DMs of {}: {}.", name, game.dms.join(", "));
+                return as_io(self.bot.send_privmsg(self.chan, &s));
+            },
+        };
+        let s = match res {
+            Ok(s) => s,
+            Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", self.chan), msg)),
+            Err(e) => return Err(e),
+        };
+        log_event(EventKind::Dm, &s);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct EndCampaign<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> EndCampaign<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 1 { return Err(incorrect_format(chan, ".endcampaign", "")); }
+        try!(permissions_test(user, chan, world));
+        Ok(Box::new(EndCampaign { bot: bot, chan: chan, world: world }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for EndCampaign<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let mut s = {
+            let game = try!(self.world.get_game(self.chan));
+            let mut s = format!("{} has ended.", game.name);
+            // A running session is over too, so its seed can be revealed.
+            if let Some(ref session) = game.session {
+                s.push_str(&format!(" The seed was {}.", session.seed));
+            }
+            s
+        };
+        log_event(EventKind::Dm, &s);
+        {
+            // Ended before anything is saved, so that the game can't be resumed with a seed that's
+            // already been revealed.
+            let chan = self.chan;
+            let game = try!(self.world.get_game(chan));
+            if game.session.is_some() {
+                try!(game.end_session().map_err(|e| Propagated(format!("{}", chan), format!("{}", e))));
+            }
+        }
+        let (game, players, path) = try!(self.world.end_game(self.chan));
+        s.push_str(&format!(" It was archived to {}. Thanks for playing!", path));
+        try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+        for &(ref nick, _) in players.iter() {
+            try!(as_io(self.bot.send_kick(self.chan, nick, "The campaign has ended.")));
+        }
+        as_io(self.bot.send(Command::PART(self.chan.to_owned(), Some(format!("{} has ended.", game.name)))))
+    }
+}
+
+pub struct ListRolls<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    name: Option<&'a str>,
+    count: usize,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> ListRolls<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 3 {
+            return Err(incorrect_format(chan, ".rolls", "[name] [count]"));
+        } else if !world.game_exists(chan) {
+            return Err(Propagated(format!("{}", chan), format!("There is no game in {}.", chan)));
+        }
+        // A lone argument is the count if it's a number and the name otherwise.
+        let (name, count) = match (args.get(1), args.get(2)) {
+            (Some(name), Some(count)) => (Some(*name), Some(*count)),
+            (Some(arg), None) if arg.parse::<usize>().is_ok() => (None, Some(*arg)),
+            (name, _) => (name.cloned(), None),
+        };
+        let count = match count.map(|s| s.parse::<usize>()) {
+            None => 5,
+            Some(Ok(count)) if count > 0 && count <= MAX_ROLLS_LISTED => count,
+            Some(Ok(_)) => return Err(Propagated(
+                format!("{}", chan), format!("You can list between 1 and {} rolls.", MAX_ROLLS_LISTED)
+            )),
+            Some(Err(_)) => return Err(incorrect_format(chan, ".rolls", "[name] [count]")),
+        };
+        Ok(Box::new(ListRolls { bot: bot, chan: chan, world: world, name: name, count: count }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for ListRolls<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let game = try!(self.world.get_game(self.chan));
+        let dice = game.dice.borrow();
+        let rolls = dice.recent(self.name, self.count);
+        if rolls.is_empty() {
+            let s = match self.name {
+                Some(name) => format!("No rolls have been made by or for {} in {}.", name, game.name),
+                None => format!("No rolls have been made in {}.", game.name),
+            };
+            return as_io(self.bot.send_privmsg(self.chan, &s));
+        }
+        for roll in rolls.iter() {
+            try!(as_io(self.bot.send_privmsg(self.chan, &format!("{}", roll))));
+        }
+        Ok(())
+    }
+}
+
+pub struct Export<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    format: Format,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Export<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World) -> BotResult<Box<Functionality + 'a>> {
+        if args.len() > 2 { return Err(incorrect_format(chan, ".export", "[markdown|html]")); }
+        try!(permissions_test(user, chan, world));
+        let format = match args.get(1).map(|s| Format::to_format(s)) {
+            None => Format::Markdown,
+            Some(Some(format)) => format,
+            Some(None) => return Err(incorrect_format(chan, ".export", "[markdown|html]")),
+        };
+        Ok(Box::new(Export { bot: bot, chan: chan, world: world, format: format }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Export<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        let game = try!(self.world.get_game(self.chan));
+        let path = try!(as_io(game.journal.borrow().export(self.format)));
+        let s = format!("The journal for {} was exported to {}.", game.name, path);
+        as_io(self.bot.send_privmsg(self.chan, &s))
+    }
+}
+
+pub struct Undo<'a, T: IrcRead, U: IrcWrite> {
+    bot: &'a ServerExt<'a, T, U>,
+    chan: &'a str,
+    world: &'a mut World,
+    count: usize,
+    redo: bool,
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Undo<'a, T, U> {
+    pub fn new(bot: &'a ServerExt<'a, T, U>, user: &'a str, chan: &'a str, args: Vec<&'a str>, world: &'a mut World, redo: bool) -> BotResult<Box<Functionality + 'a>> {
+        let cmd = if redo { ".redo" } else { ".undo" };
+        if args.len() > 2 { return Err(incorrect_format(chan, cmd, "[count]")); }
+        try!(permissions_test(user, chan, world));
+        let count = match args.get(1).map(|s| s.parse::<usize>()) {
+            None => 1,
+            Some(Ok(n)) if n > 0 && n <= MAX_HISTORY => n,
+            _ => return Err(Propagated(
+                format!("{}", chan), format!("You can {} between 1 and {} actions.", &cmd[1..], MAX_HISTORY)
+            )),
+        };
+        Ok(Box::new(Undo { bot: bot, chan: chan, world: world, count: count, redo: redo }))
+    }
+}
+
+impl<'a, T: IrcRead, U: IrcWrite> Functionality for Undo<'a, T, U> {
+    fn do_func(&mut self) -> BotResult<()> {
+        for i in 0..self.count {
+            let revision = if self.redo { self.world.redo(self.chan) } else { self.world.undo(self.chan) };
+            let s = match revision {
+                Ok(Some((ref revision, ref skipped))) if skipped.is_empty() => format!(
+                    "{} {} by {}.", if self.redo { "Redid" } else { "Undid" }, revision.command, revision.user
+                ),
+                Ok(Some((ref revision, ref skipped))) => format!(
+                    "Partly {} {} by {}. Couldn't put back: {}.",
+                    if self.redo { "redid" } else { "undid" }, revision.command, revision.user, skipped.join(", ")
+                ),
+                Ok(None) if i == 0 => {
+                    let s = format!("There is nothing to {} in {}.", if self.redo { "redo" } else { "undo" }, self.chan);
+                    return as_io(self.bot.send_privmsg(self.chan, &s));
+                },
+                Ok(None) => break,
+                Err(InvalidInput(msg)) => return Err(Propagated(format!("{}", self.chan), msg)),
+                Err(e) => return Err(e),
+            };
+            log_event(EventKind::Dm, &s);
+            try!(as_io(self.bot.send_privmsg(self.chan, &s)));
+        }
+        Ok(())
+    }
+}
+
 pub struct PrivateRoll<'a, T: IrcRead, U: IrcWrite> {
     bot: &'a ServerExt<'a, T, U>,
     user: &'a str,
@@ -82,20 +587,67 @@ impl<'a, T: IrcRead, U: IrcWrite> Functionality for SaveAll<'a, T, U> {
 #[cfg(test)]
 mod test {
     use std::borrow::ToOwned;
-    use func::test::test_helper;
+    use std::fs::{File, remove_dir_all};
+    use std::io::prelude::*;
+    use data::BotResult;
+    use data::dice::{DiceSource, roll_die, use_source};
+    use data::game::Game;
+    use data::monster::Monster;
+    use data::player::Player;
+    use data::world::World;
+    use func::test::{test_helper, test_helper_scripted};
+
+    // A game run by test with test2 logged in.
+    fn hook(world: &mut World) -> BotResult<()> {
+        world.add_game("Dungeons and Tests", "test", "#test");
+        let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+        world.add_user("test2", "#test", p);
+        Ok(())
+    }
 
     #[test]
     fn create_success() {
-        let data = test_helper(":test!test@test PRIVMSG test :create #test Dungeons and Tests\r\n",
+        let _ = remove_dir_all("games/create-tests/");
+        let data = test_helper(":test!test@test PRIVMSG test :create #test Create Tests\r\n",
                                |_| { Ok(()) }).unwrap();
         let mut exp = "JOIN #test\r\n".to_string();
-        exp.push_str("TOPIC #test :Dungeons and Tests\r\n");
+        exp.push_str("TOPIC #test :Create Tests\r\n");
+        exp.push_str("MODE #test +i\r\n");
+        exp.push_str("PRIVMSG test :Campaign created named Create Tests.\r\n");
+        exp.push_str("INVITE test #test\r\n");
+        assert_eq!(data, exp);
+        assert!(Game::load("Create Tests").unwrap().is_dm("test"));
+    }
+
+    #[test]
+    fn create_resumed() {
+        let mut g = Game::new("Resume Tests", "test2");
+        g.add_dm("test").unwrap();
+        g.purse.gp = 7;
+        g.save().unwrap();
+        let mut input = ":test!test@test PRIVMSG test :create #test resume tests\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.purse party\r\n");
+        input.push_str(":test3!test@test PRIVMSG test :create #test2 Resume Tests\r\n");
+        let data = test_helper(&input, |_| { Ok(()) }).unwrap();
+        let mut exp = "JOIN #test\r\n".to_string();
+        exp.push_str("TOPIC #test :Resume Tests\r\n");
         exp.push_str("MODE #test +i\r\n");
-        exp.push_str("PRIVMSG test :Campaign created named Dungeons and Tests.\r\n");
+        exp.push_str("PRIVMSG test :Campaign resumed named Resume Tests.\r\n");
         exp.push_str("INVITE test #test\r\n");
+        exp.push_str("PRIVMSG #test :party has 7 gp.\r\n");
+        exp.push_str("PRIVMSG test3 :A campaign named Resume Tests already exists, and only its DMs can resume it.\r\n");
         assert_eq!(data, exp);
     }
 
+    #[test]
+    fn create_failed_similar_title() {
+        Game::new("Similar & Tests", "test").save().unwrap();
+        let data = test_helper(":test!test@test PRIVMSG test :create #test Similar - Tests\r\n",
+                               |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :Similar - Tests is too close to the name of the campaign Similar & Tests. Please choose another.\r\n"));
+        assert_eq!(Game::load("Similar & Tests").unwrap().name, "Similar & Tests");
+    }
+
     #[test]
     fn create_failed_already_exists() {
         let data = test_helper(":test!test@test PRIVMSG test :create #test Dungeons and Tests\r\n",
@@ -113,6 +665,12 @@ mod test {
         assert_eq!(data[..25].to_owned(), format!("PRIVMSG test :You rolled "));
     }
 
+    #[test]
+    fn private_roll_scripted() {
+        let data = test_helper_scripted(":test!test@test PRIVMSG test :roll\r\n", vec![17], |_| { Ok(()) }).unwrap();
+        assert_eq!(data, format!("PRIVMSG test :You rolled 17.\r\n"));
+    }
+
     #[test]
     fn save_all_from_owner() {
         let data = test_helper(":test!test@test PRIVMSG test :saveall\r\n", |_| { Ok(()) }).unwrap();
@@ -124,4 +682,405 @@ mod test {
         let data = test_helper(":test2!test@test PRIVMSG test :saveall\r\n", |_| { Ok(()) }).unwrap();
         assert_eq!(data, format!("PRIVMSG test2 :You must own the bot to do that!\r\n"));
     }
+
+    #[test]
+    fn rules_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rules\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :Dungeons and Tests uses 5e: Strength (1-30), Dexterity (1-30), ".to_string();
+        exp.push_str("Constitution (1-30), Wisdom (1-30), Intellect (1-30), Charisma (1-30). Roll a d20 plus (value - 10) / 2.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn rules_set_success() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rules cthulhu\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :Dungeons and Tests uses coc: STR (1-99), CON (1-99), SIZ (1-99), DEX (1-99), ".to_string();
+        exp.push_str("APP (1-99), INT (1-99), POW (1-99), EDU (1-99). Roll a d100 at or under the stat.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn rules_set_failed_players_logged_in() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rules coc\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                let p = Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12);
+                world.add_user("test2", "#test", p);
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :The rules can't be changed while players are logged in.\r\n"));
+    }
+
+    #[test]
+    fn rules_set_failed_unknown() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rules test2\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :test2 is not a known rule system.\r\n"));
+    }
+
+    #[test]
+    fn seed_success() {
+        let mut input = ":test!test@test PRIVMSG #test :.seed\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.seed 42\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.seed random\r\n");
+        let data = test_helper(&input,
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        let mut exp = "PRIVMSG #test :Dungeons and Tests rolls randomly.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Dungeons and Tests rolls with seed 42.\r\n");
+        exp.push_str("PRIVMSG #test :Dungeons and Tests rolls randomly.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn seed_reproduces_rolls() {
+        let mut input = ":test!test@test PRIVMSG #test :.seed 1234\r\n".to_string();
+        for _ in 0..10 {
+            input.push_str(":test2!test@test PRIVMSG #test :.roll\r\n");
+        }
+        let first = test_helper(&input, hook).unwrap();
+        let second = test_helper(&input, hook).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.lines().count(), 11);
+    }
+
+    #[test]
+    fn seed_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.seed 42\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn seed_failed_invalid() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.seed -3\r\n",
+            |world| {
+                world.add_game("Dungeons and Tests", "test", "#test");
+                Ok(())
+            }
+        ).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :-3 is not a valid seed. Seeds must be non-negative integers.\r\n"));
+    }
+
+    // Drops the times from roll listings, which depend on when the test runs.
+    fn strip_times(data: &str) -> String {
+        let lines: Vec<_> = data.split("\r\n").map(|line| match line.find(" :[") {
+            Some(i) => format!("{} :{}", &line[..i], &line[i + 13..]),
+            None => line.to_owned(),
+        }).collect();
+        lines.join("\r\n")
+    }
+
+    #[test]
+    fn rolls_success() {
+        let mut input = ":test2!test@test PRIVMSG #test :.roll str\r\n".to_string();
+        input.push_str(":test2!test@test PRIVMSG test :roll\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.rolls\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.rolls test2 1\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.rolls test\r\n");
+        let data = test_helper_scripted(&input, vec![15, 7], hook).unwrap();
+        let mut exp = "PRIVMSG #test :test2 rolled 16.\r\n".to_string();
+        exp.push_str("PRIVMSG test2 :You rolled 7.\r\n");
+        exp.push_str("PRIVMSG #test :test2 rolled d20 (Strength) for test2: [15]+1 = 16\r\n");
+        exp.push_str("PRIVMSG #test :test2 rolled d20 (private)\r\n");
+        exp.push_str("PRIVMSG #test :test2 rolled d20 (private)\r\n");
+        exp.push_str("PRIVMSG #test :No rolls have been made by or for test in Dungeons and Tests.\r\n");
+        assert_eq!(strip_times(&data), exp);
+    }
+
+    #[test]
+    fn rolls_by_name_or_count() {
+        let mut input = ":test2!test@test PRIVMSG #test :.roll str\r\n".to_string();
+        input.push_str(":test2!test@test PRIVMSG test :roll\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.rolls 1\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.rolls test2\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.rolls test2 many\r\n");
+        let data = test_helper_scripted(&input, vec![15, 7], hook).unwrap();
+        let mut exp = "PRIVMSG #test :test2 rolled 16.\r\n".to_string();
+        exp.push_str("PRIVMSG test2 :You rolled 7.\r\n");
+        exp.push_str("PRIVMSG #test :test2 rolled d20 (private)\r\n");
+        exp.push_str("PRIVMSG #test :test2 rolled d20 (Strength) for test2: [15]+1 = 16\r\n");
+        exp.push_str("PRIVMSG #test :test2 rolled d20 (private)\r\n");
+        exp.push_str("PRIVMSG #test :Incorrect format for .rolls. Format is:\r\n");
+        exp.push_str("PRIVMSG #test :.rolls [name] [count]\r\n");
+        assert_eq!(strip_times(&data), exp);
+    }
+
+    #[test]
+    fn rolls_failed_invalid_count() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.rolls test2 50\r\n", hook).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :You can list between 1 and 20 rolls.\r\n"));
+    }
+
+    #[test]
+    fn session_success() {
+        let mut input = ":test!test@test PRIVMSG #test :.session start\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.seed 5\r\n");
+        for _ in 0..5 {
+            input.push_str(":test2!test@test PRIVMSG #test :.roll\r\n");
+        }
+        input.push_str(":test!test@test PRIVMSG #test :.session end\r\n");
+        let data = test_helper(&input, hook).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        let commitment = &lines[0]["PRIVMSG #test :Session started. The dice are committed to SHA-512 ".len()..lines[0].len() - 1];
+        assert_eq!(lines[1], "PRIVMSG #test :The seed can't be changed during a session.");
+        assert!(lines[7].starts_with("PRIVMSG #test :Session ended after 5 rolls. The seed was "));
+        assert!(lines[7].ends_with(&format!(", committed as SHA-512 {}.", commitment)[..]));
+        let seed: usize = lines[7]["PRIVMSG #test :Session ended after 5 rolls. The seed was ".len()..].split(',')
+            .next().unwrap().parse().unwrap();
+        assert_eq!(Game::password_hash(&seed.to_string()).unwrap(), commitment);
+        // Anyone with the seed can replay the session's rolls.
+        use_source(Some(DiceSource::seeded(seed).shared()));
+        for line in lines[2..7].iter() {
+            assert_eq!(*line, format!("PRIVMSG #test :test2 rolled {}.", roll_die(20)));
+        }
+    }
+
+    #[test]
+    fn export_success() {
+        let mut input = ":test2!test@test PRIVMSG #test :.roll str\r\n".to_string();
+        input.push_str(":test2!test@test PRIVMSG #test :.move 1 2\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.defense test2 resist fire\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.damage test2 5\r\n");
+        input.push_str(":test2!test@test PRIVMSG test :logout\r\n");
+        input.push_str(":test!test@test PRIVMSG test :addmonster #test Goblin 7 30 8 14 10 10 8 8\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.export\r\n");
+        let data = test_helper_scripted(&input, vec![15], |world| {
+            world.add_game("Export Tests", "test", "#test");
+            world.add_user("test2", "#test", Player::create_test("test2", "test", 20, 30, 12, 12, 12, 12, 12, 12));
+            Ok(())
+        }).unwrap();
+        let last = data.split("\r\n").filter(|l| !l.is_empty()).last().unwrap();
+        let prefix = "PRIVMSG #test :The journal for Export Tests was exported to ";
+        assert!(last.starts_with(prefix) && last.ends_with(".md."));
+        let path = &last[prefix.len()..last.len() - 1];
+        assert!(path.starts_with("sessions/export-tests-"));
+        let mut markdown = String::new();
+        File::open(path).unwrap().read_to_string(&mut markdown).unwrap();
+        // Drops the times, which depend on when the test runs.
+        let events: Vec<_> = markdown.lines().skip(2).map(|line| &line[13..]).collect();
+        assert_eq!(events, vec![
+            "**roll** test2 rolled d20 (Strength) for test2: \\[15\\]+1 = 16",
+            "**move** test2 moved to (1, 2).",
+            "**dm** test2 (test2) is now resistant to fire damage.",
+            "**damage** test2 took 5 damage and has 15 health remaining.",
+            "**logout** test2 logged out.",
+            "**dm** Monster (Goblin) has been created as @0.",
+        ]);
+    }
+
+    #[test]
+    fn export_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.export html\r\n", hook).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn export_failed_invalid_format() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.export pdf\r\n", hook).unwrap();
+        let mut exp = "PRIVMSG #test :Incorrect format for .export. Format is:\r\n".to_string();
+        exp.push_str("PRIVMSG #test :.export [markdown|html]\r\n");
+        assert_eq!(data, exp);
+    }
+
+    fn monster_hook(world: &mut World) -> BotResult<()> {
+        world.add_game("Dungeons and Tests", "test", "#test");
+        world.add_monster(Monster::create("Zombie", 20, 30, 12, 12, 12, 12, 12, 12), "#test");
+        Ok(())
+    }
+
+    #[test]
+    fn undo_success() {
+        let mut input = ":test!test@test PRIVMSG #test :.damage @0 50\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.undo\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.damage @0 5\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.redo\r\n");
+        let data = test_helper(&input, monster_hook).unwrap();
+        let mut exp = "PRIVMSG #test :Zombie (@0) has fallen unconscious.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Undid .damage @0 50 by test.\r\n");
+        exp.push_str("PRIVMSG #test :Zombie (@0) took 5 damage and has 15 health remaining.\r\n");
+        exp.push_str("PRIVMSG #test :There is nothing to redo in #test.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn undo_redo_many() {
+        let mut input = ":test!test@test PRIVMSG test :addmonster #test Goblin 7 30 8 14 10 8 10 8\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.move @1 2 2\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.roll @1\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.undo 5\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.redo\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.move @1 1 1\r\n");
+        let data = test_helper_scripted(&input, vec![10], monster_hook).unwrap();
+        let mut exp = "PRIVMSG test :Monster (Goblin) has been created as @1.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Goblin (@1) moved to Position(2, 2).\r\n");
+        exp.push_str("PRIVMSG #test :Goblin rolled 10.\r\n");
+        exp.push_str("PRIVMSG #test :Undid .move @1 2 2 by test.\r\n");
+        exp.push_str("PRIVMSG #test :Undid addmonster #test Goblin 7 30 8 14 10 8 10 8 by test.\r\n");
+        exp.push_str("PRIVMSG #test :Redid addmonster #test Goblin 7 30 8 14 10 8 10 8 by test.\r\n");
+        exp.push_str("PRIVMSG #test :Goblin (@1) moved to Position(1, 1).\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn snapshot_success() {
+        let mut input = ":test!test@test PRIVMSG #test :.snapshot create boss\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.damage @0 15\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.snapshot restore Boss\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.damage @0 5\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.snapshot create boss\r\n");
+        input.push_str(":test2!test@test PRIVMSG #test :.snapshot list\r\n");
+        let data = test_helper(&input, monster_hook).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG #test :Saved snapshot boss.");
+        assert_eq!(lines[1], "PRIVMSG #test :Zombie (@0) took 15 damage and has 5 health remaining.");
+        assert_eq!(lines[2], "PRIVMSG #test :Restored snapshot Boss.");
+        assert_eq!(lines[3], "PRIVMSG #test :Zombie (@0) took 5 damage and has 15 health remaining.");
+        assert_eq!(lines[4], "PRIVMSG #test :Updated snapshot boss.");
+        assert!(lines[5].starts_with("PRIVMSG #test :Snapshots of Dungeons and Tests: boss ("));
+    }
+
+    #[test]
+    fn snapshot_restore_logged_out() {
+        let mut input = ":test!test@test PRIVMSG #test :.snapshot create boss\r\n".to_string();
+        input.push_str(":test2!test@test PRIVMSG test :logout\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.snapshot restore boss\r\n");
+        let data = test_helper(&input, hook).unwrap();
+        assert!(data.ends_with(
+            "PRIVMSG #test :Restored snapshot boss. Left as they are since they're no longer logged in: test2.\r\n"
+        ));
+    }
+
+    #[test]
+    fn snapshot_at_session_start() {
+        let mut input = ":test!test@test PRIVMSG #test :.snapshot list\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.session start\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.snapshot list\r\n");
+        let data = test_helper(&input, hook).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert_eq!(lines[0], "PRIVMSG #test :Dungeons and Tests has no snapshots.");
+        assert!(lines[2].starts_with("PRIVMSG #test :Snapshots of Dungeons and Tests: session-"));
+    }
+
+    #[test]
+    fn snapshot_failed() {
+        let mut input = ":test2!test@test PRIVMSG #test :.snapshot create boss\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.snapshot create big.boss\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.snapshot restore dragon\r\n");
+        let data = test_helper(&input, hook).unwrap();
+        let mut exp = "PRIVMSG test2 :You must be the DM to do that!\r\n".to_string();
+        exp.push_str("PRIVMSG #test :Snapshot names must be up to 20 letters, numbers, dashes or underscores.\r\n");
+        exp.push_str("PRIVMSG #test :There is no snapshot named dragon.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn undo_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.undo\r\n", monster_hook).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn undo_failed_invalid_count() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.undo 0\r\n", monster_hook).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :You can undo between 1 and 50 actions.\r\n"));
+    }
+
+    #[test]
+    fn dm_success() {
+        let mut input = ":test!test@test PRIVMSG #test :.dm add test2\r\n".to_string();
+        input.push_str(":test2!test@test PRIVMSG #test :.undo\r\n");
+        input.push_str(":test2!test@test PRIVMSG #test :.dm remove test\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.dm remove test\r\n");
+        input.push_str(":test2!test@test PRIVMSG #test :.dm transfer test3\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.dm list\r\n");
+        let data = test_helper(&input, hook).unwrap();
+        let mut exp = "PRIVMSG #test :test2 is now a DM of Dungeons and Tests.\r\n".to_string();
+        exp.push_str("PRIVMSG #test :There is nothing to undo in #test.\r\n");
+        exp.push_str("PRIVMSG #test :Only test can remove themselves as a DM of Dungeons and Tests.\r\n");
+        exp.push_str("PRIVMSG #test :test is no longer a DM of Dungeons and Tests.\r\n");
+        exp.push_str("PRIVMSG #test :test2 handed Dungeons and Tests over to test3.\r\n");
+        exp.push_str("PRIVMSG #test :DMs of Dungeons and Tests: test3.\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn dm_failed() {
+        let mut input = ":test2!test@test PRIVMSG #test :.dm add test2\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.dm remove test\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.dm add test\r\n");
+        input.push_str(":test!test@test PRIVMSG #test :.dm promote test2\r\n");
+        let data = test_helper(&input, hook).unwrap();
+        let mut exp = "PRIVMSG test2 :You must be the DM to do that!\r\n".to_string();
+        exp.push_str("PRIVMSG #test :test is the only DM of Dungeons and Tests, so they can only hand it over.\r\n");
+        exp.push_str("PRIVMSG #test :test is already a DM of Dungeons and Tests.\r\n");
+        exp.push_str("PRIVMSG #test :Incorrect format for .dm. Format is:\r\n");
+        exp.push_str("PRIVMSG #test :.dm add nick | remove nick | transfer nick | list\r\n");
+        assert_eq!(data, exp);
+    }
+
+    #[test]
+    fn end_campaign_success() {
+        let mut input = ":test!test@test PRIVMSG #test :.endcampaign\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.rules\r\n");
+        let data = test_helper(&input, hook).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        let prefix = "PRIVMSG #test :Dungeons and Tests has ended. It was archived to ";
+        assert!(lines[0].starts_with(prefix) && lines[0].ends_with(". Thanks for playing!"));
+        let path = &lines[0][prefix.len()..lines[0].len() - ". Thanks for playing!".len()];
+        assert!(path.starts_with("campaigns/dungeons-and-tests-"));
+        let mut archive = String::new();
+        File::open(path).unwrap().read_to_string(&mut archive).unwrap();
+        assert!(archive.contains("\"players\":[\"test2\"]"));
+        assert_eq!(lines[1], "KICK #test test2 :The campaign has ended.");
+        assert_eq!(lines[2], "PART #test :Dungeons and Tests has ended.");
+        assert_eq!(lines[3], "PRIVMSG #test :There is no game in #test.");
+    }
+
+    #[test]
+    fn end_campaign_ends_session() {
+        let mut input = ":test!test@test PRIVMSG #test :.session start\r\n".to_string();
+        input.push_str(":test!test@test PRIVMSG #test :.endcampaign\r\n");
+        let data = test_helper(&input, |world| {
+            world.add_game("End Session Tests", "test", "#test");
+            Ok(())
+        }).unwrap();
+        let lines: Vec<_> = data.split("\r\n").collect();
+        assert!(lines[1].starts_with("PRIVMSG #test :End Session Tests has ended. The seed was "));
+        assert!(Game::load("End Session Tests").unwrap().session.is_none());
+    }
+
+    #[test]
+    fn end_campaign_failed_not_dm() {
+        let data = test_helper(":test2!test@test PRIVMSG #test :.endcampaign\r\n", hook).unwrap();
+        assert_eq!(data, format!("PRIVMSG test2 :You must be the DM to do that!\r\n"));
+    }
+
+    #[test]
+    fn session_failed_not_running() {
+        let data = test_helper(":test!test@test PRIVMSG #test :.session end\r\n", hook).unwrap();
+        assert_eq!(data, format!("PRIVMSG #test :There is no session running in Dungeons and Tests.\r\n"));
+    }
 }
@@ -24,5 +24,8 @@ fn main() {
         let mut token_store = Vec::new();
         func::process_world(&server, &source, &message.command, &args,
                             &mut token_store, &mut world).unwrap();
+        if let Err(e) = world.save_draws() {
+            println!("Failed to save session rolls: {}", e);
+        }
     }
 }